@@ -0,0 +1,962 @@
+//! Benchmark runner: executes math program instructions over an input set and
+//! reports the compute units measured by each benchmark as CSV and Markdown
+//!
+//! Build the program first, so the runner measures the SBF binary:
+//!
+//! ```bash
+//! cargo build-sbf
+//! SBF_OUT_DIR=target/deploy cargo run --example bench_report -- \
+//!     [--inputs <file>] [--filter <name>]... [--csv <file>] [--markdown <file>]
+//! ```
+//!
+//! Each line of an input file names an instruction builder followed by its
+//! arguments, e.g. `precise_muldiv 42 84 7 ceil`, `sweep sqrt_u64 geometric 1 2 32`
+//! or `batch u64_multiply 42 84 2 3`; `#` starts a comment. Without
+//! `--inputs`, a default set covering every builder is used. `--filter` keeps
+//! the cases whose builder name contains the given text. The Markdown table is
+//! printed to stdout unless `--markdown` is given.
+
+use {
+    solana_program::instruction::Instruction,
+    solana_program_test::{tokio, *},
+    solana_sdk::{signature::Signer, transaction::Transaction},
+    spl_math_example::{
+        bench::{BenchRecord, HeapUsage},
+        d18, id,
+        instruction::{
+            self, BatchOperation, BlackScholesInputs, Compounding, CurveOperation, D18Operation,
+            FixedPointOperation, FixedPointType, InterestOperation, NormalOperation, OptionKind,
+            PreciseOperation, RoundingMode, SignedOperation, SignedValue, SqrtAlgorithm,
+            StableSwapOperation, SweepInputs, SweepOperation, TradeFee, UnsignedInteger,
+            WeightedOperation, WideOperation,
+        },
+        processor::process_instruction,
+        uint::{U192, U256},
+    },
+    std::{env, fmt::Write, fs, process::exit, str::FromStr},
+};
+
+const DEFAULT_INPUTS: &str = "\
+noop
+precise_sqrt 18446744073709551615 newton
+precise_sqrt 4294967295 cordic
+precise_sqrt_array 1000 100 newton
+precise_sqrt_array 1000 100 cordic
+precise_muldiv 42 84 7
+precise_muldiv 43 85 7 ceil
+precise_muldiv 43 85 7 half_even
+sqrt_u64 18446744073709551615
+sqrt_u128 18446744073709551615
+sqrt_u128 340282366920938463463374607431768211455
+u64_multiply 42 84
+u64_divide 3 1
+f32_multiply 1.5 2
+f32_divide 3 1.5
+f32_exponentiate 4 2
+f32_natural_log 2.7182817
+f32_normal_cdf 0
+f64_pow 50 10.5
+u128_multiply 18446744073709551615 18446744073709551615
+u128_divide 340282366920938463463374607431768211455 4931628506100557441498182716402437847
+f64_multiply 4398046511104 0.0001
+f64_divide 4398046511104 420420.6969
+checked_ceil_div 100 7
+precise_arithmetic add 42000000000000 84000000000000
+precise_arithmetic mul 42000000000000 84000000000000
+precise_arithmetic div 42000000000000 84000000000000
+precise_arithmetic pow 2000000000000 64
+precise_arithmetic ceiling 2250000000000
+signed_arithmetic precise add 42000000000000 -84000000000000
+signed_arithmetic precise sub 42000000000000 84000000000000
+signed_arithmetic precise mul -42000000000000 84000000000000
+signed_arithmetic precise div -42000000000000 -84000000000000
+signed_arithmetic precise pow -2000000000000 63
+signed_arithmetic precise floor -2250000000000
+signed_arithmetic d18 sub 42000000000000000000 84000000000000000000
+signed_arithmetic d18 mul -42000000000000000000 84000000000000000000
+signed_arithmetic d18 div -42000000000000000000 -84000000000000000000
+precise_d18_arithmetic from_decimal 1234567890.123456789
+precise_d18_arithmetic mul 42 84.5
+precise_d18_arithmetic div 2 3
+precise_d18_arithmetic mul_floor 42 84.5
+precise_d18_arithmetic div_ceil 2 3
+precise_d18_arithmetic pow 1.5 64
+precise_d18_arithmetic to_u128 18446744073709551614.5
+wide_arithmetic u256_multiply 18446744073709551615 18446744073709551615
+wide_arithmetic u256_divide 340282366920938463463374607431768211455 4931628506100557441498182716402437847
+wide_arithmetic u256_mul_div 340282366920938463463374607431768211455 340282366920938463463374607431768211455 4931628506100557441498182716402437847
+wide_arithmetic u256_sqrt 340282366920938463463374607431768211455
+wide_arithmetic u256_shift_left 1 200
+wide_arithmetic u256_overflowing_mul 340282366920938463463374607431768211455 340282366920938463463374607431768211455
+wide_arithmetic u192_multiply 18446744073709551615 18446744073709551615
+fixed_point_math precise exp 10
+fixed_point_math d18 exp 10
+fixed_point_math d18 exp_neg 10
+fixed_point_math precise ln 42
+fixed_point_math d18 ln 42
+fixed_point_math d18 log2 42
+fixed_point_math precise pow 2 0.5
+fixed_point_math d18 pow 2 0.5
+fixed_point_math precise pow_down 0.8 0.25
+fixed_point_math precise pow_up 0.8 0.25
+normal_distribution cdf 1.96
+normal_distribution cdf_neg 1.96
+normal_distribution pdf 1
+normal_distribution inverse_cdf 0.975
+black_scholes call f64 100 100 0.05 0.2 1
+black_scholes put f64 100 100 0.05 0.2 1
+black_scholes call precise 100 100 0.05 0.2 1
+black_scholes put precise 100 100 0.05 0.2 1
+curve swap_exact_in 1000000 9223372036854775807 6148914691236517205 25 10000
+curve swap_exact_out 1000000 9223372036854775807 6148914691236517205 25 10000
+curve deposit 1000 1000000 9223372036854775807 6148914691236517205
+curve withdraw 1000 1000000 9223372036854775807 6148914691236517205
+stable_swap d 100 255 1000000000000000000000 10000000000000000000
+stable_swap d 100 255 1000000000000 2000000000000 3000000000000 4000000000000
+stable_swap y 200 255 0 1 1000001000000 1000000000000 2000000000000 3000000000000
+weighted_pool out_given_in 1000000000000 80 3000000000000 20 1000000000
+weighted_pool in_given_out 1000000000000 80 3000000000000 20 1000000000
+compound_interest accrue per_slot 0.1 1 down
+compound_interest accrue per_year 0.1 1 down
+compound_interest accrue continuous 0.1 1 down
+compound_interest apr_to_apy 0.12 12 up
+compound_interest apy_to_apr 0.126825030132 12
+integer_sqrt u8 255
+integer_sqrt u16 65535
+integer_sqrt u32 4294967295
+integer_sqrt u64 18446744073709551615
+integer_sqrt u128 340282366920938463463374607431768211455
+integer_sqrt u256 115792089237316195423570985008687907853269984665640564039457584007913129639935
+sweep precise_sqrt_newton geometric 1 16 16
+sweep precise_sqrt_cordic geometric 1 16 16
+batch u64_multiply 42 84 42 84 42 84 42 84 42 84 42 84 42 84 42 84
+batch f64_pow 50 10.5 50 10.5 50 10.5 50 10.5 50 10.5 50 10.5 50 10.5 50 10.5
+";
+
+#[derive(Default)]
+struct Options {
+    inputs: Option<String>,
+    filters: Vec<String>,
+    csv: Option<String>,
+    markdown: Option<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", flag))
+        };
+        match flag.as_str() {
+            "--inputs" => options.inputs = Some(value()?),
+            "--filter" => options.filters.push(value()?),
+            "--csv" => options.csv = Some(value()?),
+            "--markdown" => options.markdown = Some(value()?),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    Ok(options)
+}
+
+struct Case {
+    input: String,
+    builder: String,
+    instruction: Instruction,
+}
+
+fn arg<T: FromStr>(args: &[&str], index: usize) -> Result<T, String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("missing argument {}", index + 1))?;
+    arg.parse()
+        .map_err(|_| format!("invalid argument {}: {}", index + 1, arg))
+}
+
+fn algorithm(args: &[&str], index: usize) -> Result<SqrtAlgorithm, String> {
+    match args.get(index).copied() {
+        Some("newton") => Ok(SqrtAlgorithm::Newton),
+        Some("cordic") => Ok(SqrtAlgorithm::Cordic),
+        other => Err(format!("invalid square root algorithm: {:?}", other)),
+    }
+}
+
+/// Rounding of a muldiv, `Floor` if not given
+fn rounding(args: &[&str], index: usize) -> Result<RoundingMode, String> {
+    match args.get(index).copied() {
+        None | Some("floor") => Ok(RoundingMode::Floor),
+        Some("ceil") => Ok(RoundingMode::Ceil),
+        Some("half_up") => Ok(RoundingMode::HalfUp),
+        Some("half_even") => Ok(RoundingMode::HalfEven),
+        Some("toward_zero") => Ok(RoundingMode::TowardZero),
+        other => Err(format!("invalid rounding mode: {:?}", other)),
+    }
+}
+
+fn sweep_operation(args: &[&str], index: usize) -> Result<SweepOperation, String> {
+    match args.get(index).copied() {
+        Some("sqrt_u64") => Ok(SweepOperation::SquareRootU64),
+        Some("sqrt_u128") => Ok(SweepOperation::SquareRootU128),
+        Some("precise_sqrt_newton") => Ok(SweepOperation::PreciseSquareRoot(SqrtAlgorithm::Newton)),
+        Some("precise_sqrt_cordic") => Ok(SweepOperation::PreciseSquareRoot(SqrtAlgorithm::Cordic)),
+        other => Err(format!("invalid sweep operation: {:?}", other)),
+    }
+}
+
+fn sweep_inputs(args: &[&str], index: usize) -> Result<SweepInputs, String> {
+    let i = index + 1;
+    match args.get(index).copied() {
+        Some("explicit") => Ok(SweepInputs::Explicit(
+            (i..args.len())
+                .map(|i| arg(args, i))
+                .collect::<Result<_, _>>()?,
+        )),
+        Some("linear") => Ok(SweepInputs::Linear {
+            start: arg(args, i)?,
+            step: arg(args, i + 1)?,
+            count: arg(args, i + 2)?,
+        }),
+        Some("geometric") => Ok(SweepInputs::Geometric {
+            start: arg(args, i)?,
+            ratio: arg(args, i + 1)?,
+            count: arg(args, i + 2)?,
+        }),
+        Some("random") => Ok(SweepInputs::Random {
+            seed: arg(args, i)?,
+            max: arg(args, i + 1)?,
+            count: arg(args, i + 2)?,
+        }),
+        Some("edge") => Ok(SweepInputs::EdgeCases),
+        other => Err(format!("invalid sweep distribution: {:?}", other)),
+    }
+}
+
+fn singles<T: FromStr>(args: &[&str]) -> Result<Vec<T>, String> {
+    (0..args.len()).map(|index| arg(args, index)).collect()
+}
+
+fn pairs<T: FromStr>(args: &[&str]) -> Result<Vec<(T, T)>, String> {
+    if args.len() % 2 != 0 {
+        return Err("expected pairs of arguments".to_string());
+    }
+    args.chunks(2)
+        .map(|pair| Ok((arg(pair, 0)?, arg(pair, 1)?)))
+        .collect()
+}
+
+fn triples<T: FromStr>(args: &[&str]) -> Result<Vec<(T, T, T)>, String> {
+    if args.len() % 3 != 0 {
+        return Err("expected triples of arguments".to_string());
+    }
+    args.chunks(3)
+        .map(|triple| Ok((arg(triple, 0)?, arg(triple, 1)?, arg(triple, 2)?)))
+        .collect()
+}
+
+fn batch_operation(args: &[&str]) -> Result<BatchOperation, String> {
+    let (operation, inputs) = args.split_first().ok_or("missing batch operation")?;
+    match *operation {
+        "precise_muldiv" => Ok(BatchOperation::PreciseMulDiv(triples(inputs)?)),
+        "sqrt_u64" => Ok(BatchOperation::SquareRootU64(singles(inputs)?)),
+        "sqrt_u128" => Ok(BatchOperation::SquareRootU128(singles(inputs)?)),
+        "u64_multiply" => Ok(BatchOperation::U64Multiply(pairs(inputs)?)),
+        "u64_divide" => Ok(BatchOperation::U64Divide(pairs(inputs)?)),
+        "f32_multiply" => Ok(BatchOperation::F32Multiply(pairs(inputs)?)),
+        "f32_divide" => Ok(BatchOperation::F32Divide(pairs(inputs)?)),
+        "f32_exponentiate" => Ok(BatchOperation::F32Exponentiate(pairs(inputs)?)),
+        "f32_natural_log" => Ok(BatchOperation::F32NaturalLog(singles(inputs)?)),
+        "f32_normal_cdf" => Ok(BatchOperation::F32NormalCDF(singles(inputs)?)),
+        "f64_pow" => Ok(BatchOperation::F64Pow(pairs(inputs)?)),
+        "u128_multiply" => Ok(BatchOperation::U128Multiply(pairs(inputs)?)),
+        "u128_divide" => Ok(BatchOperation::U128Divide(pairs(inputs)?)),
+        "f64_multiply" => Ok(BatchOperation::F64Multiply(pairs(inputs)?)),
+        "f64_divide" => Ok(BatchOperation::F64Divide(pairs(inputs)?)),
+        "checked_ceil_div" => Ok(BatchOperation::CheckedCeilDiv(pairs(inputs)?)),
+        other => Err(format!("invalid batch operation: {}", other)),
+    }
+}
+
+fn inner_value(args: &[&str], index: usize) -> Result<[u64; 4], String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("missing argument {}", index + 1))?;
+    U256::from_dec_str(arg)
+        .map(|value| value.0)
+        .map_err(|_| format!("invalid argument {}: {}", index + 1, arg))
+}
+
+fn signed_value(args: &[&str], index: usize) -> Result<SignedValue, String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("missing argument {}", index + 1))?;
+    let (negative, magnitude) = match arg.strip_prefix('-') {
+        Some(magnitude) => (true, magnitude),
+        None => (false, *arg),
+    };
+    U256::from_dec_str(magnitude)
+        .map(|value| SignedValue {
+            value: value.0,
+            negative,
+        })
+        .map_err(|_| format!("invalid argument {}: {}", index + 1, arg))
+}
+
+fn signed_arithmetic(args: &[&str]) -> Result<Instruction, String> {
+    let a = args;
+    let number = fixed_point_type(args)?;
+    let operation = match args.get(1).copied() {
+        Some("add") => SignedOperation::Add {
+            lhs: signed_value(a, 2)?,
+            rhs: signed_value(a, 3)?,
+        },
+        Some("sub") => SignedOperation::Sub {
+            lhs: signed_value(a, 2)?,
+            rhs: signed_value(a, 3)?,
+        },
+        Some("mul") => SignedOperation::Mul {
+            lhs: signed_value(a, 2)?,
+            rhs: signed_value(a, 3)?,
+        },
+        Some("div") => SignedOperation::Div {
+            lhs: signed_value(a, 2)?,
+            rhs: signed_value(a, 3)?,
+        },
+        Some("pow") => SignedOperation::Pow {
+            base: signed_value(a, 2)?,
+            exponent: arg(a, 3)?,
+        },
+        Some("floor") => SignedOperation::Floor {
+            value: signed_value(a, 2)?,
+        },
+        Some("ceiling") => SignedOperation::Ceiling {
+            value: signed_value(a, 2)?,
+        },
+        Some("almost_eq") => SignedOperation::AlmostEq {
+            lhs: signed_value(a, 2)?,
+            rhs: signed_value(a, 3)?,
+            precision: inner_value(a, 4)?,
+        },
+        other => return Err(format!("invalid signed operation: {:?}", other)),
+    };
+    Ok(instruction::signed_arithmetic(number, operation))
+}
+
+fn precise_operation(args: &[&str]) -> Result<PreciseOperation, String> {
+    let a = args;
+    match args.first().copied() {
+        Some("add") => Ok(PreciseOperation::Add {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+        }),
+        Some("sub") => Ok(PreciseOperation::Sub {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+        }),
+        Some("mul") => Ok(PreciseOperation::Mul {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+        }),
+        Some("div") => Ok(PreciseOperation::Div {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+        }),
+        Some("pow") => Ok(PreciseOperation::Pow {
+            base: inner_value(a, 1)?,
+            exponent: arg(a, 2)?,
+        }),
+        Some("floor") => Ok(PreciseOperation::Floor {
+            value: inner_value(a, 1)?,
+        }),
+        Some("ceiling") => Ok(PreciseOperation::Ceiling {
+            value: inner_value(a, 1)?,
+        }),
+        Some("almost_eq") => Ok(PreciseOperation::AlmostEq {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+            precision: inner_value(a, 3)?,
+        }),
+        other => Err(format!("invalid precise operation: {:?}", other)),
+    }
+}
+
+fn d18_value(args: &[&str], index: usize) -> Result<[u64; 4], String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("missing argument {}", index + 1))?;
+    d18::from_decimal_str(arg)
+        .map(|value| value.value.0)
+        .ok_or_else(|| format!("invalid argument {}: {}", index + 1, arg))
+}
+
+fn d18_operation(args: &[&str]) -> Result<D18Operation, String> {
+    let a = args;
+    match args.first().copied() {
+        Some("from_u128") => Ok(D18Operation::FromU128 { value: arg(a, 1)? }),
+        Some("from_decimal") => Ok(D18Operation::FromDecimal { value: arg(a, 1)? }),
+        Some("mul") => Ok(D18Operation::Mul {
+            lhs: d18_value(a, 1)?,
+            rhs: d18_value(a, 2)?,
+        }),
+        Some("div") => Ok(D18Operation::Div {
+            lhs: d18_value(a, 1)?,
+            rhs: d18_value(a, 2)?,
+        }),
+        Some("mul_floor") => Ok(D18Operation::MulFloor {
+            lhs: d18_value(a, 1)?,
+            rhs: d18_value(a, 2)?,
+        }),
+        Some("mul_ceil") => Ok(D18Operation::MulCeil {
+            lhs: d18_value(a, 1)?,
+            rhs: d18_value(a, 2)?,
+        }),
+        Some("div_floor") => Ok(D18Operation::DivFloor {
+            lhs: d18_value(a, 1)?,
+            rhs: d18_value(a, 2)?,
+        }),
+        Some("div_ceil") => Ok(D18Operation::DivCeil {
+            lhs: d18_value(a, 1)?,
+            rhs: d18_value(a, 2)?,
+        }),
+        Some("pow") => Ok(D18Operation::Pow {
+            base: d18_value(a, 1)?,
+            exponent: arg(a, 2)?,
+        }),
+        Some("to_u128") => Ok(D18Operation::ToU128 {
+            value: d18_value(a, 1)?,
+        }),
+        other => Err(format!("invalid D18 operation: {:?}", other)),
+    }
+}
+
+fn fixed_point_value(
+    number: FixedPointType,
+    args: &[&str],
+    index: usize,
+) -> Result<[u64; 4], String> {
+    let value = U256(d18_value(args, index)?);
+    match number {
+        FixedPointType::PreciseNumber => Ok((value / U256::exp10(6)).0),
+        FixedPointType::PreciseNumber256D18 => Ok(value.0),
+    }
+}
+
+fn fixed_point_type(args: &[&str]) -> Result<FixedPointType, String> {
+    match args.first().copied() {
+        Some("precise") => Ok(FixedPointType::PreciseNumber),
+        Some("d18") => Ok(FixedPointType::PreciseNumber256D18),
+        other => Err(format!("invalid fixed-point type: {:?}", other)),
+    }
+}
+
+fn fixed_point_math(args: &[&str]) -> Result<Instruction, String> {
+    let number = fixed_point_type(args)?;
+    let value = |index| fixed_point_value(number, args, index);
+    let operation = match args.get(1).copied() {
+        Some("exp") => FixedPointOperation::Exp {
+            value: value(2)?,
+            negative: false,
+        },
+        Some("exp_neg") => FixedPointOperation::Exp {
+            value: value(2)?,
+            negative: true,
+        },
+        Some("ln") => FixedPointOperation::Ln { value: value(2)? },
+        Some("log2") => FixedPointOperation::Log2 { value: value(2)? },
+        Some("pow") => FixedPointOperation::Pow {
+            base: value(2)?,
+            exponent: value(3)?,
+        },
+        Some("pow_down") => FixedPointOperation::DirectedPow {
+            base: value(2)?,
+            exponent: value(3)?,
+            round_up: false,
+        },
+        Some("pow_up") => FixedPointOperation::DirectedPow {
+            base: value(2)?,
+            exponent: value(3)?,
+            round_up: true,
+        },
+        other => return Err(format!("invalid fixed-point operation: {:?}", other)),
+    };
+    Ok(instruction::fixed_point_math(number, operation))
+}
+
+fn normal_distribution(args: &[&str]) -> Result<Instruction, String> {
+    let value = |index| fixed_point_value(FixedPointType::PreciseNumber, args, index);
+    let operation = match args.first().copied() {
+        Some("cdf") => NormalOperation::Cdf {
+            argument: value(1)?,
+            negative: false,
+        },
+        Some("cdf_neg") => NormalOperation::Cdf {
+            argument: value(1)?,
+            negative: true,
+        },
+        Some("pdf") => NormalOperation::Pdf {
+            argument: value(1)?,
+        },
+        Some("inverse_cdf") => NormalOperation::InverseCdf {
+            probability: value(1)?,
+        },
+        other => return Err(format!("invalid normal operation: {:?}", other)),
+    };
+    Ok(instruction::normal_distribution(operation))
+}
+
+fn black_scholes(args: &[&str]) -> Result<Instruction, String> {
+    let option = match args.first().copied() {
+        Some("call") => OptionKind::Call,
+        Some("put") => OptionKind::Put,
+        other => return Err(format!("invalid option kind: {:?}", other)),
+    };
+    let inputs = match args.get(1).copied() {
+        Some("f64") => BlackScholesInputs::F64 {
+            spot: arg(args, 2)?,
+            strike: arg(args, 3)?,
+            rate: arg(args, 4)?,
+            volatility: arg(args, 5)?,
+            time: arg(args, 6)?,
+        },
+        Some("precise") => {
+            let value = |index| fixed_point_value(FixedPointType::PreciseNumber, args, index);
+            BlackScholesInputs::Precise {
+                spot: value(2)?,
+                strike: value(3)?,
+                rate: value(4)?,
+                volatility: value(5)?,
+                time: value(6)?,
+            }
+        }
+        other => return Err(format!("invalid Black-Scholes arithmetic: {:?}", other)),
+    };
+    Ok(instruction::black_scholes(option, inputs))
+}
+
+fn curve_operation(args: &[&str]) -> Result<CurveOperation, String> {
+    let fee = || -> Result<TradeFee, String> {
+        Ok(TradeFee {
+            numerator: arg(args, 4)?,
+            denominator: arg(args, 5)?,
+        })
+    };
+    match args.first().copied() {
+        Some("swap_exact_in") => Ok(CurveOperation::SwapExactIn {
+            amount_in: arg(args, 1)?,
+            reserve_in: arg(args, 2)?,
+            reserve_out: arg(args, 3)?,
+            fee: fee()?,
+        }),
+        Some("swap_exact_out") => Ok(CurveOperation::SwapExactOut {
+            amount_out: arg(args, 1)?,
+            reserve_in: arg(args, 2)?,
+            reserve_out: arg(args, 3)?,
+            fee: fee()?,
+        }),
+        Some("deposit") => Ok(CurveOperation::Deposit {
+            pool_tokens: arg(args, 1)?,
+            pool_supply: arg(args, 2)?,
+            reserve_a: arg(args, 3)?,
+            reserve_b: arg(args, 4)?,
+        }),
+        Some("withdraw") => Ok(CurveOperation::Withdraw {
+            pool_tokens: arg(args, 1)?,
+            pool_supply: arg(args, 2)?,
+            reserve_a: arg(args, 3)?,
+            reserve_b: arg(args, 4)?,
+        }),
+        other => Err(format!("invalid curve operation: {:?}", other)),
+    }
+}
+
+fn stable_swap_operation(args: &[&str]) -> Result<StableSwapOperation, String> {
+    let amp = arg(args, 1)?;
+    let max_iterations = arg(args, 2)?;
+    match args.first().copied() {
+        Some("d") => Ok(StableSwapOperation::ComputeD {
+            amp,
+            balances: singles(args.get(3..).unwrap_or_default())?,
+            max_iterations,
+        }),
+        Some("y") => Ok(StableSwapOperation::GetY {
+            amp,
+            balances: singles(args.get(6..).unwrap_or_default())?,
+            i: arg(args, 3)?,
+            j: arg(args, 4)?,
+            x: arg(args, 5)?,
+            max_iterations,
+        }),
+        other => Err(format!("invalid StableSwap operation: {:?}", other)),
+    }
+}
+
+fn weighted_operation(args: &[&str]) -> Result<WeightedOperation, String> {
+    match args.first().copied() {
+        Some("out_given_in") => Ok(WeightedOperation::OutGivenIn {
+            balance_in: arg(args, 1)?,
+            weight_in: arg(args, 2)?,
+            balance_out: arg(args, 3)?,
+            weight_out: arg(args, 4)?,
+            amount_in: arg(args, 5)?,
+        }),
+        Some("in_given_out") => Ok(WeightedOperation::InGivenOut {
+            balance_in: arg(args, 1)?,
+            weight_in: arg(args, 2)?,
+            balance_out: arg(args, 3)?,
+            weight_out: arg(args, 4)?,
+            amount_out: arg(args, 5)?,
+        }),
+        other => Err(format!("invalid weighted pool operation: {:?}", other)),
+    }
+}
+
+fn interest_operation(args: &[&str]) -> Result<InterestOperation, String> {
+    let value = |index| fixed_point_value(FixedPointType::PreciseNumber, args, index);
+    let round_up = |index| match args.get(index).copied() {
+        Some("down") => Ok(false),
+        Some("up") => Ok(true),
+        other => Err(format!("invalid rounding: {:?}", other)),
+    };
+    match args.first().copied() {
+        Some("accrue") => Ok(InterestOperation::Accrue {
+            compounding: match args.get(1).copied() {
+                Some("per_slot") => Compounding::PerSlot,
+                Some("per_year") => Compounding::PerYear,
+                Some("continuous") => Compounding::Continuous,
+                other => return Err(format!("invalid compounding: {:?}", other)),
+            },
+            apr: value(2)?,
+            years: arg(args, 3)?,
+            round_up: round_up(4)?,
+        }),
+        Some("apr_to_apy") => Ok(InterestOperation::AprToApy {
+            apr: value(1)?,
+            periods_per_year: arg(args, 2)?,
+            round_up: round_up(3)?,
+        }),
+        Some("apy_to_apr") => Ok(InterestOperation::ApyToApr {
+            apy: value(1)?,
+            periods_per_year: arg(args, 2)?,
+        }),
+        other => Err(format!("invalid interest operation: {:?}", other)),
+    }
+}
+
+fn u192_value(args: &[&str], index: usize) -> Result<[u64; 3], String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("missing argument {}", index + 1))?;
+    U192::from_dec_str(arg)
+        .map(|value| value.0)
+        .map_err(|_| format!("invalid argument {}: {}", index + 1, arg))
+}
+
+fn wide_operation(args: &[&str]) -> Result<WideOperation, String> {
+    let a = args;
+    match args.first().copied() {
+        Some("u256_multiply") => Ok(WideOperation::U256Multiply {
+            multiplicand: inner_value(a, 1)?,
+            multiplier: inner_value(a, 2)?,
+        }),
+        Some("u256_divide") => Ok(WideOperation::U256Divide {
+            dividend: inner_value(a, 1)?,
+            divisor: inner_value(a, 2)?,
+        }),
+        Some("u256_mul_div") => Ok(WideOperation::U256MulDiv {
+            value: inner_value(a, 1)?,
+            numerator: inner_value(a, 2)?,
+            denominator: inner_value(a, 3)?,
+        }),
+        Some("u256_sqrt") => Ok(WideOperation::U256Sqrt {
+            radicand: inner_value(a, 1)?,
+        }),
+        Some("u256_shift_left") => Ok(WideOperation::U256ShiftLeft {
+            value: inner_value(a, 1)?,
+            shift: arg(a, 2)?,
+        }),
+        Some("u256_shift_right") => Ok(WideOperation::U256ShiftRight {
+            value: inner_value(a, 1)?,
+            shift: arg(a, 2)?,
+        }),
+        Some("u256_overflowing_add") => Ok(WideOperation::U256OverflowingAdd {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+        }),
+        Some("u256_overflowing_sub") => Ok(WideOperation::U256OverflowingSub {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+        }),
+        Some("u256_overflowing_mul") => Ok(WideOperation::U256OverflowingMul {
+            lhs: inner_value(a, 1)?,
+            rhs: inner_value(a, 2)?,
+        }),
+        Some("u192_multiply") => Ok(WideOperation::U192Multiply {
+            multiplicand: u192_value(a, 1)?,
+            multiplier: u192_value(a, 2)?,
+        }),
+        Some("u192_divide") => Ok(WideOperation::U192Divide {
+            dividend: u192_value(a, 1)?,
+            divisor: u192_value(a, 2)?,
+        }),
+        other => Err(format!("invalid wide operation: {:?}", other)),
+    }
+}
+
+fn unsigned_integer(args: &[&str]) -> Result<UnsignedInteger, String> {
+    match args.first().copied() {
+        Some("u8") => Ok(UnsignedInteger::U8(arg(args, 1)?)),
+        Some("u16") => Ok(UnsignedInteger::U16(arg(args, 1)?)),
+        Some("u32") => Ok(UnsignedInteger::U32(arg(args, 1)?)),
+        Some("u64") => Ok(UnsignedInteger::U64(arg(args, 1)?)),
+        Some("u128") => Ok(UnsignedInteger::U128(arg(args, 1)?)),
+        Some("u256") => Ok(UnsignedInteger::U256(inner_value(args, 1)?)),
+        other => Err(format!("invalid integer width: {:?}", other)),
+    }
+}
+
+fn parse_case(input: &str) -> Result<Case, String> {
+    let mut parts = input.split_whitespace();
+    let builder = parts.next().ok_or("empty case")?;
+    let args: Vec<&str> = parts.collect();
+    let a = &args;
+    let instruction = match builder {
+        "noop" => instruction::noop(),
+        "precise_sqrt" => instruction::precise_sqrt(arg(a, 0)?, algorithm(a, 1)?),
+        "precise_sqrt_array" => {
+            instruction::precise_sqrt_array(arg(a, 0)?, arg(a, 1)?, algorithm(a, 2)?)
+        }
+        "precise_muldiv" => {
+            instruction::precise_muldiv_rounded(arg(a, 0)?, arg(a, 1)?, arg(a, 2)?, rounding(a, 3)?)
+        }
+        "sqrt_u64" => instruction::sqrt_u64(arg(a, 0)?),
+        "sqrt_u128" => instruction::sqrt_u128(arg(a, 0)?),
+        "u64_multiply" => instruction::u64_multiply(arg(a, 0)?, arg(a, 1)?),
+        "u64_divide" => instruction::u64_divide(arg(a, 0)?, arg(a, 1)?),
+        "f32_multiply" => instruction::f32_multiply(arg(a, 0)?, arg(a, 1)?),
+        "f32_divide" => instruction::f32_divide(arg(a, 0)?, arg(a, 1)?),
+        "f32_exponentiate" => instruction::f32_exponentiate(arg(a, 0)?, arg(a, 1)?),
+        "f32_natural_log" => instruction::f32_natural_log(arg(a, 0)?),
+        "f32_normal_cdf" => instruction::f32_normal_cdf(arg(a, 0)?),
+        "f64_pow" => instruction::f64_pow(arg(a, 0)?, arg(a, 1)?),
+        "u128_multiply" => instruction::u128_multiply(arg(a, 0)?, arg(a, 1)?),
+        "u128_divide" => instruction::u128_divide(arg(a, 0)?, arg(a, 1)?),
+        "f64_multiply" => instruction::f64_multiply(arg(a, 0)?, arg(a, 1)?),
+        "f64_divide" => instruction::f64_divide(arg(a, 0)?, arg(a, 1)?),
+        "checked_ceil_div" => instruction::checked_ceil_div(arg(a, 0)?, arg(a, 1)?),
+        "precise_arithmetic" => instruction::precise_arithmetic(precise_operation(a)?),
+        "signed_arithmetic" => signed_arithmetic(a)?,
+        "precise_d18_arithmetic" => instruction::precise_d18_arithmetic(d18_operation(a)?),
+        "wide_arithmetic" => instruction::wide_arithmetic(wide_operation(a)?),
+        "integer_sqrt" => instruction::integer_sqrt(unsigned_integer(a)?),
+        "fixed_point_math" => fixed_point_math(a)?,
+        "normal_distribution" => normal_distribution(a)?,
+        "black_scholes" => black_scholes(a)?,
+        "curve" => instruction::curve(curve_operation(a)?),
+        "stable_swap" => instruction::stable_swap(stable_swap_operation(a)?),
+        "weighted_pool" => instruction::weighted_pool(weighted_operation(a)?),
+        "compound_interest" => instruction::compound_interest(interest_operation(a)?),
+        "sweep" => instruction::sweep(sweep_operation(a, 0)?, sweep_inputs(a, 1)?),
+        "batch" => instruction::batch(batch_operation(a)?),
+        _ => return Err(format!("unknown builder {}", builder)),
+    };
+    Ok(Case {
+        input: args
+            .iter()
+            .fold(builder.to_string(), |input, arg| input + " " + arg),
+        builder: builder.to_string(),
+        instruction,
+    })
+}
+
+fn parse_cases(inputs: &str, filters: &[String]) -> Result<Vec<Case>, String> {
+    let mut cases = vec![];
+    for (number, line) in inputs.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let case = parse_case(line).map_err(|err| format!("line {}: {}", number + 1, err))?;
+        if filters.is_empty() || filters.iter().any(|filter| case.builder.contains(filter)) {
+            cases.push(case);
+        }
+    }
+    Ok(cases)
+}
+
+struct Row {
+    input: String,
+    label: String,
+    consumed: Option<u64>,
+    raw: Option<u64>,
+    overhead: Option<u64>,
+    count: Option<u64>,
+    per_op: Option<u64>,
+    heap: Option<HeapUsage>,
+    error: Option<String>,
+}
+
+async fn run_cases(cases: Vec<Case>) -> Vec<Row> {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+    pc.prefer_bpf(true);
+    pc.set_compute_max_units(1_400_000);
+
+    let (banks_client, payer, _) = pc.start().await;
+
+    let mut rows = vec![];
+    for case in cases {
+        let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+        let mut transaction =
+            Transaction::new_with_payer(&[case.instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client
+            .process_transaction_with_metadata(transaction)
+            .await;
+        let (logs, error) = match result {
+            Ok(result) => (
+                result
+                    .metadata
+                    .map(|metadata| metadata.log_messages)
+                    .unwrap_or_default(),
+                result.result.err().map(|err| err.to_string()),
+            ),
+            Err(err) => (vec![], Some(err.to_string())),
+        };
+        let records: Vec<BenchRecord> = logs
+            .iter()
+            .filter_map(|log| BenchRecord::parse(log))
+            .collect();
+        if records.is_empty() || error.is_some() {
+            rows.push(Row {
+                input: case.input.clone(),
+                label: String::new(),
+                consumed: None,
+                raw: None,
+                overhead: None,
+                count: None,
+                per_op: None,
+                heap: None,
+                error: Some(error.unwrap_or_else(|| "no measurement logged".to_string())),
+            });
+        }
+        for record in records {
+            rows.push(Row {
+                input: case.input.clone(),
+                label: record.label,
+                consumed: Some(record.consumed),
+                raw: Some(record.raw),
+                overhead: Some(record.overhead),
+                count: Some(record.count),
+                per_op: Some(record.per_op()),
+                heap: record.heap,
+                error: None,
+            });
+        }
+    }
+    rows
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn to_csv(rows: &[Row]) -> String {
+    let mut csv = "case,label,compute_units,raw,overhead,count,per_op,\
+         heap_allocated,heap_peak,heap_allocations,error\n"
+        .to_string();
+    for row in rows {
+        writeln!(
+            csv,
+            "\"{}\",{},{},{},{},{},{},{},{},{},\"{}\"",
+            row.input,
+            row.label,
+            optional(row.consumed),
+            optional(row.raw),
+            optional(row.overhead),
+            optional(row.count),
+            optional(row.per_op),
+            optional(row.heap.map(|heap| heap.allocated)),
+            optional(row.heap.map(|heap| heap.peak)),
+            optional(row.heap.map(|heap| heap.allocations)),
+            row.error.as_deref().unwrap_or_default().replace('"', "'"),
+        )
+        .unwrap();
+    }
+    csv
+}
+
+fn to_markdown(rows: &[Row]) -> String {
+    let with_heap = rows.iter().any(|row| row.heap.is_some());
+    let mut markdown = String::new();
+    if with_heap {
+        markdown.push_str(
+            "| case | label | compute units | raw | count | per op \
+             | heap bytes | heap peak | allocations |\n",
+        );
+        markdown.push_str("|---|---|---:|---:|---:|---:|---:|---:|---:|\n");
+    } else {
+        markdown.push_str("| case | label | compute units | raw | count | per op |\n");
+        markdown.push_str("|---|---|---:|---:|---:|---:|\n");
+    }
+    for row in rows {
+        let consumed = match (&row.consumed, &row.error) {
+            (Some(consumed), _) => consumed.to_string(),
+            (None, Some(error)) => format!("error: {}", error),
+            (None, None) => String::new(),
+        };
+        write!(
+            markdown,
+            "| `{}` | {} | {} | {} | {} | {} |",
+            row.input,
+            row.label,
+            consumed,
+            optional(row.raw),
+            optional(row.count),
+            optional(row.per_op),
+        )
+        .unwrap();
+        if with_heap {
+            write!(
+                markdown,
+                " {} | {} | {} |",
+                optional(row.heap.map(|heap| heap.allocated)),
+                optional(row.heap.map(|heap| heap.peak)),
+                optional(row.heap.map(|heap| heap.allocations)),
+            )
+            .unwrap();
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+fn main() {
+    let run = || -> Result<(), String> {
+        let options = parse_options()?;
+        let inputs = match &options.inputs {
+            Some(path) => fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?,
+            None => DEFAULT_INPUTS.to_string(),
+        };
+        let cases = parse_cases(&inputs, &options.filters)?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| err.to_string())?;
+        let rows = runtime.block_on(run_cases(cases));
+
+        if let Some(path) = &options.csv {
+            fs::write(path, to_csv(&rows)).map_err(|err| format!("{}: {}", path, err))?;
+        }
+        match &options.markdown {
+            Some(path) => {
+                fs::write(path, to_markdown(&rows)).map_err(|err| format!("{}: {}", path, err))?
+            }
+            None => print!("{}", to_markdown(&rows)),
+        }
+        Ok(())
+    };
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        exit(1);
+    }
+}
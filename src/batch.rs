@@ -0,0 +1,341 @@
+//! Batches of a scalar operation measured in one window, so the fixed cost of
+//! the measurement is amortized over many inputs
+
+use {
+    crate::{
+        approximations::{f32_normal_cdf, sqrt},
+        bench::Bench,
+        checked_ceil_div::CheckedCeilDiv,
+        error::MathError,
+        instruction::{BatchOperation, MathResult},
+        processor::{
+            check_divisor, check_integer_divisor, check_log_argument, check_pow, check_product,
+            f32_divide, f32_exponentiate, f32_multiply, f32_natural_log, f64_divide, f64_multiply,
+            not_nan, precise_muldiv_inputs, u128_divide, u128_multiply, u64_divide, u64_multiply,
+        },
+    },
+    num_traits::Float,
+};
+
+/// Most inputs in one batch, so the inputs fit in a transaction and the
+/// results in the return data
+pub const MAX_BATCH_INPUTS: usize = 16;
+
+impl BatchOperation {
+    /// Number of inputs of the batch
+    pub(crate) fn count(&self) -> usize {
+        match self {
+            Self::PreciseMulDiv(inputs) => inputs.len(),
+            Self::SquareRootU64(inputs) => inputs.len(),
+            Self::SquareRootU128(inputs) => inputs.len(),
+            Self::U64Multiply(inputs) | Self::U64Divide(inputs) => inputs.len(),
+            Self::F32Multiply(inputs) | Self::F32Divide(inputs) | Self::F32Exponentiate(inputs) => {
+                inputs.len()
+            }
+            Self::F32NaturalLog(inputs) | Self::F32NormalCDF(inputs) => inputs.len(),
+            Self::F64Pow(inputs) | Self::F64Multiply(inputs) | Self::F64Divide(inputs) => {
+                inputs.len()
+            }
+            Self::U128Multiply(inputs)
+            | Self::U128Divide(inputs)
+            | Self::CheckedCeilDiv(inputs) => inputs.len(),
+        }
+    }
+}
+
+/// Run `op` on every input in one measurement window, stopping at the first
+/// input it fails on
+#[inline(always)]
+fn measure_each<I, R>(
+    bench: &Bench,
+    label: &str,
+    inputs: Vec<I>,
+    mut op: impl FnMut(I) -> Option<R>,
+) -> Option<Vec<R>> {
+    // allocate up front so the measurement only covers the operations
+    let mut results = Vec::with_capacity(inputs.len());
+    let count = inputs.len();
+    bench.measure_batch(label, count, || {
+        inputs.into_iter().try_for_each(|input| {
+            results.push(op(input)?);
+            Some(())
+        })
+    })?;
+    Some(results)
+}
+
+/// Reject floating point values that are not a number
+fn not_nan_all<T: Float>(results: Vec<T>) -> Result<Vec<T>, MathError> {
+    results.into_iter().map(not_nan).collect()
+}
+
+/// Check the inputs of a batch of binary operations
+fn check_inputs<T, I>(
+    inputs: Vec<I>,
+    check: impl Fn(I) -> Result<(T, T), MathError>,
+) -> Result<Vec<(T, T)>, MathError> {
+    inputs.into_iter().map(check).collect()
+}
+
+/// Run the batch, measuring all of its inputs in one window
+pub(crate) fn process_batch(
+    bench: &Bench,
+    operation: BatchOperation,
+) -> Result<MathResult, MathError> {
+    if operation.count() == 0 || operation.count() > MAX_BATCH_INPUTS {
+        return Err(MathError::InvalidArgument);
+    }
+    match operation {
+        BatchOperation::PreciseMulDiv(inputs) => {
+            let inputs = inputs
+                .into_iter()
+                .map(|(val, num, denom)| precise_muldiv_inputs(val, num, denom))
+                .collect::<Result<Vec<_>, _>>()?;
+            let results = measure_each(
+                bench,
+                "batch_precise_muldiv",
+                inputs,
+                |(val, num, denom)| val.mul_div_floor(num, denom),
+            )
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::PreciseArray(
+                results.into_iter().map(|result| result.value.0).collect(),
+            ))
+        }
+        BatchOperation::SquareRootU64(inputs) => {
+            let results =
+                measure_each(bench, "batch_sqrt_u64", inputs, sqrt).ok_or(MathError::Overflow)?;
+            Ok(MathResult::U64Array(results))
+        }
+        BatchOperation::SquareRootU128(inputs) => {
+            let results =
+                measure_each(bench, "batch_sqrt_u128", inputs, sqrt).ok_or(MathError::Overflow)?;
+            Ok(MathResult::U128Array(results))
+        }
+        BatchOperation::U64Multiply(inputs) => {
+            let inputs = check_inputs(inputs, |(multiplicand, multiplier)| {
+                check_product(multiplicand, multiplier)
+            })?;
+            let results = measure_each(
+                bench,
+                "batch_u64_multiply",
+                inputs,
+                |(multiplicand, multiplier)| Some(u64_multiply(multiplicand, multiplier)),
+            )
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U64Array(results))
+        }
+        BatchOperation::U64Divide(inputs) => {
+            let inputs = check_inputs(inputs, |(dividend, divisor)| {
+                Ok((dividend, check_integer_divisor(divisor)?))
+            })?;
+            let results = measure_each(bench, "batch_u64_divide", inputs, |(dividend, divisor)| {
+                Some(u64_divide(dividend, divisor))
+            })
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U64Array(results))
+        }
+        BatchOperation::F32Multiply(inputs) => {
+            let inputs = check_inputs(inputs, |(multiplicand, multiplier)| {
+                Ok((not_nan(multiplicand)?, not_nan(multiplier)?))
+            })?;
+            let results = measure_each(
+                bench,
+                "batch_f32_multiply",
+                inputs,
+                |(multiplicand, multiplier)| Some(f32_multiply(multiplicand, multiplier)),
+            )
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::F32Array(not_nan_all(results)?))
+        }
+        BatchOperation::F32Divide(inputs) => {
+            let inputs = check_inputs(inputs, |(dividend, divisor)| {
+                Ok((not_nan(dividend)?, check_divisor(divisor)?))
+            })?;
+            let results = measure_each(bench, "batch_f32_divide", inputs, |(dividend, divisor)| {
+                Some(f32_divide(dividend, divisor))
+            })
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::F32Array(not_nan_all(results)?))
+        }
+        BatchOperation::F32Exponentiate(inputs) => {
+            let inputs = check_inputs(inputs, |(base, exponent)| check_pow(base, exponent))?;
+            let results = measure_each(
+                bench,
+                "batch_f32_exponentiate",
+                inputs,
+                |(base, exponent)| Some(f32_exponentiate(base, exponent)),
+            )
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::F32Array(not_nan_all(results)?))
+        }
+        BatchOperation::F32NaturalLog(inputs) => {
+            let inputs = inputs
+                .into_iter()
+                .map(check_log_argument)
+                .collect::<Result<Vec<_>, _>>()?;
+            let results = measure_each(bench, "batch_f32_natural_log", inputs, |argument| {
+                Some(f32_natural_log(argument))
+            })
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::F32Array(not_nan_all(results)?))
+        }
+        BatchOperation::F32NormalCDF(inputs) => {
+            let inputs = not_nan_all(inputs)?;
+            let results = measure_each(bench, "batch_f32_normal_cdf", inputs, |argument| {
+                Some(f32_normal_cdf(argument))
+            })
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::F32Array(not_nan_all(results)?))
+        }
+        BatchOperation::F64Pow(inputs) => {
+            let inputs = check_inputs(inputs, |(base, exponent)| check_pow(base, exponent))?;
+            let results = measure_each(bench, "batch_f64_pow", inputs, |(base, exponent)| {
+                Some((base.powi(exponent as i32), base.powf(exponent)))
+            })
+            .ok_or(MathError::Overflow)?;
+            let results = results
+                .into_iter()
+                .map(|(powi, powf)| Ok((not_nan(powi)?, not_nan(powf)?)))
+                .collect::<Result<_, MathError>>()?;
+            Ok(MathResult::F64PowArray(results))
+        }
+        BatchOperation::U128Multiply(inputs) => {
+            let inputs = check_inputs(inputs, |(multiplicand, multiplier)| {
+                check_product(multiplicand, multiplier)
+            })?;
+            let results = measure_each(
+                bench,
+                "batch_u128_multiply",
+                inputs,
+                |(multiplicand, multiplier)| Some(u128_multiply(multiplicand, multiplier)),
+            )
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U128Array(results))
+        }
+        BatchOperation::U128Divide(inputs) => {
+            let inputs = check_inputs(inputs, |(dividend, divisor)| {
+                Ok((dividend, check_integer_divisor(divisor)?))
+            })?;
+            let results =
+                measure_each(bench, "batch_u128_divide", inputs, |(dividend, divisor)| {
+                    Some(u128_divide(dividend, divisor))
+                })
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U128Array(results))
+        }
+        BatchOperation::F64Multiply(inputs) => {
+            let inputs = check_inputs(inputs, |(multiplicand, multiplier)| {
+                Ok((not_nan(multiplicand)?, not_nan(multiplier)?))
+            })?;
+            let results = measure_each(
+                bench,
+                "batch_f64_multiply",
+                inputs,
+                |(multiplicand, multiplier)| Some(f64_multiply(multiplicand, multiplier)),
+            )
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::F64Array(not_nan_all(results)?))
+        }
+        BatchOperation::F64Divide(inputs) => {
+            let inputs = check_inputs(inputs, |(dividend, divisor)| {
+                Ok((not_nan(dividend)?, check_divisor(divisor)?))
+            })?;
+            let results = measure_each(bench, "batch_f64_divide", inputs, |(dividend, divisor)| {
+                Some(f64_divide(dividend, divisor))
+            })
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::F64Array(not_nan_all(results)?))
+        }
+        BatchOperation::CheckedCeilDiv(inputs) => {
+            if inputs.iter().any(|(_, divisor)| *divisor == 0) {
+                return Err(MathError::DivisionByZero);
+            }
+            let results = measure_each(
+                bench,
+                "batch_checked_ceil_div",
+                inputs,
+                |(dividend, divisor)| dividend.checked_ceil_div(divisor),
+            )
+            .ok_or(MathError::Overflow)?;
+            Ok(MathResult::CeilDivArray(results))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(operation: BatchOperation) -> Result<MathResult, MathError> {
+        process_batch(&Bench::default(), operation)
+    }
+
+    #[test]
+    fn test_batch_limits() {
+        assert_eq!(
+            run(BatchOperation::U64Multiply(vec![])),
+            Err(MathError::InvalidArgument)
+        );
+        assert_eq!(
+            run(BatchOperation::SquareRootU64(vec![4; MAX_BATCH_INPUTS + 1])),
+            Err(MathError::InvalidArgument)
+        );
+        assert_eq!(
+            run(BatchOperation::SquareRootU64(vec![4; MAX_BATCH_INPUTS])),
+            Ok(MathResult::U64Array(vec![2; MAX_BATCH_INPUTS]))
+        );
+    }
+
+    #[test]
+    fn test_batch_integer_operations() {
+        assert_eq!(
+            run(BatchOperation::U64Multiply(vec![(42, 84), (2, 3)])),
+            Ok(MathResult::U64Array(vec![42 * 84, 6]))
+        );
+        assert_eq!(
+            run(BatchOperation::U64Multiply(vec![(2, 3), (u64::MAX, 2)])),
+            Err(MathError::Overflow)
+        );
+        assert_eq!(
+            run(BatchOperation::U128Divide(vec![(u128::MAX, 1), (1, 0)])),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(BatchOperation::PreciseMulDiv(vec![(1, 1, 0)])),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(BatchOperation::CheckedCeilDiv(vec![(10, 3), (10, 5)])),
+            Ok(MathResult::CeilDivArray(vec![(4, 3), (2, 5)]))
+        );
+        assert_eq!(
+            run(BatchOperation::CheckedCeilDiv(vec![(10, 3), (10, 0)])),
+            Err(MathError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_batch_float_operations() {
+        assert_eq!(
+            run(BatchOperation::F64Divide(vec![(3.0, 1.5), (1.0, 4.0)])),
+            Ok(MathResult::F64Array(vec![2.0, 0.25]))
+        );
+        assert_eq!(
+            run(BatchOperation::F64Pow(vec![(2.0, 3.0)])),
+            Ok(MathResult::F64PowArray(vec![(8.0, 8.0)]))
+        );
+        assert_eq!(
+            run(BatchOperation::F32Divide(vec![(1.0, 1.0), (1.0, 0.0)])),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(BatchOperation::F32NaturalLog(vec![1.0, -1.0])),
+            Err(MathError::DomainError)
+        );
+        assert_eq!(
+            run(BatchOperation::F32NormalCDF(vec![f32::NAN])),
+            Err(MathError::NotANumber)
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Compute unit measurement shared by all benchmarks
+
+use {
+    solana_program::{compute_units::sol_remaining_compute_units, log::sol_log_compute_units, msg},
+    std::hint::black_box,
+};
+
+/// Prefix of the log record emitted for every measurement
+pub const LOG_PREFIX: &str = "cu_bench_consumed";
+
+/// Compute unit meter of one instruction
+///
+/// The meter is calibrated when the instruction starts, by measuring an empty
+/// window like the Noop instruction does. That overhead, the cost of reading
+/// the remaining compute units, is subtracted from every measurement, so it
+/// follows changes of the runtime and toolchain.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bench {
+    /// Compute units consumed by an empty measurement window
+    pub overhead: u64,
+}
+
+impl Bench {
+    /// Measure the overhead of an empty measurement window
+    #[inline(always)]
+    pub fn calibrate() -> Self {
+        let cu_before = sol_remaining_compute_units();
+        black_box(());
+        let cu_after = sol_remaining_compute_units();
+        Self {
+            overhead: cu_before.saturating_sub(cu_after),
+        }
+    }
+
+    /// Run `op` in a compute unit measurement window and log one record
+    ///
+    /// The record has the form
+    /// `cu_bench_consumed <units> label=<label> raw=<raw> overhead=<overhead>`,
+    /// where `raw` is the compute units consumed by the window and `units`
+    /// the same minus the calibrated overhead. The result of `op` goes
+    /// through a black box, so the optimizer cannot elide the measured work.
+    ///
+    /// With the instrumented allocator of the `custom-heap` feature, the
+    /// record also carries `heap_allocated`, `heap_peak` and
+    /// `heap_allocations` for the measured operation.
+    #[inline(always)]
+    pub fn measure<R>(&self, label: &str, op: impl FnOnce() -> R) -> R {
+        self.measure_units(label, op).0
+    }
+
+    /// Like `measure`, but also return the logged compute units
+    #[inline(always)]
+    pub fn measure_units<R>(&self, label: &str, op: impl FnOnce() -> R) -> (R, u64) {
+        self.measure_window(label, 1, op)
+    }
+
+    /// Like `measure`, for `op` running `count` operations in one window
+    ///
+    /// The record adds `count=<count> per_op=<units>`, where `per_op` is the
+    /// compute units amortized over the operations.
+    #[inline(always)]
+    pub fn measure_batch<R>(&self, label: &str, count: usize, op: impl FnOnce() -> R) -> R {
+        self.measure_window(label, count, op).0
+    }
+
+    /// Measure `op`, which runs `count` operations, and log its record
+    #[inline(always)]
+    fn measure_window<R>(&self, label: &str, count: usize, op: impl FnOnce() -> R) -> (R, u64) {
+        #[cfg(all(
+            feature = "custom-heap",
+            not(feature = "no-entrypoint"),
+            target_os = "solana"
+        ))]
+        let heap_before = {
+            crate::heap::reset_peak();
+            crate::heap::stats()
+        };
+        sol_log_compute_units();
+        let cu_before = sol_remaining_compute_units();
+        let result = black_box(op());
+        let cu_after = sol_remaining_compute_units();
+        sol_log_compute_units();
+        let raw = cu_before.saturating_sub(cu_after);
+        let consumed = raw.saturating_sub(self.overhead);
+        #[cfg(all(
+            feature = "custom-heap",
+            not(feature = "no-entrypoint"),
+            target_os = "solana"
+        ))]
+        {
+            let heap_after = crate::heap::stats();
+            msg!(
+                "{} {} label={} raw={} overhead={}{} heap_allocated={} heap_peak={} heap_allocations={}",
+                LOG_PREFIX,
+                consumed,
+                label,
+                raw,
+                self.overhead,
+                batch_fields(consumed, count),
+                heap_after.allocated.saturating_sub(heap_before.allocated),
+                heap_after.peak.saturating_sub(heap_before.in_use),
+                heap_after
+                    .allocations
+                    .saturating_sub(heap_before.allocations),
+            );
+        }
+        #[cfg(not(all(
+            feature = "custom-heap",
+            not(feature = "no-entrypoint"),
+            target_os = "solana"
+        )))]
+        msg!(
+            "{} {} label={} raw={} overhead={}{}",
+            LOG_PREFIX,
+            consumed,
+            label,
+            raw,
+            self.overhead,
+            batch_fields(consumed, count)
+        );
+        (result, consumed)
+    }
+}
+
+/// Record fields of a batch of `count` operations, empty for a single one
+fn batch_fields(consumed: u64, count: usize) -> String {
+    if count == 1 {
+        String::new()
+    } else {
+        format!(" count={} per_op={}", count, per_op(consumed, count as u64))
+    }
+}
+
+/// Compute units amortized over `count` operations
+fn per_op(consumed: u64, count: u64) -> u64 {
+    consumed.checked_div(count).unwrap_or(consumed)
+}
+
+/// Heap usage of a measured operation, as reported by the `custom-heap`
+/// allocator
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HeapUsage {
+    /// Bytes allocated during the operation
+    pub allocated: u64,
+    /// Peak of bytes in use during the operation, above those already in use
+    /// before it
+    pub peak: u64,
+    /// Number of allocations during the operation
+    pub allocations: u64,
+}
+
+/// A measurement record, parsed back from the program logs
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchRecord {
+    /// Label passed to `measure`
+    pub label: String,
+    /// Compute units consumed by the measured operation, corrected by the
+    /// calibrated overhead
+    pub consumed: u64,
+    /// Compute units consumed by the measurement window, overhead included
+    pub raw: u64,
+    /// Overhead of an empty measurement window, as calibrated by the program
+    pub overhead: u64,
+    /// Number of operations measured together, 1 unless batched
+    pub count: u64,
+    /// Heap usage of the measured operation, if the program was built with
+    /// the `custom-heap` feature
+    pub heap: Option<HeapUsage>,
+}
+
+impl BenchRecord {
+    /// Compute units amortized over the measured operations
+    pub fn per_op(&self) -> u64 {
+        per_op(self.consumed, self.count)
+    }
+
+    /// Parse a record from a log line, with or without the `Program log: `
+    /// prefix added by the runtime
+    ///
+    /// Records without `raw` and `overhead`, logged before the calibration,
+    /// parse as uncorrected.
+    pub fn parse(log: &str) -> Option<Self> {
+        let log = log.strip_prefix("Program log: ").unwrap_or(log);
+        let mut parts = log.strip_prefix(LOG_PREFIX)?.split_whitespace();
+        let consumed = parts.next()?.parse().ok()?;
+        let mut label = None;
+        let mut count = 1;
+        let (mut raw, mut overhead) = (None, None);
+        let (mut allocated, mut peak, mut allocations) = (None, None, None);
+        for part in parts {
+            match part.split_once('=')? {
+                ("label", value) => label = Some(value.to_string()),
+                ("raw", value) => raw = Some(value.parse().ok()?),
+                ("overhead", value) => overhead = Some(value.parse().ok()?),
+                ("count", value) => count = value.parse().ok()?,
+                ("heap_allocated", value) => allocated = Some(value.parse().ok()?),
+                ("heap_peak", value) => peak = Some(value.parse().ok()?),
+                ("heap_allocations", value) => allocations = Some(value.parse().ok()?),
+                _ => {}
+            }
+        }
+        let heap = match (allocated, peak, allocations) {
+            (Some(allocated), Some(peak), Some(allocations)) => Some(HeapUsage {
+                allocated,
+                peak,
+                allocations,
+            }),
+            _ => None,
+        };
+        Some(Self {
+            label: label?,
+            consumed,
+            raw: raw.unwrap_or(consumed),
+            overhead: overhead.unwrap_or_default(),
+            count,
+            heap,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        assert_eq!(
+            BenchRecord::parse(
+                "Program log: cu_bench_consumed 560 label=sqrt_u64 raw=662 overhead=102"
+            ),
+            Some(BenchRecord {
+                label: "sqrt_u64".to_string(),
+                consumed: 560,
+                raw: 662,
+                overhead: 102,
+                count: 1,
+                heap: None,
+            })
+        );
+        assert_eq!(
+            BenchRecord::parse("cu_bench_consumed 0 label=noop raw=102 overhead=102"),
+            Some(BenchRecord {
+                label: "noop".to_string(),
+                consumed: 0,
+                raw: 102,
+                overhead: 102,
+                count: 1,
+                heap: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_record_with_heap_usage() {
+        assert_eq!(
+            BenchRecord::parse(
+                "Program log: cu_bench_consumed 288153 label=precise_sqrt_newton raw=288255 \
+                 overhead=102 heap_allocated=256 heap_peak=256 heap_allocations=1"
+            ),
+            Some(BenchRecord {
+                label: "precise_sqrt_newton".to_string(),
+                consumed: 288153,
+                raw: 288255,
+                overhead: 102,
+                count: 1,
+                heap: Some(HeapUsage {
+                    allocated: 256,
+                    peak: 256,
+                    allocations: 1,
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_uncalibrated_record() {
+        let record = BenchRecord::parse("cu_bench_consumed 7 label=u64_multiply").unwrap();
+        assert_eq!(record.raw, 7);
+        assert_eq!(record.overhead, 0);
+    }
+
+    #[test]
+    fn test_calibrated_measurement() {
+        // off-chain there is no compute meter, so nothing is subtracted
+        let bench = Bench::calibrate();
+        assert_eq!(bench.overhead, 0);
+        assert_eq!(bench.measure_units("noop", || 42), (42, 0));
+    }
+
+    #[test]
+    fn test_parse_batch_record() {
+        let record = BenchRecord::parse(
+            "Program log: cu_bench_consumed 1000 label=batch_u64_multiply raw=1102 \
+             overhead=102 count=16 per_op=62",
+        )
+        .unwrap();
+        assert_eq!(record.count, 16);
+        assert_eq!(record.per_op(), 62);
+        assert_eq!(batch_fields(1000, 16), " count=16 per_op=62");
+        assert_eq!(batch_fields(1000, 1), "");
+    }
+
+    #[test]
+    fn test_parse_record_rejects_other_logs() {
+        assert_eq!(BenchRecord::parse("Program log: Perform NOOP"), None);
+        assert_eq!(
+            BenchRecord::parse("Program log: cu_bench_consumed 12"),
+            None
+        );
+        assert_eq!(
+            BenchRecord::parse("Program log: cu_bench_consumed many label=noop"),
+            None
+        );
+    }
+}
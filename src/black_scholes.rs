@@ -0,0 +1,368 @@
+//! Black-Scholes valuation of European options, composed of the logarithm,
+//! exponential, square root and normal distribution
+//!
+//! The f64 valuation uses `approximations::f32_normal_cdf`, so its price and
+//! delta are only as accurate as that approximation, about `1e-3`. The
+//! fixed-point valuation works in the 36-decimal intermediates of the `fixed`
+//! and `normal` modules and rounds each result to the nearest unit in the
+//! last place of `PreciseNumber`.
+
+use crate::{
+    approximations::f32_normal_cdf,
+    bench::Bench,
+    error::MathError,
+    fixed::{wide_exp, wide_ln, wide_one, widening, PRECISE_DECIMALS, WIDE_DECIMALS},
+    instruction::{BlackScholesInputs, MathResult, OptionKind},
+    normal::{narrow, signed_add, wide_cdf, wide_pdf},
+    precise_number::PreciseNumber,
+    processor::not_nan,
+    uint::U256,
+};
+
+/// `1 / sqrt(2 pi)`
+const F64_INV_SQRT_2PI: f64 = 0.398_942_280_401_432_7;
+
+/// Market inputs of a valuation
+///
+/// The rate is annual and continuously compounded, the volatility annual and
+/// the time to expiry in years.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Market<T> {
+    /// Price of the underlying
+    pub spot: T,
+    /// Strike price
+    pub strike: T,
+    /// Risk-free rate
+    pub rate: T,
+    /// Volatility of the underlying
+    pub volatility: T,
+    /// Time to expiry
+    pub time: T,
+}
+
+/// Price and sensitivities of a European option
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Valuation<T> {
+    /// Price of the option
+    pub price: T,
+    /// Sensitivity of the price to the spot; for a put it is negative, and a
+    /// `PreciseNumber` holds its magnitude
+    pub delta: T,
+    /// Sensitivity of the price to the volatility, per unit of volatility
+    pub vega: T,
+}
+
+/// Black-Scholes valuation in f64
+///
+/// Inputs outside the domain, like a zero time, give non-finite results.
+pub fn f64_black_scholes(option: OptionKind, market: &Market<f64>) -> Valuation<f64> {
+    let cdf = |x: f64| f32_normal_cdf(x as f32) as f64;
+    let sqrt_time = market.time.sqrt();
+    let deviation = market.volatility * sqrt_time;
+    let d1 = ((market.spot / market.strike).ln()
+        + (market.rate + market.volatility * market.volatility / 2.0) * market.time)
+        / deviation;
+    let d2 = d1 - deviation;
+    let discounted_strike = market.strike * (-market.rate * market.time).exp();
+    let (price, delta) = match option {
+        OptionKind::Call => (market.spot * cdf(d1) - discounted_strike * cdf(d2), cdf(d1)),
+        OptionKind::Put => (
+            discounted_strike * cdf(-d2) - market.spot * cdf(-d1),
+            -cdf(-d1),
+        ),
+    };
+    let vega = market.spot * F64_INV_SQRT_2PI * (-d1 * d1 / 2.0).exp() * sqrt_time;
+    Valuation { price, delta, vega }
+}
+
+/// `lhs * rhs` in the intermediate precision, for an `lhs` of any size and an
+/// `rhs` up to about `1e5`
+fn mul(lhs: U256, rhs: U256) -> Option<U256> {
+    let one = wide_one();
+    (lhs / one)
+        .checked_mul(rhs)?
+        .checked_add((lhs % one).checked_mul(rhs)? / one)
+}
+
+/// Black-Scholes valuation in `PreciseNumber`
+///
+/// Returns `None` if the spot, strike, volatility or time is zero, or on
+/// overflow.
+pub fn precise_black_scholes(
+    option: OptionKind,
+    market: &Market<PreciseNumber>,
+) -> Option<Valuation<PreciseNumber>> {
+    let one = wide_one();
+    let widen = |x: &PreciseNumber| x.value.checked_mul(widening(PRECISE_DECIMALS));
+    let spot = widen(&market.spot)?;
+    let strike = widen(&market.strike)?;
+    let rate = widen(&market.rate)?;
+    let volatility = widen(&market.volatility)?;
+    let time = widen(&market.time)?;
+
+    // ln(S / K) as ln S - ln K, so the ratio cannot overflow
+    let (ln_spot, spot_below_one) = wide_ln(market.spot.value, PRECISE_DECIMALS)?;
+    let (ln_strike, strike_below_one) = wide_ln(market.strike.value, PRECISE_DECIMALS)?;
+    let log_moneyness = signed_add((ln_spot, spot_below_one), (ln_strike, !strike_below_one));
+
+    // d1 = (ln(S / K) + (r + v^2 / 2) T) / (v sqrt T), d2 = d1 - v sqrt T
+    let drift = mul(rate.checked_add(mul(volatility, volatility)? / 2)?, time)?;
+    let sqrt_time = time.checked_mul(one)?.integer_sqrt();
+    let deviation = mul(volatility, sqrt_time)?;
+    if deviation.is_zero() {
+        return None;
+    }
+    let (numerator, negative) = signed_add(log_moneyness, (drift, false));
+    let d1 = (numerator.checked_mul(one)? / deviation, negative);
+    let d2 = signed_add(d1, (deviation, true));
+
+    let discounted_strike = mul(strike, wide_exp(mul(rate, time)?, true, WIDE_DECIMALS)?)?;
+    // prices are clamped at zero, where rounding could take them below
+    let (price, delta) = match option {
+        OptionKind::Call => {
+            let delta = wide_cdf(d1.0, d1.1);
+            let price =
+                mul(spot, delta)?.saturating_sub(mul(discounted_strike, wide_cdf(d2.0, d2.1))?);
+            (price, delta)
+        }
+        OptionKind::Put => {
+            let delta = wide_cdf(d1.0, !d1.1);
+            let price =
+                mul(discounted_strike, wide_cdf(d2.0, !d2.1))?.saturating_sub(mul(spot, delta)?);
+            (price, delta)
+        }
+    };
+    let vega = mul(mul(spot, wide_pdf(d1.0))?, sqrt_time)?;
+    Some(Valuation {
+        price: narrow(price)?,
+        delta: narrow(delta)?,
+        vega: narrow(vega)?,
+    })
+}
+
+/// Value the option in the arithmetic selected by the inputs
+pub(crate) fn process_black_scholes(
+    bench: &Bench,
+    option: OptionKind,
+    inputs: BlackScholesInputs,
+) -> Result<MathResult, MathError> {
+    let label = |prefix: &str| {
+        let kind = match option {
+            OptionKind::Call => "call",
+            OptionKind::Put => "put",
+        };
+        format!("{}_black_scholes_{}", prefix, kind)
+    };
+    match inputs {
+        BlackScholesInputs::F64 {
+            spot,
+            strike,
+            rate,
+            volatility,
+            time,
+        } => {
+            let market = Market {
+                spot: not_nan(spot)?,
+                strike: not_nan(strike)?,
+                rate: not_nan(rate)?,
+                volatility: not_nan(volatility)?,
+                time: not_nan(time)?,
+            };
+            let positive = [spot, strike, volatility, time]
+                .iter()
+                .all(|input| *input > 0.0 && input.is_finite());
+            if !positive || !(0.0..f64::INFINITY).contains(&rate) {
+                return Err(MathError::DomainError);
+            }
+            let valuation = bench.measure(&label("f64"), || f64_black_scholes(option, &market));
+            Ok(MathResult::BlackScholesF64 {
+                price: not_nan(valuation.price)?,
+                delta: not_nan(valuation.delta)?,
+                vega: not_nan(valuation.vega)?,
+            })
+        }
+        BlackScholesInputs::Precise {
+            spot,
+            strike,
+            rate,
+            volatility,
+            time,
+        } => {
+            if [spot, strike, volatility, time].contains(&[0; 4]) {
+                return Err(MathError::DomainError);
+            }
+            let precise = |value| PreciseNumber { value: U256(value) };
+            let market = Market {
+                spot: precise(spot),
+                strike: precise(strike),
+                rate: precise(rate),
+                volatility: precise(volatility),
+                time: precise(time),
+            };
+            let valuation = bench
+                .measure(&label("precise"), || precise_black_scholes(option, &market))
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::BlackScholesPrecise {
+                price: valuation.price.value.0,
+                delta: valuation.delta.value.0,
+                vega: valuation.vega.value.0,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::precise_number::ONE};
+
+    /// Inner value of `units / 1000`
+    fn thousandths(units: u128) -> U256 {
+        U256::from(units) * U256::from(ONE) / U256::from(1000)
+    }
+
+    fn market(
+        spot: u128,
+        strike: u128,
+        rate: u128,
+        volatility: u128,
+        time: u128,
+    ) -> Market<PreciseNumber> {
+        let precise = |units| PreciseNumber {
+            value: thousandths(units),
+        };
+        Market {
+            spot: precise(spot),
+            strike: precise(strike),
+            rate: precise(rate),
+            volatility: precise(volatility),
+            time: precise(time),
+        }
+    }
+
+    /// Inner values of the price, delta and vega
+    fn inner(option: OptionKind, market: &Market<PreciseNumber>) -> (u128, u128, u128) {
+        let valuation = precise_black_scholes(option, market).unwrap();
+        (
+            valuation.price.value.as_u128(),
+            valuation.delta.value.as_u128(),
+            valuation.vega.value.as_u128(),
+        )
+    }
+
+    #[test]
+    fn test_precise_black_scholes() {
+        // spot 100, strike 100, 5%, 20%, one year
+        let at_the_money = market(100_000, 100_000, 50, 200, 1_000);
+        assert_eq!(
+            inner(OptionKind::Call, &at_the_money),
+            (10_450_583_572_186, 636_830_651_176, 37_524_034_691_694)
+        );
+        assert_eq!(
+            inner(OptionKind::Put, &at_the_money),
+            (5_573_526_022_257, 363_169_348_824, 37_524_034_691_694)
+        );
+
+        // spot 42, strike 40, 10%, 20%, half a year
+        let in_the_money = market(42_000, 40_000, 100, 200, 500);
+        assert_eq!(
+            inner(OptionKind::Call, &in_the_money),
+            (4_759_422_392_872, 779_131_290_943, 8_813_415_059_603)
+        );
+        assert_eq!(inner(OptionKind::Put, &in_the_money).0, 808_599_372_900);
+
+        // without a rate, calls and puts at the money are worth the same
+        let no_rate = market(100_000, 100_000, 0, 300, 250);
+        assert_eq!(inner(OptionKind::Call, &no_rate).0, 5_978_528_810_579);
+        assert_eq!(inner(OptionKind::Put, &no_rate).0, 5_978_528_810_579);
+
+        // spot 1, strike 1000: worthless call, put worth the discounted strike
+        // less the spot
+        let far = market(1_000, 1_000_000, 10, 500, 2_000);
+        assert_eq!(inner(OptionKind::Call, &far), (0, 0, 0));
+        assert_eq!(inner(OptionKind::Put, &far), (979_198_673_306_755, ONE, 0));
+    }
+
+    #[test]
+    fn test_precise_put_call_parity() {
+        // C - P = S - K e^-rT, up to the rounding of both prices
+        for (spot, strike, rate, volatility, time) in [
+            (100_000, 90_000, 30, 150, 750),
+            (1_000, 1_200, 0, 800, 100),
+            (25_000_000, 24_000_000, 45, 600, 3_000),
+        ] {
+            let market = market(spot, strike, rate, volatility, time);
+            let call = inner(OptionKind::Call, &market).0 as i128;
+            let put = inner(OptionKind::Put, &market).0 as i128;
+            let discount = (-(rate as f64 / 1000.) * (time as f64 / 1000.)).exp();
+            let forward = (spot as f64 - strike as f64 * discount) / 1000. * ONE as f64;
+            assert!(
+                ((call - put) as f64 - forward).abs() <= 2. + forward.abs() * 1e-15,
+                "{} {}",
+                call - put,
+                forward
+            );
+        }
+    }
+
+    #[test]
+    fn test_f64_black_scholes() {
+        let market = Market {
+            spot: 100.,
+            strike: 100.,
+            rate: 0.05,
+            volatility: 0.2,
+            time: 1.,
+        };
+        let call = f64_black_scholes(OptionKind::Call, &market);
+        let put = f64_black_scholes(OptionKind::Put, &market);
+        // as accurate as `f32_normal_cdf`
+        assert!(
+            (call.price - 10.450_583_572_186).abs() <= 0.1,
+            "{}",
+            call.price
+        );
+        assert!(
+            (call.delta - 0.636_830_651_176).abs() <= 1e-3,
+            "{}",
+            call.delta
+        );
+        // parity holds for any symmetric CDF, however approximate
+        assert!((call.price - put.price - (100. - 100. * (-0.05_f64).exp())).abs() < 1e-9);
+        assert!((call.delta - put.delta - 1.).abs() < 1e-6);
+        // vega does not go through the CDF
+        assert!((call.vega - 37.524_034_691_693_79).abs() < 1e-9);
+        assert_eq!(call.vega, put.vega);
+    }
+
+    #[test]
+    fn test_process_black_scholes() {
+        let run = |inputs| process_black_scholes(&Bench::default(), OptionKind::Call, inputs);
+        let f64_inputs = |rate, time| BlackScholesInputs::F64 {
+            spot: 100.,
+            strike: 100.,
+            rate,
+            volatility: 0.2,
+            time,
+        };
+        assert_eq!(run(f64_inputs(0.05, 0.)), Err(MathError::DomainError));
+        assert_eq!(run(f64_inputs(-0.05, 1.)), Err(MathError::DomainError));
+        assert_eq!(run(f64_inputs(0.05, f64::NAN)), Err(MathError::NotANumber));
+
+        let precise_inputs = |time: u128| BlackScholesInputs::Precise {
+            spot: thousandths(100_000).0,
+            strike: thousandths(100_000).0,
+            rate: thousandths(50).0,
+            volatility: thousandths(200).0,
+            time: thousandths(time).0,
+        };
+        assert_eq!(
+            run(precise_inputs(1_000)),
+            Ok(MathResult::BlackScholesPrecise {
+                price: U256::from(10_450_583_572_186u128).0,
+                delta: U256::from(636_830_651_176u128).0,
+                vega: U256::from(37_524_034_691_694u128).0,
+            })
+        );
+        assert_eq!(run(precise_inputs(0)), Err(MathError::DomainError));
+    }
+}
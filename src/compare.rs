@@ -0,0 +1,130 @@
+//! Side-by-side runs of the operations shared by the original spl-math and
+//! spl-math-evolved
+
+use crate::{
+    bench::Bench,
+    d18,
+    error::MathError,
+    instruction::{ComparedOperation, Implementation, MathResult},
+};
+
+/// Run `operation` with the evolved implementation
+fn run_evolved(bench: &Bench, operation: ComparedOperation) -> Result<MathResult, MathError> {
+    use spl_math::{
+        approximations::{f32_normal_cdf, sqrt},
+        precise_number::PreciseNumber,
+    };
+    match operation {
+        ComparedOperation::SquareRootU64 { radicand } => {
+            let result = bench
+                .measure("evolved_sqrt_u64", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U64(result))
+        }
+        ComparedOperation::SquareRootU128 { radicand } => {
+            let result = bench
+                .measure("evolved_sqrt_u128", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U128(result))
+        }
+        ComparedOperation::MulDivFloor { val, num, denom } => {
+            let val = PreciseNumber::new(val as u128).ok_or(MathError::ConversionFailed)?;
+            let num = PreciseNumber::new(num as u128).ok_or(MathError::ConversionFailed)?;
+            let denom = PreciseNumber::new(denom as u128).ok_or(MathError::ConversionFailed)?;
+            let result = bench
+                .measure("evolved_mul_div_floor", || val.mul_div_floor(num, denom))
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::Precise(result.value.0))
+        }
+        ComparedOperation::SqrtNewton { radicand } => {
+            let radicand = d18::from_u128(radicand as u128).ok_or(MathError::ConversionFailed)?;
+            let result = bench
+                .measure("evolved_sqrt_newton", || radicand.sqrt_newton())
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::Precise(result.value.0))
+        }
+        ComparedOperation::NormalCdf { argument } => {
+            let result = bench.measure("evolved_normal_cdf", || f32_normal_cdf(argument));
+            Ok(MathResult::F32(result))
+        }
+    }
+}
+
+/// Run `operation` with the original implementation
+#[cfg(feature = "compare-upstream")]
+fn run_upstream(bench: &Bench, operation: ComparedOperation) -> Result<MathResult, MathError> {
+    use spl_math_upstream::{
+        approximations::{f32_normal_cdf, sqrt},
+        precise_number::PreciseNumber,
+    };
+    match operation {
+        ComparedOperation::SquareRootU64 { radicand } => {
+            let result = bench
+                .measure("upstream_sqrt_u64", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U64(result))
+        }
+        ComparedOperation::SquareRootU128 { radicand } => {
+            let result = bench
+                .measure("upstream_sqrt_u128", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::U128(result))
+        }
+        ComparedOperation::MulDivFloor { val, num, denom } => {
+            let val = PreciseNumber::new(val as u128).ok_or(MathError::ConversionFailed)?;
+            let num = PreciseNumber::new(num as u128).ok_or(MathError::ConversionFailed)?;
+            let denom = PreciseNumber::new(denom as u128).ok_or(MathError::ConversionFailed)?;
+            // upstream has no mul_div_floor, and its checked_mul and
+            // checked_div round half up, so floor the quotient of the inner
+            // values like the evolved implementation
+            let result = bench
+                .measure("upstream_mul_div_floor", || {
+                    let value = val.value.checked_mul(num.value)?.checked_div(denom.value)?;
+                    Some(PreciseNumber { value })
+                })
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::Precise(result.value.0))
+        }
+        ComparedOperation::SqrtNewton { radicand } => {
+            let radicand =
+                PreciseNumber::new(radicand as u128).ok_or(MathError::ConversionFailed)?;
+            let result = bench
+                .measure("upstream_sqrt_newton", || radicand.sqrt())
+                .ok_or(MathError::Overflow)?;
+            Ok(MathResult::Precise(result.value.0))
+        }
+        ComparedOperation::NormalCdf { argument } => {
+            let result = bench.measure("upstream_normal_cdf", || f32_normal_cdf(argument));
+            Ok(MathResult::F32(result))
+        }
+    }
+}
+
+/// Run `operation` with the original implementation
+#[cfg(not(feature = "compare-upstream"))]
+fn run_upstream(_bench: &Bench, _operation: ComparedOperation) -> Result<MathResult, MathError> {
+    solana_program::msg!("Build with the compare-upstream feature to run the original spl-math");
+    Err(MathError::ImplementationUnavailable)
+}
+
+/// Run `operation` with the selected implementation
+///
+/// `SqrtNewton` results carry 18 decimals for the evolved implementation and
+/// 12 decimals for the original one.
+pub(crate) fn process_compare(
+    bench: &Bench,
+    implementation: Implementation,
+    operation: ComparedOperation,
+) -> Result<MathResult, MathError> {
+    match operation {
+        ComparedOperation::MulDivFloor { denom: 0, .. } => return Err(MathError::DivisionByZero),
+        ComparedOperation::NormalCdf { argument } if argument.is_nan() => {
+            return Err(MathError::NotANumber)
+        }
+        _ => {}
+    }
+    match implementation {
+        Implementation::Evolved => run_evolved(bench, operation),
+        Implementation::Upstream => run_upstream(bench, operation),
+    }
+}
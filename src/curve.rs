@@ -0,0 +1,445 @@
+//! Constant-product pool math: swaps with an exact amount in or out, and
+//! deposits and withdrawals of pool tokens
+//!
+//! Amounts are u64 token amounts, computed in u128 so products of two
+//! amounts cannot overflow. Every rounding favors the pool: fees and amounts
+//! owed to the pool are rounded up, amounts paid out rounded down, so the
+//! product of the reserves never decreases. Ceilings use `u128::div_ceil`
+//! rather than `CheckedCeilDiv`, which rounds quotients below 1 to the
+//! nearest and would let a fee of less than one token round to zero.
+
+use crate::{
+    bench::Bench,
+    error::MathError,
+    instruction::{CurveOperation, MathResult, TradeFee},
+};
+
+/// Amounts of a swap
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Swap {
+    /// Amount given to the pool, fee included
+    pub amount_in: u64,
+    /// Amount taken from the pool
+    pub amount_out: u64,
+    /// Part of the amount in kept as the fee
+    pub fee: u64,
+}
+
+/// `numerator / denominator` rounded up; `None` if `denominator` is zero
+fn div_ceil(numerator: u128, denominator: u128) -> Option<u128> {
+    (denominator != 0).then(|| numerator.div_ceil(denominator))
+}
+
+/// Whether the fee is a fraction below 1, or no fee at all
+fn is_valid_fee(fee: &TradeFee) -> bool {
+    fee.numerator == 0 || fee.numerator < fee.denominator
+}
+
+/// Swap `amount_in`, fee included, for as much as the pool gives
+///
+/// The fee is rounded up and the amount out down. Returns `None` for an
+/// empty pool or a fee of 1 or more.
+pub fn swap_exact_in(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: &TradeFee,
+) -> Option<Swap> {
+    if reserve_in == 0 || reserve_out == 0 || !is_valid_fee(fee) {
+        return None;
+    }
+    let fee_amount = if fee.numerator == 0 {
+        0
+    } else {
+        div_ceil(
+            amount_in as u128 * fee.numerator as u128,
+            fee.denominator as u128,
+        )?
+    };
+    // out = reserve_out * net / (reserve_in + net)
+    let net = amount_in as u128 - fee_amount;
+    let amount_out = reserve_out as u128 * net / (reserve_in as u128 + net);
+    Some(Swap {
+        amount_in,
+        amount_out: u64::try_from(amount_out).ok()?,
+        fee: u64::try_from(fee_amount).ok()?,
+    })
+}
+
+/// Swap as little as the pool takes, fee included, for `amount_out`
+///
+/// The amount in before the fee, and then with it, are rounded up. Returns
+/// `None` for an empty pool, an amount out that would empty it, a fee of 1
+/// or more, or an amount in beyond u64.
+pub fn swap_exact_out(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: &TradeFee,
+) -> Option<Swap> {
+    if reserve_in == 0 || amount_out >= reserve_out || !is_valid_fee(fee) {
+        return None;
+    }
+    // net = reserve_in * out / (reserve_out - out)
+    let net = div_ceil(
+        reserve_in as u128 * amount_out as u128,
+        (reserve_out - amount_out) as u128,
+    )?;
+    let amount_in = if fee.numerator == 0 {
+        net
+    } else {
+        div_ceil(
+            net.checked_mul(fee.denominator as u128)?,
+            (fee.denominator - fee.numerator) as u128,
+        )?
+    };
+    Some(Swap {
+        amount_in: u64::try_from(amount_in).ok()?,
+        amount_out,
+        fee: u64::try_from(amount_in - net).ok()?,
+    })
+}
+
+/// Share of a reserve for the given pool tokens, rounded up if `ceil`
+fn share(pool_tokens: u64, pool_supply: u64, reserve: u64, ceil: bool) -> Option<u64> {
+    let numerator = pool_tokens as u128 * reserve as u128;
+    let share = if ceil {
+        div_ceil(numerator, pool_supply as u128)?
+    } else {
+        numerator.checked_div(pool_supply as u128)?
+    };
+    u64::try_from(share).ok()
+}
+
+/// Amounts of tokens A and B to deposit to mint `pool_tokens`, rounded up
+///
+/// Returns `None` if there are no pool tokens, or an amount beyond u64.
+pub fn deposit(
+    pool_tokens: u64,
+    pool_supply: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+) -> Option<(u64, u64)> {
+    Some((
+        share(pool_tokens, pool_supply, reserve_a, true)?,
+        share(pool_tokens, pool_supply, reserve_b, true)?,
+    ))
+}
+
+/// Amounts of tokens A and B withdrawn by burning `pool_tokens`, rounded
+/// down
+///
+/// Returns `None` if there are no pool tokens or more are burnt than exist.
+pub fn withdraw(
+    pool_tokens: u64,
+    pool_supply: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+) -> Option<(u64, u64)> {
+    if pool_tokens > pool_supply {
+        return None;
+    }
+    Some((
+        share(pool_tokens, pool_supply, reserve_a, false)?,
+        share(pool_tokens, pool_supply, reserve_b, false)?,
+    ))
+}
+
+/// Result of a swap
+fn swap_result(swap: Swap) -> MathResult {
+    MathResult::Swap {
+        amount_in: swap.amount_in,
+        amount_out: swap.amount_out,
+        fee: swap.fee,
+    }
+}
+
+/// Result of a deposit or withdrawal
+fn pair_result((token_a, token_b): (u64, u64)) -> MathResult {
+    MathResult::TokenPair { token_a, token_b }
+}
+
+/// Run the operation, after rejecting the inputs it has no answer for
+pub(crate) fn process_curve(
+    bench: &Bench,
+    operation: CurveOperation,
+) -> Result<MathResult, MathError> {
+    match operation {
+        CurveOperation::SwapExactIn {
+            amount_in,
+            reserve_in,
+            reserve_out,
+            fee,
+        } => {
+            if reserve_in == 0 || reserve_out == 0 || !is_valid_fee(&fee) {
+                return Err(MathError::InvalidArgument);
+            }
+            bench
+                .measure("curve_swap_exact_in", || {
+                    swap_exact_in(amount_in, reserve_in, reserve_out, &fee)
+                })
+                .map(swap_result)
+                .ok_or(MathError::Overflow)
+        }
+        CurveOperation::SwapExactOut {
+            amount_out,
+            reserve_in,
+            reserve_out,
+            fee,
+        } => {
+            if reserve_in == 0 || amount_out >= reserve_out || !is_valid_fee(&fee) {
+                return Err(MathError::InvalidArgument);
+            }
+            bench
+                .measure("curve_swap_exact_out", || {
+                    swap_exact_out(amount_out, reserve_in, reserve_out, &fee)
+                })
+                .map(swap_result)
+                .ok_or(MathError::Overflow)
+        }
+        CurveOperation::Deposit {
+            pool_tokens,
+            pool_supply,
+            reserve_a,
+            reserve_b,
+        } => {
+            if pool_supply == 0 {
+                return Err(MathError::DivisionByZero);
+            }
+            bench
+                .measure("curve_deposit", || {
+                    deposit(pool_tokens, pool_supply, reserve_a, reserve_b)
+                })
+                .map(pair_result)
+                .ok_or(MathError::Overflow)
+        }
+        CurveOperation::Withdraw {
+            pool_tokens,
+            pool_supply,
+            reserve_a,
+            reserve_b,
+        } => {
+            if pool_supply == 0 {
+                return Err(MathError::DivisionByZero);
+            }
+            if pool_tokens > pool_supply {
+                return Err(MathError::InvalidArgument);
+            }
+            bench
+                .measure("curve_withdraw", || {
+                    withdraw(pool_tokens, pool_supply, reserve_a, reserve_b)
+                })
+                .map(pair_result)
+                .ok_or(MathError::Overflow)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, proptest::prelude::*};
+
+    /// 0.3%
+    const FEE: TradeFee = TradeFee {
+        numerator: 3,
+        denominator: 1000,
+    };
+
+    const NO_FEE: TradeFee = TradeFee {
+        numerator: 0,
+        denominator: 0,
+    };
+
+    #[test]
+    fn test_swap_exact_in() {
+        // fee 3, then 1_000_000 * 997 / 1_000_997 = 996.00...
+        assert_eq!(
+            swap_exact_in(1_000, 1_000_000, 1_000_000, &FEE),
+            Some(Swap {
+                amount_in: 1_000,
+                amount_out: 996,
+                fee: 3
+            })
+        );
+        // a fee of 0.003 tokens is still 1
+        assert_eq!(
+            swap_exact_in(1, 1_000_000, 1_000_000, &FEE),
+            Some(Swap {
+                amount_in: 1,
+                amount_out: 0,
+                fee: 1
+            })
+        );
+        assert_eq!(
+            swap_exact_in(u64::MAX, u64::MAX, u64::MAX, &NO_FEE).map(|swap| swap.amount_out),
+            Some(u64::MAX / 2)
+        );
+        assert_eq!(swap_exact_in(1_000, 0, 1_000_000, &FEE), None);
+        let whole = TradeFee {
+            numerator: 1,
+            denominator: 1,
+        };
+        assert_eq!(swap_exact_in(1_000, 1_000_000, 1_000_000, &whole), None);
+    }
+
+    #[test]
+    fn test_swap_exact_out() {
+        // 1_000_000 * 996 / 999_004 = 996.99..., then 997 / 0.997 = 1000
+        assert_eq!(
+            swap_exact_out(996, 1_000_000, 1_000_000, &FEE),
+            Some(Swap {
+                amount_in: 1_000,
+                amount_out: 996,
+                fee: 3
+            })
+        );
+        assert_eq!(
+            swap_exact_out(500, 1_000, 1_000, &NO_FEE),
+            Some(Swap {
+                amount_in: 1_000,
+                amount_out: 500,
+                fee: 0
+            })
+        );
+        assert_eq!(swap_exact_out(1_000, 1_000, 1_000, &NO_FEE), None);
+        assert_eq!(
+            swap_exact_out(u64::MAX - 1, u64::MAX, u64::MAX, &NO_FEE),
+            None
+        );
+    }
+
+    #[test]
+    fn test_deposit_withdraw() {
+        // a tenth of a pool of 1_001 and 333
+        assert_eq!(deposit(10, 100, 1_001, 333), Some((101, 34)));
+        assert_eq!(withdraw(10, 100, 1_001, 333), Some((100, 33)));
+        assert_eq!(withdraw(100, 100, 1_001, 333), Some((1_001, 333)));
+        assert_eq!(withdraw(101, 100, 1_001, 333), None);
+        assert_eq!(deposit(10, 0, 1_001, 333), None);
+        assert_eq!(deposit(u64::MAX, 1, 2, 2), None);
+    }
+
+    #[test]
+    fn test_process_curve() {
+        let run = |operation| process_curve(&Bench::default(), operation);
+        assert_eq!(
+            run(CurveOperation::SwapExactIn {
+                amount_in: 1_000,
+                reserve_in: 1_000_000,
+                reserve_out: 1_000_000,
+                fee: FEE
+            }),
+            Ok(MathResult::Swap {
+                amount_in: 1_000,
+                amount_out: 996,
+                fee: 3
+            })
+        );
+        assert_eq!(
+            run(CurveOperation::SwapExactOut {
+                amount_out: 1_000_000,
+                reserve_in: 1_000_000,
+                reserve_out: 1_000_000,
+                fee: FEE
+            }),
+            Err(MathError::InvalidArgument)
+        );
+        assert_eq!(
+            run(CurveOperation::SwapExactOut {
+                amount_out: u64::MAX - 1,
+                reserve_in: u64::MAX,
+                reserve_out: u64::MAX,
+                fee: FEE
+            }),
+            Err(MathError::Overflow)
+        );
+        assert_eq!(
+            run(CurveOperation::Deposit {
+                pool_tokens: 10,
+                pool_supply: 100,
+                reserve_a: 1_001,
+                reserve_b: 333
+            }),
+            Ok(MathResult::TokenPair {
+                token_a: 101,
+                token_b: 34
+            })
+        );
+        assert_eq!(
+            run(CurveOperation::Withdraw {
+                pool_tokens: 10,
+                pool_supply: 0,
+                reserve_a: 1_001,
+                reserve_b: 333
+            }),
+            Err(MathError::DivisionByZero)
+        );
+    }
+
+    fn fee() -> impl Strategy<Value = TradeFee> {
+        (1u64..=u32::MAX as u64).prop_flat_map(|denominator| {
+            (0..denominator).prop_map(move |numerator| TradeFee {
+                numerator,
+                denominator,
+            })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_swap_exact_in_keeps_invariant(
+            amount_in: u64,
+            reserve_in in 1u64..,
+            reserve_out in 1u64..,
+            fee in fee(),
+        ) {
+            let swap = swap_exact_in(amount_in, reserve_in, reserve_out, &fee).unwrap();
+            let before = reserve_in as u128 * reserve_out as u128;
+            // the fee stays in the pool, so leaving it out is conservative
+            let net = (swap.amount_in - swap.fee) as u128;
+            let after = (reserve_in as u128 + net) * (reserve_out - swap.amount_out) as u128;
+            prop_assert!(after >= before);
+            let fee_share = swap.fee as u128 * fee.denominator as u128;
+            prop_assert!(fee_share >= amount_in as u128 * fee.numerator as u128);
+        }
+
+        #[test]
+        fn test_swap_exact_out_is_enough(
+            reserve_in in 1u64..=u32::MAX as u64,
+            reserve_out in 2u64..=u32::MAX as u64,
+            amount_out: u64,
+            fee in fee(),
+        ) {
+            let amount_out = amount_out % reserve_out;
+            let swap = swap_exact_out(amount_out, reserve_in, reserve_out, &fee).unwrap();
+            // paying the amount in gives at least the amount out
+            let exact_in = swap_exact_in(swap.amount_in, reserve_in, reserve_out, &fee).unwrap();
+            prop_assert!(exact_in.amount_out >= amount_out);
+            // and one token less would not
+            if swap.amount_in > 0 {
+                let less = swap_exact_in(swap.amount_in - 1, reserve_in, reserve_out, &fee).unwrap();
+                prop_assert!(less.amount_out < amount_out);
+            }
+        }
+
+        #[test]
+        fn test_deposit_then_withdraw_never_profits(
+            pool_tokens: u32,
+            pool_supply in 1u32..,
+            reserve_a: u32,
+            reserve_b: u32,
+        ) {
+            let (pool_tokens, pool_supply) = (pool_tokens as u64, pool_supply as u64);
+            let (reserve_a, reserve_b) = (reserve_a as u64, reserve_b as u64);
+            let (a, b) = deposit(pool_tokens, pool_supply, reserve_a, reserve_b).unwrap();
+            let (withdrawn_a, withdrawn_b) = withdraw(
+                pool_tokens,
+                pool_supply + pool_tokens,
+                reserve_a + a,
+                reserve_b + b,
+            )
+            .unwrap();
+            prop_assert!(withdrawn_a <= a && withdrawn_b <= b);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Exact construction and directed rounding of `PreciseNumber256D18`, the
+//! 256-bit fixed-point number with 18 decimals
+//!
+//! Everything here works on the inner value, `value * 10^18`, so no input
+//! goes through a float. The products and quotients round down or up, where
+//! the type's own `checked_mul` and `checked_div` round to the nearest.
+
+use crate::{
+    error::MathError, instruction::D18Input, precise_number::PreciseNumber256D18, uint::U256,
+};
+
+/// Number of decimals of `PreciseNumber256D18`
+pub const DECIMALS: usize = 18;
+
+/// Inner value of 1
+pub const ONE: u128 = 1_000_000_000_000_000_000;
+
+/// `PreciseNumber256D18` with the given inner value
+pub fn from_inner(value: U256) -> PreciseNumber256D18 {
+    PreciseNumber256D18 { value }
+}
+
+/// `PreciseNumber256D18` with the given inner value, as little-endian u64
+/// limbs
+pub fn from_limbs(limbs: [u64; 4]) -> PreciseNumber256D18 {
+    from_inner(U256(limbs))
+}
+
+/// Exact `PreciseNumber256D18` of an integer
+pub fn from_u128(value: u128) -> Option<PreciseNumber256D18> {
+    U256::from(value)
+        .checked_mul(U256::from(ONE))
+        .map(from_inner)
+}
+
+/// Exact `PreciseNumber256D18` of a decimal string like `42`, `0.5` or
+/// `1234.000000000000000001`
+///
+/// Returns `None` for anything else, including signs, exponents and more
+/// than 18 decimals, which could not be represented exactly.
+pub fn from_decimal_str(value: &str) -> Option<PreciseNumber256D18> {
+    let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let is_digits = |digits: &str| digits.bytes().all(|digit| digit.is_ascii_digit());
+    if integer.is_empty()
+        || !is_digits(integer)
+        || !is_digits(fraction)
+        || fraction.len() > DECIMALS
+        || (value.contains('.') && fraction.is_empty())
+    {
+        return None;
+    }
+    let integer = U256::from_dec_str(integer).ok()?;
+    let fraction = if fraction.is_empty() {
+        U256::zero()
+    } else {
+        let scale = U256::from(10).pow(U256::from(DECIMALS - fraction.len()));
+        U256::from_dec_str(fraction).ok()?.checked_mul(scale)?
+    };
+    integer
+        .checked_mul(U256::from(ONE))?
+        .checked_add(fraction)
+        .map(from_inner)
+}
+
+impl D18Input {
+    /// Exact `PreciseNumber256D18` of the input
+    ///
+    /// A negative decimal is a `DomainError`, any other decimal that cannot be
+    /// represented exactly a `ConversionFailed`.
+    pub fn to_precise(&self) -> Result<PreciseNumber256D18, MathError> {
+        match self {
+            Self::U64(value) => from_u128(*value as u128).ok_or(MathError::ConversionFailed),
+            Self::U128(value) => from_u128(*value).ok_or(MathError::ConversionFailed),
+            Self::Limbs(limbs) => Ok(from_limbs(*limbs)),
+            Self::Decimal(value) => match value.strip_prefix('-').map(from_decimal_str) {
+                Some(Some(magnitude)) if !magnitude.value.is_zero() => Err(MathError::DomainError),
+                Some(magnitude) => magnitude.ok_or(MathError::ConversionFailed),
+                None => from_decimal_str(value).ok_or(MathError::ConversionFailed),
+            },
+        }
+    }
+}
+
+/// `numerator / denominator`, rounded up if `ceil`
+fn div_rounded(numerator: U256, denominator: U256, ceil: bool) -> Option<U256> {
+    let quotient = numerator.checked_div(denominator)?;
+    if ceil && !numerator.checked_rem(denominator)?.is_zero() {
+        quotient.checked_add(U256::one())
+    } else {
+        Some(quotient)
+    }
+}
+
+/// `lhs * rhs`, rounded down
+pub fn checked_mul_floor(
+    lhs: &PreciseNumber256D18,
+    rhs: &PreciseNumber256D18,
+) -> Option<PreciseNumber256D18> {
+    let product = lhs.value.checked_mul(rhs.value)?;
+    div_rounded(product, U256::from(ONE), false).map(from_inner)
+}
+
+/// `lhs * rhs`, rounded up
+pub fn checked_mul_ceil(
+    lhs: &PreciseNumber256D18,
+    rhs: &PreciseNumber256D18,
+) -> Option<PreciseNumber256D18> {
+    let product = lhs.value.checked_mul(rhs.value)?;
+    div_rounded(product, U256::from(ONE), true).map(from_inner)
+}
+
+/// `lhs / rhs`, rounded down; `None` if `rhs` is zero
+pub fn checked_div_floor(
+    lhs: &PreciseNumber256D18,
+    rhs: &PreciseNumber256D18,
+) -> Option<PreciseNumber256D18> {
+    let numerator = lhs.value.checked_mul(U256::from(ONE))?;
+    div_rounded(numerator, rhs.value, false).map(from_inner)
+}
+
+/// `lhs / rhs`, rounded up; `None` if `rhs` is zero
+pub fn checked_div_ceil(
+    lhs: &PreciseNumber256D18,
+    rhs: &PreciseNumber256D18,
+) -> Option<PreciseNumber256D18> {
+    let numerator = lhs.value.checked_mul(U256::from(ONE))?;
+    div_rounded(numerator, rhs.value, true).map(from_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inner(value: &str) -> U256 {
+        from_decimal_str(value).unwrap().value
+    }
+
+    #[test]
+    fn test_from_decimal_str() {
+        assert_eq!(inner("42"), U256::from(42 * ONE));
+        assert_eq!(inner("0.5"), U256::from(ONE / 2));
+        assert_eq!(inner("1.000000000000000001"), U256::from(ONE + 1));
+        assert_eq!(inner("007.250"), U256::from(7 * ONE + ONE / 4));
+        // beyond f64 precision
+        assert_eq!(
+            inner("18446744073709551615"),
+            U256::from(u64::MAX) * U256::from(ONE)
+        );
+        for invalid in [
+            "",
+            ".5",
+            "1.",
+            "-1",
+            "+1",
+            "1e3",
+            "1.2.3",
+            " 1",
+            "0.0000000000000000001",
+        ] {
+            assert!(from_decimal_str(invalid).is_none(), "{:?}", invalid);
+        }
+        assert_eq!(
+            from_u128(u128::MAX).unwrap().value,
+            U256::from(u128::MAX) * U256::from(ONE)
+        );
+    }
+
+    #[test]
+    fn test_input_to_precise() {
+        let value = |input: D18Input| input.to_precise().map(|value| value.value);
+        // exact beyond the 53 bits of an f64
+        assert_eq!(
+            value(D18Input::U64(u64::MAX)),
+            Ok(U256::from(u64::MAX) * U256::from(ONE))
+        );
+        assert_eq!(
+            value(D18Input::U128(u128::MAX)),
+            Ok(U256::from(u128::MAX) * U256::from(ONE))
+        );
+        assert_eq!(value(D18Input::Limbs([1, 2, 3, 4])), Ok(U256([1, 2, 3, 4])));
+        assert_eq!(
+            value(D18Input::Decimal("9007199254740993.5".to_string())),
+            Ok(U256::from(9_007_199_254_740_993_u128) * U256::from(ONE) + U256::from(ONE / 2))
+        );
+        assert_eq!(
+            value(D18Input::Decimal("-2.5".to_string())),
+            Err(MathError::DomainError)
+        );
+        assert_eq!(value(D18Input::Decimal("-0".to_string())), Ok(U256::zero()));
+        assert_eq!(
+            value(D18Input::Decimal("-x".to_string())),
+            Err(MathError::ConversionFailed)
+        );
+    }
+
+    #[test]
+    fn test_rounding() {
+        let third = checked_div_floor(&from_u128(1).unwrap(), &from_u128(3).unwrap()).unwrap();
+        assert_eq!(third.value, U256::from(ONE / 3));
+        let third_ceil = checked_div_ceil(&from_u128(1).unwrap(), &from_u128(3).unwrap()).unwrap();
+        assert_eq!(third_ceil.value, U256::from(ONE / 3 + 1));
+        // exact results are not rounded up
+        let half = checked_div_ceil(&from_u128(1).unwrap(), &from_u128(2).unwrap()).unwrap();
+        assert_eq!(half.value, U256::from(ONE / 2));
+
+        let tiny = from_inner(U256::one());
+        assert_eq!(checked_mul_floor(&tiny, &tiny).unwrap().value, U256::zero());
+        assert_eq!(checked_mul_ceil(&tiny, &tiny).unwrap().value, U256::one());
+        assert_eq!(
+            checked_mul_floor(&from_decimal_str("1.5").unwrap(), &from_u128(3).unwrap())
+                .unwrap()
+                .value,
+            inner("4.5")
+        );
+        assert!(checked_div_floor(&tiny, &from_inner(U256::zero())).is_none());
+    }
+}
@@ -15,6 +15,30 @@ pub enum MathError {
     /// Calculation underflowed the destination number
     #[error("Calculation underflowed the destination number")]
     Underflow,
+    /// Instruction data could not be deserialized
+    #[error("Instruction data could not be deserialized")]
+    InvalidInstruction,
+    /// Calculation attempted to divide by zero
+    #[error("Calculation attempted to divide by zero")]
+    DivisionByZero,
+    /// Input could not be converted to the required number type
+    #[error("Input could not be converted to the required number type")]
+    ConversionFailed,
+    /// Floating point input or result is not a number
+    #[error("Floating point input or result is not a number")]
+    NotANumber,
+    /// Input is outside the domain of the function
+    #[error("Input is outside the domain of the function")]
+    DomainError,
+    /// Implementation was not compiled into the program
+    #[error("Implementation was not compiled into the program")]
+    ImplementationUnavailable,
+    /// Instruction argument is outside the supported range
+    #[error("Instruction argument is outside the supported range")]
+    InvalidArgument,
+    /// Iteration did not converge within its cap
+    #[error("Iteration did not converge within its cap")]
+    NotConverged,
 }
 impl From<MathError> for ProgramError {
     fn from(e: MathError) -> Self {
@@ -33,5 +57,29 @@ mod tests {
 
         let program_error = ProgramError::from(MathError::Underflow);
         assert_eq!(program_error, ProgramError::Custom(1));
+
+        let program_error = ProgramError::from(MathError::InvalidInstruction);
+        assert_eq!(program_error, ProgramError::Custom(2));
+
+        let program_error = ProgramError::from(MathError::DivisionByZero);
+        assert_eq!(program_error, ProgramError::Custom(3));
+
+        let program_error = ProgramError::from(MathError::ConversionFailed);
+        assert_eq!(program_error, ProgramError::Custom(4));
+
+        let program_error = ProgramError::from(MathError::NotANumber);
+        assert_eq!(program_error, ProgramError::Custom(5));
+
+        let program_error = ProgramError::from(MathError::DomainError);
+        assert_eq!(program_error, ProgramError::Custom(6));
+
+        let program_error = ProgramError::from(MathError::ImplementationUnavailable);
+        assert_eq!(program_error, ProgramError::Custom(7));
+
+        let program_error = ProgramError::from(MathError::InvalidArgument);
+        assert_eq!(program_error, ProgramError::Custom(8));
+
+        let program_error = ProgramError::from(MathError::NotConverged);
+        assert_eq!(program_error, ProgramError::Custom(9));
     }
 }
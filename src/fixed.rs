@@ -0,0 +1,600 @@
+//! Deterministic exponential and logarithms of the fixed-point numbers, with
+//! integer arithmetic only
+//!
+//! Every function works on a 36-decimal intermediate, reduces its argument by
+//! powers of two, and sums a series:
+//!
+//! * `exp`: `e^x = 2^k * e^r` with `0 <= r < ln 2`, Taylor series of `e^r`
+//! * `ln`: `x = 2^k * m` with `1 <= m < 2`, `ln m = 2 * atanh((m - 1) / (m + 1))`
+//! * `log2`: `ln x / ln 2`
+//! * `pow`: `e^(exponent * ln base)`
+//!
+//! Error bounds, checked by the tests against constants known to more digits
+//! than the types carry: before the result is rounded to the nearest unit in
+//! the last place, `ln` and `log2` are within `1e-30` of the exact value,
+//! `exp` within a relative `1e-30`, and `pow` within a relative
+//! `1e-30 * max(1, exponent)`.
+//!
+//! The directed powers, for math that must round in one direction such as
+//! weighted-pool swaps, move the nearest power by ten times that bound plus
+//! a unit in the last place, so they are never above or below the exact
+//! value respectively.
+
+use crate::{
+    d18,
+    precise_number::{self, PreciseNumber, PreciseNumber256D18},
+    signed::Signed,
+    uint::U256,
+};
+
+/// Decimals of `PreciseNumber`
+pub(crate) const PRECISE_DECIMALS: usize = 12;
+
+// `PreciseNumber` is only known through its `ONE`
+const _: () = assert!(precise_number::ONE == 10u128.pow(PRECISE_DECIMALS as u32));
+
+/// Decimals of the intermediate results
+pub(crate) const WIDE_DECIMALS: usize = 36;
+
+/// `ln 2` with 36 decimals
+const LN_2: u128 = 693_147_180_559_945_309_417_232_121_458_176_568;
+
+/// `ln 10` with 36 decimals
+const LN_10: u128 = 2_302_585_092_994_045_684_017_991_454_684_364_208;
+
+/// Decimals of the relative error bound of `pow` used by the directed
+/// powers, ten times the proven `1e-30` so that it also covers the error of
+/// applying it to the rounded rather than the exact power
+const POW_ERROR_DECIMALS: usize = 29;
+
+/// Bits kept of the mantissa of a logarithm argument, a little more than the
+/// intermediate precision
+const MANTISSA_BITS: usize = 120;
+
+/// Exponential and logarithms of a fixed-point number
+///
+/// The types are unsigned, so a negative argument of the exponential is
+/// given as its magnitude, and the logarithms are `Signed`.
+pub trait FixedPointMath: Sized {
+    /// `e^self`; `None` if it does not fit
+    fn checked_exp(&self) -> Option<Self>;
+    /// `e^-self`, zero once it is below the last place
+    fn checked_exp_neg(&self) -> Option<Self>;
+    /// Natural logarithm; `None` for zero
+    fn checked_ln(&self) -> Option<Signed<Self>>;
+    /// Base 2 logarithm; `None` for zero
+    fn checked_log2(&self) -> Option<Signed<Self>>;
+    /// `self^exponent` for any non-negative exponent, integer or not; `None`
+    /// if it does not fit
+    fn checked_pow_fraction(&self, exponent: &Self) -> Option<Self>;
+    /// `self^exponent` rounded down, never above the exact value; `None` if
+    /// it does not fit
+    fn checked_pow_down(&self, exponent: &Self) -> Option<Self>;
+    /// `self^exponent` rounded up, never below the exact value; `None` if it
+    /// does not fit
+    fn checked_pow_up(&self, exponent: &Self) -> Option<Self>;
+}
+
+impl FixedPointMath for PreciseNumber {
+    fn checked_exp(&self) -> Option<Self> {
+        exp(self.value, false, PRECISE_DECIMALS).map(|value| Self { value })
+    }
+
+    fn checked_exp_neg(&self) -> Option<Self> {
+        exp(self.value, true, PRECISE_DECIMALS).map(|value| Self { value })
+    }
+
+    fn checked_ln(&self) -> Option<Signed<Self>> {
+        let (value, negative) = ln(self.value, PRECISE_DECIMALS)?;
+        Some(Signed::from_magnitude(Self { value }, negative))
+    }
+
+    fn checked_log2(&self) -> Option<Signed<Self>> {
+        let (value, negative) = log2(self.value, PRECISE_DECIMALS)?;
+        Some(Signed::from_magnitude(Self { value }, negative))
+    }
+
+    fn checked_pow_fraction(&self, exponent: &Self) -> Option<Self> {
+        pow(self.value, exponent.value, PRECISE_DECIMALS).map(|value| Self { value })
+    }
+
+    fn checked_pow_down(&self, exponent: &Self) -> Option<Self> {
+        directed_pow(self.value, exponent.value, PRECISE_DECIMALS, false)
+            .map(|value| Self { value })
+    }
+
+    fn checked_pow_up(&self, exponent: &Self) -> Option<Self> {
+        directed_pow(self.value, exponent.value, PRECISE_DECIMALS, true).map(|value| Self { value })
+    }
+}
+
+impl FixedPointMath for PreciseNumber256D18 {
+    fn checked_exp(&self) -> Option<Self> {
+        exp(self.value, false, d18::DECIMALS).map(d18::from_inner)
+    }
+
+    fn checked_exp_neg(&self) -> Option<Self> {
+        exp(self.value, true, d18::DECIMALS).map(d18::from_inner)
+    }
+
+    fn checked_ln(&self) -> Option<Signed<Self>> {
+        let (value, negative) = ln(self.value, d18::DECIMALS)?;
+        Some(Signed::from_magnitude(d18::from_inner(value), negative))
+    }
+
+    fn checked_log2(&self) -> Option<Signed<Self>> {
+        let (value, negative) = log2(self.value, d18::DECIMALS)?;
+        Some(Signed::from_magnitude(d18::from_inner(value), negative))
+    }
+
+    fn checked_pow_fraction(&self, exponent: &Self) -> Option<Self> {
+        pow(self.value, exponent.value, d18::DECIMALS).map(d18::from_inner)
+    }
+
+    fn checked_pow_down(&self, exponent: &Self) -> Option<Self> {
+        directed_pow(self.value, exponent.value, d18::DECIMALS, false).map(d18::from_inner)
+    }
+
+    fn checked_pow_up(&self, exponent: &Self) -> Option<Self> {
+        directed_pow(self.value, exponent.value, d18::DECIMALS, true).map(d18::from_inner)
+    }
+}
+
+/// 1 in the intermediate precision
+pub(crate) fn wide_one() -> U256 {
+    U256::exp10(WIDE_DECIMALS)
+}
+
+/// Factor from a number with `decimals` to the intermediate precision
+pub(crate) fn widening(decimals: usize) -> U256 {
+    U256::exp10(WIDE_DECIMALS - decimals)
+}
+
+/// `numerator / denominator` rounded to the nearest, half up
+pub(crate) fn div_nearest(numerator: U256, denominator: U256) -> Option<U256> {
+    numerator
+        .checked_add(denominator >> 1)?
+        .checked_div(denominator)
+}
+
+/// `lhs - rhs` as a magnitude and whether it is negative
+pub(crate) fn signed_sub(lhs: U256, rhs: U256) -> (U256, bool) {
+    if lhs >= rhs {
+        (lhs - rhs, false)
+    } else {
+        (rhs - lhs, true)
+    }
+}
+
+/// `e^r` for `0 <= r < ln 2`, both in the intermediate precision
+fn wide_exp_reduced(r: U256) -> U256 {
+    let one = wide_one();
+    let mut sum = one;
+    let mut term = one;
+    let mut n = 1u64;
+    loop {
+        // term < 2^121 and r < 2^120, so the product fits
+        term = term * r / (one * U256::from(n));
+        if term.is_zero() {
+            return sum;
+        }
+        sum += term;
+        n += 1;
+    }
+}
+
+/// `e^x` for `x` in the intermediate precision, as `e^r` in the
+/// intermediate precision and `k` with `e^x = e^r * 2^k`
+pub(crate) fn wide_exp_split(x: U256) -> (U256, U256) {
+    let ln_2 = U256::from(LN_2);
+    let k = x / ln_2;
+    (wide_exp_reduced(x - k * ln_2), k)
+}
+
+/// `e^x` or `e^-x` for `x` in the intermediate precision, rounded to
+/// `decimals`
+pub(crate) fn wide_exp(x: U256, negative: bool, decimals: usize) -> Option<U256> {
+    let (e_r, k) = wide_exp_split(x);
+    let narrowing = widening(decimals);
+    if negative {
+        // e^-x = e^-r / 2^k, nothing left once 2^k dwarfs the intermediate
+        if k >= U256::from(256 - narrowing.bits()) {
+            return Some(U256::zero());
+        }
+        let one = wide_one();
+        let e_minus_r = div_nearest(one * one, e_r)?;
+        div_nearest(e_minus_r, narrowing << k.as_usize())
+    } else {
+        // shift before narrowing as far as the intermediate allows, to keep
+        // the precision of large results
+        if k > U256::from(256) {
+            return None;
+        }
+        let k = k.as_usize();
+        let early = k.min(MANTISSA_BITS);
+        let narrowed = div_nearest(e_r << early, narrowing)?;
+        let late = k - early;
+        if narrowed.bits() + late > 256 {
+            return None;
+        }
+        Some(narrowed << late)
+    }
+}
+
+/// `ln(value / 10^decimals)` in the intermediate precision, as its
+/// magnitude and whether it is negative
+pub(crate) fn wide_ln(value: U256, decimals: usize) -> Option<(U256, bool)> {
+    if value.is_zero() {
+        return None;
+    }
+    // value = 2^k * m with 1 <= m < 2, keeping MANTISSA_BITS of m
+    let k = value.bits() - 1;
+    let dropped = k.saturating_sub(MANTISSA_BITS);
+    let one = wide_one();
+    let m = ((value >> dropped) * one) >> (k - dropped);
+
+    // ln m = 2 * (z + z^3 / 3 + z^5 / 5 + ...) with z = (m - 1) / (m + 1) < 1/3
+    let z = (m - one) * one / (m + one);
+    let z_squared = z * z / one;
+    let mut sum = U256::zero();
+    let mut power = z;
+    let mut n = 1u64;
+    while !power.is_zero() {
+        sum += power / U256::from(n);
+        power = power * z_squared / one;
+        n += 2;
+    }
+    let ln_m = sum << 1;
+
+    // ln value - ln 10^decimals
+    let positive = U256::from(k) * U256::from(LN_2) + ln_m;
+    let negative = U256::from(decimals) * U256::from(LN_10);
+    Some(signed_sub(positive, negative))
+}
+
+/// Signed intermediate result rounded to `decimals`, never a negative zero
+fn narrow_signed(value: U256, negative: bool, decimals: usize) -> Option<(U256, bool)> {
+    let value = div_nearest(value, widening(decimals))?;
+    Some((value, negative && !value.is_zero()))
+}
+
+/// `e^x` or `e^-x` of a number with `decimals`
+fn exp(x: U256, negative: bool, decimals: usize) -> Option<U256> {
+    match x.checked_mul(widening(decimals)) {
+        Some(x) => wide_exp(x, negative, decimals),
+        None if negative => Some(U256::zero()),
+        None => None,
+    }
+}
+
+/// Natural logarithm of a number with `decimals`
+fn ln(value: U256, decimals: usize) -> Option<(U256, bool)> {
+    let (ln, negative) = wide_ln(value, decimals)?;
+    narrow_signed(ln, negative, decimals)
+}
+
+/// Base 2 logarithm of a number with `decimals`
+fn log2(value: U256, decimals: usize) -> Option<(U256, bool)> {
+    let (ln, negative) = wide_ln(value, decimals)?;
+    let log2 = ln.checked_mul(wide_one())? / U256::from(LN_2);
+    narrow_signed(log2, negative, decimals)
+}
+
+/// `base^exponent` of numbers with `decimals`
+fn pow(base: U256, exponent: U256, decimals: usize) -> Option<U256> {
+    let one = U256::exp10(decimals);
+    if exponent.is_zero() {
+        return Some(one);
+    }
+    if base.is_zero() {
+        return Some(U256::zero());
+    }
+    let (ln, negative) = wide_ln(base, decimals)?;
+    // exponent * ln base, in the intermediate precision
+    match exponent.checked_mul(ln) {
+        Some(product) => wide_exp(product / U256::exp10(decimals), negative, decimals),
+        None if negative => Some(U256::zero()),
+        None => None,
+    }
+}
+
+/// `base^exponent` of numbers with `decimals`, rounded down or up
+fn directed_pow(base: U256, exponent: U256, decimals: usize, up: bool) -> Option<U256> {
+    let one = U256::exp10(decimals);
+    // exact powers, nothing to bound
+    if exponent.is_zero() || base.is_zero() || base == one {
+        return pow(base, exponent, decimals);
+    }
+    if exponent == one {
+        return Some(base);
+    }
+    let nearest = pow(base, exponent, decimals)?;
+    // nearest * max(1, exponent) * 1e-29, and the half unit of the rounding
+    let scale = exponent.max(one);
+    let divisor = one * U256::exp10(POW_ERROR_DECIMALS);
+    let relative = match nearest.checked_mul(scale) {
+        Some(product) => product / divisor,
+        None => (nearest / divisor).checked_mul(scale)?,
+    };
+    let error = relative + U256::one();
+    if up {
+        nearest.checked_add(error)
+    } else {
+        Some(nearest.saturating_sub(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, num_bigint::BigInt, num_rational::BigRational, num_traits::ToPrimitive};
+
+    fn d18(value: &str) -> PreciseNumber256D18 {
+        d18::from_decimal_str(value).unwrap()
+    }
+
+    fn d18_f64(value: &PreciseNumber256D18) -> f64 {
+        value.value.as_u128() as f64 / d18::ONE as f64
+    }
+
+    fn precise(value: &str) -> PreciseNumber {
+        PreciseNumber {
+            value: d18(value).value / U256::exp10(d18::DECIMALS - PRECISE_DECIMALS),
+        }
+    }
+
+    #[test]
+    fn test_exp() {
+        // the digits after the 18th, in comments, decide the rounding
+        for (x, expected) in [
+            ("0", "1"),
+            ("1", "2.718281828459045235"),                    // 360
+            ("10", "22026.465794806716516958"),               // 9007
+            ("0.5", "1.648721270700128147"),                  // 8486
+            ("0.693147180559945309", "1.999999999999999999"), // 1655
+        ] {
+            assert_eq!(
+                d18(x).checked_exp().unwrap().value,
+                d18(expected).value,
+                "e^{}",
+                x
+            );
+        }
+        for (x, expected) in [
+            ("1", "0.367879441171442322"),  // 1595
+            ("10", "0.000045399929762485"), // 8515
+            ("42", "0.000000000000000001"), // 0.57 units in the last place
+            ("1000000", "0"),
+        ] {
+            assert_eq!(
+                d18(x).checked_exp_neg().unwrap().value,
+                d18(expected).value,
+                "e^-{}",
+                x
+            );
+        }
+        // a relative 1e-30 leaves 13 exact decimals on e^100
+        let expected = d18("26881171418161354484126255515800135873611118.773741922415191608");
+        let e_100 = d18("100").checked_exp().unwrap();
+        assert!(signed_sub(e_100.value, expected.value).0 < expected.value / U256::exp10(30));
+        assert!(d18("137").checked_exp().is_none());
+        assert_eq!(
+            precise("1").checked_exp().unwrap().value,
+            U256::from(2_718_281_828_459u128)
+        );
+    }
+
+    #[test]
+    fn test_ln() {
+        for (x, expected, negative) in [
+            ("1", "0", false),
+            ("2", "0.693147180559945309", false),  // 4172
+            ("10", "2.302585092994045684", false), // 0180
+            ("0.5", "0.693147180559945309", true),
+            ("0.000000000000000001", "41.446531673892822312", true), // 3238
+            ("2.718281828459045235", "1", false),                    // 1 - 1.3e-19
+        ] {
+            let ln = d18(x).checked_ln().unwrap();
+            assert_eq!(
+                (ln.value.value, ln.negative),
+                (d18(expected).value, negative),
+                "ln {}",
+                x
+            );
+        }
+        assert!(d18("0").checked_ln().is_none());
+        let ln = precise("2").checked_ln().unwrap();
+        assert_eq!(
+            (ln.value.value, ln.negative),
+            (U256::from(693_147_180_560u128), false)
+        );
+    }
+
+    #[test]
+    fn test_log2() {
+        for (x, expected, negative) in [
+            ("8", "3", false),
+            ("0.125", "3", true),
+            ("1024", "10", false),
+            ("3", "1.584962500721156181", false),  // 4537
+            ("10", "3.321928094887362348", false), // 8703
+        ] {
+            let log2 = d18(x).checked_log2().unwrap();
+            assert_eq!(
+                (log2.value.value, log2.negative),
+                (d18(expected).value, negative),
+                "log2 {}",
+                x
+            );
+        }
+        let log2 = precise("0.25").checked_log2().unwrap();
+        assert_eq!(
+            (log2.value.value, log2.negative),
+            (U256::from(2_000_000_000_000u128), true)
+        );
+    }
+
+    #[test]
+    fn test_pow_fraction() {
+        for (base, exponent, expected) in [
+            ("2", "0.5", "1.414213562373095049"), // 8801
+            ("4", "0.5", "2"),
+            ("2", "10", "1024"),
+            ("0.5", "3", "0.125"),
+            ("10", "0.3", "1.995262314968879601"),       // 3524
+            ("1.0001", "10000", "2.718145926825224864"), // 0376
+            ("0", "0.5", "0"),
+            ("0", "0", "1"),
+            ("123.456", "0", "1"),
+        ] {
+            assert_eq!(
+                d18(base)
+                    .checked_pow_fraction(&d18(exponent))
+                    .unwrap()
+                    .value,
+                d18(expected).value,
+                "{}^{}",
+                base,
+                exponent
+            );
+        }
+        assert!(d18("2").checked_pow_fraction(&d18("300")).is_none());
+        assert_eq!(
+            d18("0.5").checked_pow_fraction(&d18("300")).unwrap().value,
+            U256::zero()
+        );
+    }
+
+    #[test]
+    fn test_against_f64() {
+        for i in 0..100 {
+            let x = d18(&format!("{}.{:03}", i / 3, i * 37 % 1000));
+            let float = d18_f64(&x);
+
+            let exp = d18_f64(&x.checked_exp().unwrap());
+            assert!((exp / float.exp() - 1.0).abs() < 1e-14, "e^{}", float);
+
+            if float > 0.0 {
+                let ln = x.checked_ln().unwrap();
+                let ln = if ln.negative {
+                    -d18_f64(&ln.value)
+                } else {
+                    d18_f64(&ln.value)
+                };
+                assert!((ln - float.ln()).abs() < 1e-14, "ln {}", float);
+
+                let pow = d18("1.5").checked_pow_fraction(&x).unwrap();
+                assert!((d18_f64(&pow) / 1.5f64.powf(float) - 1.0).abs() < 1e-14);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for x in [
+            "0.001",
+            "0.5",
+            "1",
+            "3.14159",
+            "42",
+            "99.999999999999999999",
+        ] {
+            let ln = d18(x).checked_exp().unwrap().checked_ln().unwrap();
+            assert!(!ln.negative);
+            let difference = signed_sub(ln.value.value, d18(x).value).0;
+            assert!(difference <= U256::one(), "ln e^{}", x);
+        }
+    }
+
+    /// `value / 10^decimals` as an exact rational
+    fn rational(value: U256, decimals: usize) -> BigRational {
+        BigRational::new(
+            value.to_string().parse().unwrap(),
+            BigInt::from(10).pow(decimals as u32),
+        )
+    }
+
+    /// Whether `down <= base^exponent <= up` exactly, raising both sides to
+    /// the denominator of the exponent to stay in rationals
+    fn brackets(base: U256, exponent: U256, down: U256, up: U256, decimals: usize) -> bool {
+        let exponent = rational(exponent, decimals);
+        let numerator = exponent.numer().to_i32().unwrap();
+        let denominator = exponent.denom().to_i32().unwrap();
+        let power = rational(base, decimals).pow(numerator);
+        rational(down, decimals).pow(denominator) <= power
+            && power <= rational(up, decimals).pow(denominator)
+    }
+
+    #[test]
+    fn test_directed_pow() {
+        for (base, exponent) in [
+            ("0.5", "0.5"),
+            ("0.9", "0.25"),
+            ("0.999", "0.8"),
+            ("0.3", "1.5"),
+            ("0.75", "4"),
+            ("0.000001", "3"),
+            ("1.25", "0.2"),
+            ("2", "0.5"),
+            ("10", "0.3"),
+            ("1.0001", "2.5"),
+            ("123.456", "0.05"),
+            ("7", "3.75"),
+        ] {
+            let (b, e) = (precise(base), precise(exponent));
+            let (down, up) = (
+                b.checked_pow_down(&e).unwrap(),
+                b.checked_pow_up(&e).unwrap(),
+            );
+            assert!(
+                brackets(b.value, e.value, down.value, up.value, PRECISE_DECIMALS),
+                "{}^{}",
+                base,
+                exponent
+            );
+            assert!(
+                up.value - down.value <= U256::from(2),
+                "{}^{}",
+                base,
+                exponent
+            );
+
+            let (b, e) = (d18(base), d18(exponent));
+            let (down, up) = (
+                b.checked_pow_down(&e).unwrap(),
+                b.checked_pow_up(&e).unwrap(),
+            );
+            assert!(
+                brackets(b.value, e.value, down.value, up.value, d18::DECIMALS),
+                "{}^{}",
+                base,
+                exponent
+            );
+            assert!(
+                up.value - down.value <= U256::from(2),
+                "{}^{}",
+                base,
+                exponent
+            );
+        }
+
+        // exact powers are returned as they are
+        for (base, exponent, expected) in
+            [("0.7", "1", "0.7"), ("0.7", "0", "1"), ("1", "2.5", "1")]
+        {
+            let (b, e) = (precise(base), precise(exponent));
+            assert_eq!(
+                b.checked_pow_down(&e).unwrap().value,
+                precise(expected).value
+            );
+            assert_eq!(b.checked_pow_up(&e).unwrap().value, precise(expected).value);
+        }
+        assert_eq!(
+            precise("0.5")
+                .checked_pow_down(&precise("100"))
+                .unwrap()
+                .value,
+            U256::zero()
+        );
+        assert!(precise("2").checked_pow_up(&precise("300")).is_none());
+    }
+}
@@ -0,0 +1,87 @@
+//! Instrumented bump allocator, installed by the `custom-heap` feature
+
+#![allow(clippy::arithmetic_side_effects)]
+#![allow(unsafe_code)]
+
+use {
+    solana_program::entrypoint::{HEAP_LENGTH, HEAP_START_ADDRESS},
+    std::{
+        alloc::{GlobalAlloc, Layout},
+        mem::size_of,
+        ptr::null_mut,
+    },
+};
+
+/// Heap usage counters maintained by the allocator
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HeapStats {
+    /// Total bytes handed out, including memory freed since
+    pub allocated: usize,
+    /// Bytes allocated and not yet freed
+    pub in_use: usize,
+    /// Highest value of `in_use` since the last call to `reset_peak`
+    pub peak: usize,
+    /// Number of allocations
+    pub allocations: usize,
+}
+
+/// Allocator state, stored at the start of the heap region like the position
+/// pointer of the default bump allocator, since programs have no writable
+/// static memory
+#[repr(C)]
+struct Header {
+    pos: usize,
+    stats: HeapStats,
+}
+
+/// Bump allocator over the program heap that counts every allocation
+struct InstrumentedBumpAllocator;
+
+#[global_allocator]
+static ALLOCATOR: InstrumentedBumpAllocator = InstrumentedBumpAllocator;
+
+fn header() -> *mut Header {
+    HEAP_START_ADDRESS as usize as *mut Header
+}
+
+unsafe impl GlobalAlloc for InstrumentedBumpAllocator {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let start = HEAP_START_ADDRESS as usize;
+        let header = &mut *header();
+        let mut pos = header.pos;
+        if pos == 0 {
+            // the heap is zero-initialized, so this is the first allocation
+            pos = start + HEAP_LENGTH;
+        }
+        pos = pos.saturating_sub(layout.size());
+        pos &= !(layout.align().wrapping_sub(1));
+        if pos < start + size_of::<Header>() {
+            return null_mut();
+        }
+        header.pos = pos;
+        header.stats.allocated += layout.size();
+        header.stats.in_use += layout.size();
+        header.stats.peak = header.stats.peak.max(header.stats.in_use);
+        header.stats.allocations += 1;
+        pos as *mut u8
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, _: *mut u8, layout: Layout) {
+        // memory is never reused, only the live byte count goes down
+        let header = &mut *header();
+        header.stats.in_use = header.stats.in_use.saturating_sub(layout.size());
+    }
+}
+
+/// Snapshot of the heap usage counters
+pub fn stats() -> HeapStats {
+    unsafe { (*header()).stats }
+}
+
+/// Restart peak tracking from the bytes currently in use
+pub fn reset_peak() {
+    let header = unsafe { &mut *header() };
+    header.stats.peak = header.stats.in_use;
+}
@@ -3,7 +3,7 @@
 use {
     crate::id,
     borsh::{BorshDeserialize, BorshSerialize},
-    solana_program::instruction::Instruction,
+    solana_program::{instruction::Instruction, program::MAX_RETURN_DATA, pubkey::Pubkey},
 };
 
 /// Algorithms supported for square root calculation
@@ -15,6 +15,906 @@ pub enum SqrtAlgorithm {
     Cordic,
 }
 
+/// Exact input of a `PreciseNumber256D18`
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum D18Input {
+    /// An integer
+    U64(u64),
+    /// An integer
+    U128(u128),
+    /// Inner value, `value * 10^18`, as little-endian u64 limbs
+    Limbs([u64; 4]),
+    /// Decimal string with up to 18 decimals, like `1234.5678`
+    Decimal(String),
+}
+
+/// Rounding of the last place of a quotient, with `rounding::MulDivRounding`
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum RoundingMode {
+    /// Toward negative infinity
+    Floor,
+    /// Toward positive infinity
+    Ceil,
+    /// To the nearest, halves away from zero
+    HalfUp,
+    /// To the nearest, halves to the even neighbor
+    HalfEven,
+    /// Toward zero, dropping the remainder
+    TowardZero,
+}
+
+/// spl-math implementation that runs an operation
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum Implementation {
+    /// spl-math-evolved, the default dependency
+    Evolved,
+    /// Original spl-math, only available with the `compare-upstream` feature
+    Upstream,
+}
+
+/// Operations provided by both spl-math implementations
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum ComparedOperation {
+    /// Integer square root of a u64, with `approximations::sqrt`
+    SquareRootU64 {
+        /// Number underneath the square root sign
+        radicand: u64,
+    },
+    /// Integer square root of a u128, with `approximations::sqrt`
+    SquareRootU128 {
+        /// Number underneath the square root sign
+        radicand: u128,
+    },
+    /// `val * num / denom` rounded down, with `PreciseNumber::mul_div_floor`
+    /// or, upstream, which has no `mul_div_floor`, the floored quotient of the
+    /// inner values
+    MulDivFloor {
+        /// The value to be multiplied and divided
+        val: u64,
+        /// The numerator
+        num: u64,
+        /// The denominator
+        denom: u64,
+    },
+    /// Newton square root, with `PreciseNumber256D18::sqrt_newton` or,
+    /// upstream, `PreciseNumber::sqrt`
+    ///
+    /// Not like-for-like: the evolved root carries 18 decimals and the
+    /// original one 12, so its iterations work on wider numbers.
+    SqrtNewton {
+        /// Number underneath the square root sign
+        radicand: u64,
+    },
+    /// Normal CDF, with `approximations::f32_normal_cdf`
+    NormalCdf {
+        /// The argument
+        argument: f32,
+    },
+}
+
+/// Operations that can be swept over a distribution of inputs
+///
+/// Only the square roots: they take a single integer input, whose magnitude
+/// sets the iterations of Newton and CORDIC. The other operations are not
+/// swept.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum SweepOperation {
+    /// Integer square root of a u64, inputs up to `u64::MAX`
+    SquareRootU64,
+    /// Integer square root of a u128, inputs up to `u128::MAX`
+    SquareRootU128,
+    /// Square root of a PreciseNumber256D18, inputs up to `u64::MAX`
+    PreciseSquareRoot(SqrtAlgorithm),
+}
+
+/// Distribution of the inputs of a sweep
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum SweepInputs {
+    /// Inputs given by the caller
+    Explicit(Vec<u128>),
+    /// `count` inputs `start + step * i`
+    Linear {
+        /// First input
+        start: u128,
+        /// Difference between consecutive inputs
+        step: u128,
+        /// Number of inputs
+        count: u8,
+    },
+    /// `count` inputs `start * ratio^i`, e.g. powers of two for `start: 1`
+    /// and `ratio: 2`
+    Geometric {
+        /// First input
+        start: u128,
+        /// Ratio between consecutive inputs
+        ratio: u128,
+        /// Number of inputs
+        count: u8,
+    },
+    /// `count` pseudo-random inputs up to `max`, reproducible from `seed`
+    Random {
+        /// Seed of the generator
+        seed: u64,
+        /// Largest input
+        max: u128,
+        /// Number of inputs
+        count: u8,
+    },
+    /// 0, 1, 2, half the maximum, the maximum minus one and the maximum input
+    /// of the operation
+    EdgeCases,
+}
+
+/// Compute units measured for one input of a sweep
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct SweepPoint {
+    /// The input
+    pub input: u128,
+    /// Compute units consumed by the operation on this input
+    pub compute_units: u64,
+}
+
+/// Inputs of a batch, one variant per scalar instruction
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum BatchOperation {
+    /// `(val, num, denom)` inputs of `PreciseMulDiv`
+    PreciseMulDiv(Vec<(u64, u64, u64)>),
+    /// Radicands of `SquareRootU64`
+    SquareRootU64(Vec<u64>),
+    /// Radicands of `SquareRootU128`
+    SquareRootU128(Vec<u128>),
+    /// `(multiplicand, multiplier)` inputs of `U64Multiply`
+    U64Multiply(Vec<(u64, u64)>),
+    /// `(dividend, divisor)` inputs of `U64Divide`
+    U64Divide(Vec<(u64, u64)>),
+    /// `(multiplicand, multiplier)` inputs of `F32Multiply`
+    F32Multiply(Vec<(f32, f32)>),
+    /// `(dividend, divisor)` inputs of `F32Divide`
+    F32Divide(Vec<(f32, f32)>),
+    /// `(base, exponent)` inputs of `F32Exponentiate`
+    F32Exponentiate(Vec<(f32, f32)>),
+    /// Arguments of `F32NaturalLog`
+    F32NaturalLog(Vec<f32>),
+    /// Arguments of `F32NormalCDF`
+    F32NormalCDF(Vec<f32>),
+    /// `(base, exponent)` inputs of `F64Pow`
+    F64Pow(Vec<(f64, f64)>),
+    /// `(multiplicand, multiplier)` inputs of `U128Multiply`
+    U128Multiply(Vec<(u128, u128)>),
+    /// `(dividend, divisor)` inputs of `U128Divide`
+    U128Divide(Vec<(u128, u128)>),
+    /// `(multiplicand, multiplier)` inputs of `F64Multiply`
+    F64Multiply(Vec<(f64, f64)>),
+    /// `(dividend, divisor)` inputs of `F64Divide`
+    F64Divide(Vec<(f64, f64)>),
+    /// `(dividend, divisor)` inputs of `CheckedCeilDiv`
+    CheckedCeilDiv(Vec<(u128, u128)>),
+}
+
+/// Operations of `PreciseNumber`, on inner values given as little-endian u64
+/// limbs, so any precision can be tested
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum PreciseOperation {
+    /// `lhs + rhs`, with `checked_add`
+    Add {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs - rhs`, with `checked_sub`
+    Sub {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs * rhs`, with `checked_mul`
+    Mul {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs / rhs`, with `checked_div`
+    Div {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `base ^ exponent`, with `checked_pow`
+    Pow {
+        /// Inner value of the base
+        base: [u64; 4],
+        /// The exponent, an integer
+        exponent: u128,
+    },
+    /// Largest integer not above the value, with `floor`
+    Floor {
+        /// Inner value of the operand
+        value: [u64; 4],
+    },
+    /// Smallest integer not below the value, with `ceiling`
+    Ceiling {
+        /// Inner value of the operand
+        value: [u64; 4],
+    },
+    /// Whether `lhs` and `rhs` differ by less than `precision`, with
+    /// `almost_eq`
+    AlmostEq {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+        /// Inner value of the largest difference, exclusive
+        precision: [u64; 4],
+    },
+}
+
+/// Operand of a signed fixed-point number, as the inner value of its
+/// magnitude, as little-endian u64 limbs, and its sign
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct SignedValue {
+    /// Inner value of the magnitude
+    pub value: [u64; 4],
+    /// Whether the operand is negative
+    pub negative: bool,
+}
+
+/// Operations of `Signed` fixed-point numbers, parallel to
+/// `PreciseOperation`
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum SignedOperation {
+    /// `lhs + rhs`, with `checked_add`
+    Add {
+        /// Left operand
+        lhs: SignedValue,
+        /// Right operand
+        rhs: SignedValue,
+    },
+    /// `lhs - rhs`, with `checked_sub`, negative below zero
+    Sub {
+        /// Left operand
+        lhs: SignedValue,
+        /// Right operand
+        rhs: SignedValue,
+    },
+    /// `lhs * rhs`, with `checked_mul`
+    Mul {
+        /// Left operand
+        lhs: SignedValue,
+        /// Right operand
+        rhs: SignedValue,
+    },
+    /// `lhs / rhs`, with `checked_div`
+    Div {
+        /// Left operand
+        lhs: SignedValue,
+        /// Right operand
+        rhs: SignedValue,
+    },
+    /// `base ^ exponent`, with `checked_pow`
+    Pow {
+        /// The base
+        base: SignedValue,
+        /// The exponent, an integer
+        exponent: u128,
+    },
+    /// Largest integer not above the value, with `floor`
+    Floor {
+        /// The operand
+        value: SignedValue,
+    },
+    /// Smallest integer not below the value, with `ceiling`
+    Ceiling {
+        /// The operand
+        value: SignedValue,
+    },
+    /// Whether `lhs` and `rhs` differ by less than `precision`, with
+    /// `almost_eq`
+    AlmostEq {
+        /// Left operand
+        lhs: SignedValue,
+        /// Right operand
+        rhs: SignedValue,
+        /// Inner value of the largest difference, exclusive
+        precision: [u64; 4],
+    },
+}
+
+/// Operations of `PreciseNumber256D18`, on inner values given as
+/// little-endian u64 limbs
+///
+/// `Mul`, `Div`, `Pow` and `ToU128` time the methods of the type, which round
+/// to the nearest. The type has no floor or ceiling variants, so `MulFloor`,
+/// `MulCeil`, `DivFloor` and `DivCeil` time the helpers of the `d18` module
+/// instead, as do the exact constructions, recorded as e.g.
+/// `d18_local_mul_ceil` to keep them apart from the type's own costs.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum D18Operation {
+    /// Exact construction from an integer, with `d18::from_u128`
+    FromU128 {
+        /// The integer
+        value: u128,
+    },
+    /// Exact construction from a decimal string with up to 18 decimals, with
+    /// `d18::from_decimal_str`
+    FromDecimal {
+        /// The decimal string, like `1234.5678`
+        value: String,
+    },
+    /// `lhs * rhs` rounded down, with `d18::checked_mul_floor`
+    MulFloor {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs * rhs` rounded up, with `d18::checked_mul_ceil`
+    MulCeil {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs / rhs` rounded down, with `d18::checked_div_floor`
+    DivFloor {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs / rhs` rounded up, with `d18::checked_div_ceil`
+    DivCeil {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `base ^ exponent`, with `checked_pow`
+    Pow {
+        /// Inner value of the base
+        base: [u64; 4],
+        /// The exponent, an integer
+        exponent: u128,
+    },
+    /// Nearest integer as a u128, with `to_imprecise`
+    ToU128 {
+        /// Inner value of the operand
+        value: [u64; 4],
+    },
+    /// `lhs * rhs`, with `checked_mul`
+    Mul {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs / rhs`, with `checked_div`
+    Div {
+        /// Inner value of the left operand
+        lhs: [u64; 4],
+        /// Inner value of the right operand
+        rhs: [u64; 4],
+    },
+}
+
+/// Fixed-point number types of spl-math
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum FixedPointType {
+    /// `PreciseNumber`, 12 decimals
+    PreciseNumber,
+    /// `PreciseNumber256D18`, 18 decimals
+    PreciseNumber256D18,
+}
+
+/// Exponential and logarithms of a fixed-point number, on inner values given
+/// as little-endian u64 limbs
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum FixedPointOperation {
+    /// `e^value`, or `e^-value` if `negative`, with `checked_exp` or
+    /// `checked_exp_neg`
+    Exp {
+        /// Inner value of the magnitude of the exponent
+        value: [u64; 4],
+        /// Whether the exponent is negative
+        negative: bool,
+    },
+    /// Natural logarithm, with `checked_ln`
+    Ln {
+        /// Inner value of the operand
+        value: [u64; 4],
+    },
+    /// Base 2 logarithm, with `checked_log2`
+    Log2 {
+        /// Inner value of the operand
+        value: [u64; 4],
+    },
+    /// `base ^ exponent` for a fractional exponent, with
+    /// `checked_pow_fraction`
+    Pow {
+        /// Inner value of the base
+        base: [u64; 4],
+        /// Inner value of the exponent
+        exponent: [u64; 4],
+    },
+    /// `base ^ exponent` rounded down or up, with `checked_pow_down` or
+    /// `checked_pow_up`
+    DirectedPow {
+        /// Inner value of the base
+        base: [u64; 4],
+        /// Inner value of the exponent
+        exponent: [u64; 4],
+        /// Whether to round up rather than down
+        round_up: bool,
+    },
+}
+
+/// Standard normal distribution of a `PreciseNumber`, on inner values given
+/// as little-endian u64 limbs
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum NormalOperation {
+    /// CDF at `argument`, or at `-argument` if `negative`, with `normal_cdf`
+    /// and then `approximations::f32_normal_cdf`
+    Cdf {
+        /// Inner value of the magnitude of the argument
+        argument: [u64; 4],
+        /// Whether the argument is negative
+        negative: bool,
+    },
+    /// PDF at `argument`, with `normal_pdf` and then in f32
+    Pdf {
+        /// Inner value of the magnitude of the argument
+        argument: [u64; 4],
+    },
+    /// Quantile of `probability`, with `normal_inverse_cdf`
+    InverseCdf {
+        /// Inner value of the probability, strictly between 0 and 1
+        probability: [u64; 4],
+    },
+}
+
+/// Kind of a European option
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum OptionKind {
+    /// Right to buy at the strike
+    Call,
+    /// Right to sell at the strike
+    Put,
+}
+
+/// Inputs of a Black-Scholes valuation, whose variant selects the arithmetic
+///
+/// The rate is annual and continuously compounded, the volatility annual and
+/// the time to expiry in years. The rate may be zero, the others must be
+/// positive.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum BlackScholesInputs {
+    /// In f64, with `approximations::f32_normal_cdf` as the normal CDF
+    F64 {
+        /// Price of the underlying
+        spot: f64,
+        /// Strike price
+        strike: f64,
+        /// Risk-free rate
+        rate: f64,
+        /// Volatility of the underlying
+        volatility: f64,
+        /// Time to expiry
+        time: f64,
+    },
+    /// In `PreciseNumber`, on inner values given as little-endian u64 limbs
+    Precise {
+        /// Price of the underlying
+        spot: [u64; 4],
+        /// Strike price
+        strike: [u64; 4],
+        /// Risk-free rate
+        rate: [u64; 4],
+        /// Volatility of the underlying
+        volatility: [u64; 4],
+        /// Time to expiry
+        time: [u64; 4],
+    },
+}
+
+/// Trade fee of a pool, as the fraction `numerator / denominator` of the
+/// amount in; no fee if the numerator is zero
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct TradeFee {
+    /// Numerator of the fee
+    pub numerator: u64,
+    /// Denominator of the fee
+    pub denominator: u64,
+}
+
+/// Constant-product pool operations of the `curve` module
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum CurveOperation {
+    /// Swap a given amount in, with `swap_exact_in`
+    SwapExactIn {
+        /// Amount given to the pool, fee included
+        amount_in: u64,
+        /// Reserve of the token given
+        reserve_in: u64,
+        /// Reserve of the token taken
+        reserve_out: u64,
+        /// Trade fee of the pool
+        fee: TradeFee,
+    },
+    /// Swap for a given amount out, with `swap_exact_out`
+    SwapExactOut {
+        /// Amount taken from the pool
+        amount_out: u64,
+        /// Reserve of the token given
+        reserve_in: u64,
+        /// Reserve of the token taken
+        reserve_out: u64,
+        /// Trade fee of the pool
+        fee: TradeFee,
+    },
+    /// Tokens to deposit for the given pool tokens, with `deposit`
+    Deposit {
+        /// Pool tokens to mint
+        pool_tokens: u64,
+        /// Pool tokens in circulation
+        pool_supply: u64,
+        /// Reserve of token A
+        reserve_a: u64,
+        /// Reserve of token B
+        reserve_b: u64,
+    },
+    /// Tokens withdrawn for the given pool tokens, with `withdraw`
+    Withdraw {
+        /// Pool tokens to burn
+        pool_tokens: u64,
+        /// Pool tokens in circulation
+        pool_supply: u64,
+        /// Reserve of token A
+        reserve_a: u64,
+        /// Reserve of token B
+        reserve_b: u64,
+    },
+}
+
+/// StableSwap invariant solves of the `stableswap` module
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum StableSwapOperation {
+    /// Invariant `D` of the balances, with `compute_d`
+    ComputeD {
+        /// Amplification coefficient
+        amp: u64,
+        /// Balances of the 2 to `MAX_COINS` coins
+        balances: Vec<u128>,
+        /// Most Newton iterations
+        max_iterations: u32,
+    },
+    /// Balance of coin `j` once coin `i` is set to `x`, with `compute_d` and
+    /// then `get_y`
+    GetY {
+        /// Amplification coefficient
+        amp: u64,
+        /// Balances of the 2 to `MAX_COINS` coins
+        balances: Vec<u128>,
+        /// Index of the coin set to `x`
+        i: u8,
+        /// Index of the coin solved for
+        j: u8,
+        /// New balance of coin `i`
+        x: u128,
+        /// Most Newton iterations, of each solve
+        max_iterations: u32,
+    },
+}
+
+/// Swap of a two-token weighted pool; weights are only taken in proportion
+/// to each other
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum WeightedOperation {
+    /// Amount out for `amount_in`, with `out_given_in`
+    OutGivenIn {
+        /// Balance of the token in
+        balance_in: u64,
+        /// Weight of the token in
+        weight_in: u64,
+        /// Balance of the token out
+        balance_out: u64,
+        /// Weight of the token out
+        weight_out: u64,
+        /// Amount given to the pool
+        amount_in: u64,
+    },
+    /// Amount in for `amount_out`, with `in_given_out`
+    InGivenOut {
+        /// Balance of the token in
+        balance_in: u64,
+        /// Weight of the token in
+        weight_in: u64,
+        /// Balance of the token out
+        balance_out: u64,
+        /// Weight of the token out
+        weight_out: u64,
+        /// Amount taken from the pool
+        amount_out: u64,
+    },
+}
+
+/// How often interest compounds
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum Compounding {
+    /// Every slot, `interest::SLOTS_PER_YEAR` times a year
+    PerSlot,
+    /// Once a year
+    PerYear,
+    /// Continuously
+    Continuous,
+}
+
+/// Compound interest of `PreciseNumber` rates, on inner values given as
+/// little-endian u64 limbs
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum InterestOperation {
+    /// Growth factor of `apr` over whole years, with `accrue` or
+    /// `continuous_accrue`; per-slot and per-year compounding also run
+    /// `compound` for comparison
+    Accrue {
+        /// Inner value of the APR
+        apr: [u64; 4],
+        /// Years of growth
+        years: u64,
+        /// How often interest compounds
+        compounding: Compounding,
+        /// Whether to round up rather than down
+        round_up: bool,
+    },
+    /// APY of an APR, with `apr_to_apy`
+    AprToApy {
+        /// Inner value of the APR
+        apr: [u64; 4],
+        /// Compounding periods per year
+        periods_per_year: u64,
+        /// Whether to round up rather than down
+        round_up: bool,
+    },
+    /// APR of an APY, with `apy_to_apr`
+    ApyToApr {
+        /// Inner value of the APY
+        apy: [u64; 4],
+        /// Compounding periods per year
+        periods_per_year: u64,
+    },
+}
+
+/// Unsigned integer of any width supported by the program
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum UnsignedInteger {
+    /// An 8-bit integer
+    U8(u8),
+    /// A 16-bit integer
+    U16(u16),
+    /// A 32-bit integer
+    U32(u32),
+    /// A 64-bit integer
+    U64(u64),
+    /// A 128-bit integer
+    U128(u128),
+    /// A 256-bit integer, as little-endian u64 limbs
+    U256([u64; 4]),
+}
+
+/// Operations of the wide integers of `uint`, on values given as
+/// little-endian u64 limbs
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum WideOperation {
+    /// `multiplicand * multiplier` in U256, with `checked_mul`
+    U256Multiply {
+        /// The multiplicand
+        multiplicand: [u64; 4],
+        /// The multiplier
+        multiplier: [u64; 4],
+    },
+    /// `dividend / divisor` in U256, with `checked_div`
+    U256Divide {
+        /// The dividend
+        dividend: [u64; 4],
+        /// The divisor
+        divisor: [u64; 4],
+    },
+    /// `value * numerator / denominator` in U256, rounded down
+    U256MulDiv {
+        /// The value
+        value: [u64; 4],
+        /// The numerator
+        numerator: [u64; 4],
+        /// The denominator
+        denominator: [u64; 4],
+    },
+    /// Largest integer whose square is not above the radicand, with
+    /// `integer_sqrt`
+    U256Sqrt {
+        /// The radicand
+        radicand: [u64; 4],
+    },
+    /// `value << shift` in U256, dropping the bits shifted out
+    U256ShiftLeft {
+        /// The value
+        value: [u64; 4],
+        /// Number of bits, below 256
+        shift: u32,
+    },
+    /// `value >> shift` in U256
+    U256ShiftRight {
+        /// The value
+        value: [u64; 4],
+        /// Number of bits, below 256
+        shift: u32,
+    },
+    /// `lhs + rhs` in U256, wrapping, with `overflowing_add`
+    U256OverflowingAdd {
+        /// The left operand
+        lhs: [u64; 4],
+        /// The right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs - rhs` in U256, wrapping, with `overflowing_sub`
+    U256OverflowingSub {
+        /// The left operand
+        lhs: [u64; 4],
+        /// The right operand
+        rhs: [u64; 4],
+    },
+    /// `lhs * rhs` in U256, wrapping, with `overflowing_mul`
+    U256OverflowingMul {
+        /// The left operand
+        lhs: [u64; 4],
+        /// The right operand
+        rhs: [u64; 4],
+    },
+    /// `multiplicand * multiplier` in U192, with `checked_mul`
+    U192Multiply {
+        /// The multiplicand
+        multiplicand: [u64; 3],
+        /// The multiplier
+        multiplier: [u64; 3],
+    },
+    /// `dividend / divisor` in U192, with `checked_div`
+    U192Divide {
+        /// The dividend
+        dividend: [u64; 3],
+        /// The divisor
+        divisor: [u64; 3],
+    },
+}
+
+/// Full-precision result of a math instruction, published by the program
+/// through `set_return_data`
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum MathResult {
+    /// Result of a u64 operation
+    U64(u64),
+    /// Result of a u128 operation
+    U128(u128),
+    /// Result of an f32 operation
+    F32(f32),
+    /// Result of an f64 operation
+    F64(f64),
+    /// Results of `F64Pow`, computed with `powi` and `powf` respectively
+    F64Pow {
+        /// Base raised to the exponent truncated to an integer
+        powi: f64,
+        /// Base raised to the full exponent
+        powf: f64,
+    },
+    /// Inner value of a precise number, as little-endian u64 limbs
+    Precise([u64; 4]),
+    /// Inner values of several precise numbers, as little-endian u64 limbs
+    PreciseArray(Vec<[u64; 4]>),
+    /// Compute units per input of a sweep
+    Sweep(Vec<SweepPoint>),
+    /// Results of a batch of u64 operations
+    U64Array(Vec<u64>),
+    /// Results of a batch of u128 operations
+    U128Array(Vec<u128>),
+    /// Results of a batch of f32 operations
+    F32Array(Vec<f32>),
+    /// Results of a batch of f64 operations
+    F64Array(Vec<f64>),
+    /// `(powi, powf)` results of a batch of `F64Pow`
+    F64PowArray(Vec<(f64, f64)>),
+    /// Result of `CheckedCeilDiv`
+    CeilDiv {
+        /// Quotient rounded up
+        quotient: u128,
+        /// Smallest divisor giving the same rounded-up quotient
+        divisor: u128,
+    },
+    /// `(quotient, divisor)` results of a batch of `CheckedCeilDiv`
+    CeilDivArray(Vec<(u128, u128)>),
+    /// Result of a comparison
+    Bool(bool),
+    /// Result of a U256 operation, as little-endian u64 limbs
+    U256([u64; 4]),
+    /// Result of a U192 operation, as little-endian u64 limbs
+    U192([u64; 3]),
+    /// Result of a wrapping U256 operation
+    U256Overflowing {
+        /// The wrapped result, as little-endian u64 limbs
+        value: [u64; 4],
+        /// Whether the operation overflowed
+        overflowed: bool,
+    },
+    /// Result of an operation on an integer of the same width as its input
+    Unsigned(UnsignedInteger),
+    /// Signed result of a fixed-point operation
+    SignedPrecise {
+        /// Inner value of the magnitude, as little-endian u64 limbs
+        value: [u64; 4],
+        /// Whether the result is negative
+        negative: bool,
+    },
+    /// Black-Scholes valuation in f64
+    BlackScholesF64 {
+        /// Price of the option
+        price: f64,
+        /// Sensitivity of the price to the spot, negative for a put
+        delta: f64,
+        /// Sensitivity of the price to the volatility
+        vega: f64,
+    },
+    /// Black-Scholes valuation in `PreciseNumber`, as inner values given as
+    /// little-endian u64 limbs
+    BlackScholesPrecise {
+        /// Price of the option
+        price: [u64; 4],
+        /// Magnitude of the sensitivity of the price to the spot, which is
+        /// negative for a put
+        delta: [u64; 4],
+        /// Sensitivity of the price to the volatility
+        vega: [u64; 4],
+    },
+    /// Result of a swap
+    Swap {
+        /// Amount given to the pool, fee included
+        amount_in: u64,
+        /// Amount taken from the pool
+        amount_out: u64,
+        /// Part of the amount in kept as the fee
+        fee: u64,
+    },
+    /// Amounts of both tokens of a pool
+    TokenPair {
+        /// Amount of token A
+        token_a: u64,
+        /// Amount of token B
+        token_b: u64,
+    },
+    /// Result of an iterative solve
+    Iterated {
+        /// Solution
+        value: u128,
+        /// Iterations taken to converge
+        iterations: u32,
+    },
+}
+
+impl MathResult {
+    /// Decode the return data of a transaction, as found in
+    /// `TransactionReturnData`, into a typed result
+    ///
+    /// Returns `None` if the data was not set by the math program or cannot
+    /// be decoded
+    pub fn from_return_data(program_id: &Pubkey, data: &[u8]) -> Option<Self> {
+        if *program_id != id() {
+            return None;
+        }
+        // the runtime strips trailing zero bytes when recording return data
+        let mut padded = data.to_vec();
+        padded.resize(MAX_RETURN_DATA, 0);
+        Self::deserialize(&mut padded.as_slice()).ok()
+    }
+}
+
 /// Instructions supported by the math program, used for testing instruction
 /// counts
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
@@ -25,11 +925,11 @@ pub enum MathInstruction {
     PreciseSquareRoot {
         /// Number underneath the square root sign, whose square root will be
         /// calculated
-        radicand: Vec<f64>,
+        radicand: Vec<D18Input>,
         /// Algorithm to use for square root calculation
         algorithm: SqrtAlgorithm,
     },
-    /// Muldiv three u64 values
+    /// Muldiv three u64 values, with a chosen rounding of the quotient
     ///
     /// No accounts required for this instruction
     PreciseMulDiv {
@@ -39,6 +939,8 @@ pub enum MathInstruction {
         num: u64,
         /// The denominator
         denom: u64,
+        /// Rounding of the quotient, `Floor` for `mul_div_floor`
+        rounding: RoundingMode,
     },
     /// Calculate the integer square root of the given u64
     ///
@@ -170,38 +1072,198 @@ pub enum MathInstruction {
     ///
     /// No accounts required for this instruction
     Noop,
+
+    /// Run an operation with the selected spl-math implementation, to
+    /// compare the original and evolved versions
+    ///
+    /// No accounts required for this instruction
+    Compare {
+        /// Implementation to run the operation with
+        implementation: Implementation,
+        /// The operation
+        operation: ComparedOperation,
+    },
+
+    /// Run an operation over a distribution of inputs, measuring each input
+    /// separately
+    ///
+    /// No accounts required for this instruction
+    Sweep {
+        /// The operation
+        operation: SweepOperation,
+        /// Inputs to run the operation on, at most `sweep::MAX_SWEEP_INPUTS`
+        inputs: SweepInputs,
+    },
+
+    /// Run a scalar operation on several inputs in one measurement window,
+    /// reporting the total and amortized compute units
+    ///
+    /// No accounts required for this instruction
+    Batch {
+        /// The operation and its inputs, at most `batch::MAX_BATCH_INPUTS`
+        operation: BatchOperation,
+    },
+
+    /// Divide two u128 values rounding up, with `CheckedCeilDiv`
+    ///
+    /// No accounts required for this instruction
+    CheckedCeilDiv {
+        /// The dividend
+        dividend: u128,
+        /// The divisor
+        divisor: u128,
+    },
+
+    /// Run an arithmetic operation of `PreciseNumber` on raw inner values
+    ///
+    /// No accounts required for this instruction
+    PreciseArithmetic {
+        /// The operation and its operands
+        operation: PreciseOperation,
+    },
+
+    /// Run an operation of `PreciseNumber256D18` on exact inputs
+    ///
+    /// No accounts required for this instruction
+    PreciseD18Arithmetic {
+        /// The operation and its operands
+        operation: D18Operation,
+    },
+    /// Run an operation of the wide integers U256 or U192
+    ///
+    /// No accounts required for this instruction
+    WideArithmetic {
+        /// The operation and its operands
+        operation: WideOperation,
+    },
+    /// Calculate the integer square root of an unsigned integer of any
+    /// width, in that width
+    ///
+    /// No accounts required for this instruction
+    IntegerSquareRoot {
+        /// Number underneath the square root sign, whose width selects the
+        /// implementation
+        radicand: UnsignedInteger,
+    },
+    /// Run an exponential or logarithm of a fixed-point number, then the same
+    /// operation in f64 for comparison
+    ///
+    /// No accounts required for this instruction
+    FixedPointMath {
+        /// Type of the operands and result
+        number: FixedPointType,
+        /// The operation and its operands
+        operation: FixedPointOperation,
+    },
+    /// Run the standard normal distribution of a `PreciseNumber`, then its
+    /// f32 counterpart for comparison
+    ///
+    /// No accounts required for this instruction
+    NormalDistribution {
+        /// The operation and its operand
+        operation: NormalOperation,
+    },
+    /// Price a European option and its delta and vega with Black-Scholes, in
+    /// f64 or fixed point depending on the inputs
+    ///
+    /// No accounts required for this instruction
+    BlackScholes {
+        /// Call or put
+        option: OptionKind,
+        /// Market inputs, in the arithmetic to use
+        inputs: BlackScholesInputs,
+    },
+    /// Run a swap, deposit or withdrawal of a constant-product pool
+    ///
+    /// No accounts required for this instruction
+    Curve {
+        /// The operation and its amounts
+        operation: CurveOperation,
+    },
+    /// Solve the StableSwap invariant with Newton's method, reporting the
+    /// iterations taken
+    ///
+    /// No accounts required for this instruction
+    StableSwap {
+        /// The solve and its inputs
+        operation: StableSwapOperation,
+    },
+    /// Swap against a two-token weighted pool, whose balances are raised to
+    /// the ratio of the weights in fixed point
+    ///
+    /// No accounts required for this instruction
+    WeightedPool {
+        /// The swap and its amounts
+        operation: WeightedOperation,
+    },
+    /// Compound interest or convert between APR and APY, in fixed point
+    ///
+    /// No accounts required for this instruction
+    CompoundInterest {
+        /// The operation and its rates
+        operation: InterestOperation,
+    },
+    /// Run an arithmetic operation of `SignedPreciseNumber` or
+    /// `SignedPreciseNumber256D18`, parallel to `PreciseArithmetic`
+    ///
+    /// No accounts required for this instruction
+    SignedArithmetic {
+        /// Type of the magnitudes of the operands and result
+        number: FixedPointType,
+        /// The operation and its operands
+        operation: SignedOperation,
+    },
 }
 
 /// Create SquareRoot instruction
 pub fn precise_sqrt(radicand: u64, sqrt_algorithm: SqrtAlgorithm) -> Instruction {
-    Instruction {
-        program_id: id(),
-        accounts: vec![],
-        data: borsh::to_vec(&MathInstruction::PreciseSquareRoot { radicand: vec![radicand as f64], algorithm: sqrt_algorithm }).unwrap(),
-    }
+    precise_sqrt_inputs(vec![D18Input::U64(radicand)], sqrt_algorithm)
 }
 
-/// Create SquareRoot instruction for array of f64
-pub fn precise_sqrt_array(start: f64, step: f64, sqrt_algorithm: SqrtAlgorithm) -> Instruction {
-
-    let mut radicand: Vec<f64> = Vec::new();
-    for i in 0..8 {
-        radicand.push(start + step * (i as f64));
-    }
+/// Create SquareRoot instruction for array of u64
+pub fn precise_sqrt_array(start: u64, step: u64, sqrt_algorithm: SqrtAlgorithm) -> Instruction {
+    // in u128, where `start + step * 7` cannot overflow
+    let radicand = (0..8)
+        .map(|i| D18Input::U128(start as u128 + step as u128 * i))
+        .collect();
+    precise_sqrt_inputs(radicand, sqrt_algorithm)
+}
 
+/// Create SquareRoot instruction for exact inputs
+pub fn precise_sqrt_inputs(radicand: Vec<D18Input>, sqrt_algorithm: SqrtAlgorithm) -> Instruction {
     Instruction {
         program_id: id(),
         accounts: vec![],
-        data: borsh::to_vec(&MathInstruction::PreciseSquareRoot { radicand, algorithm: sqrt_algorithm }).unwrap(),
+        data: borsh::to_vec(&MathInstruction::PreciseSquareRoot {
+            radicand,
+            algorithm: sqrt_algorithm,
+        })
+        .unwrap(),
     }
 }
 
-/// Create PreciseMulDiv instruction
+/// Create PreciseMulDiv instruction, rounding down
 pub fn precise_muldiv(val: u64, num: u64, denom: u64) -> Instruction {
+    precise_muldiv_rounded(val, num, denom, RoundingMode::Floor)
+}
+
+/// Create PreciseMulDiv instruction with the given rounding
+pub fn precise_muldiv_rounded(
+    val: u64,
+    num: u64,
+    denom: u64,
+    rounding: RoundingMode,
+) -> Instruction {
     Instruction {
         program_id: id(),
         accounts: vec![],
-        data: borsh::to_vec(&MathInstruction::PreciseMulDiv { val, num, denom }).unwrap(),
+        data: borsh::to_vec(&MathInstruction::PreciseMulDiv {
+            val,
+            num,
+            denom,
+            rounding,
+        })
+        .unwrap(),
     }
 }
 
@@ -355,3 +1417,151 @@ pub fn noop() -> Instruction {
         data: borsh::to_vec(&MathInstruction::Noop).unwrap(),
     }
 }
+
+/// Create Compare instruction
+pub fn compare(implementation: Implementation, operation: ComparedOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::Compare {
+            implementation,
+            operation,
+        })
+        .unwrap(),
+    }
+}
+
+/// Create Sweep instruction
+pub fn sweep(operation: SweepOperation, inputs: SweepInputs) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::Sweep { operation, inputs }).unwrap(),
+    }
+}
+
+/// Create Batch instruction
+pub fn batch(operation: BatchOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::Batch { operation }).unwrap(),
+    }
+}
+
+/// Create CheckedCeilDiv instruction
+pub fn checked_ceil_div(dividend: u128, divisor: u128) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::CheckedCeilDiv { dividend, divisor }).unwrap(),
+    }
+}
+
+/// Create PreciseArithmetic instruction
+pub fn precise_arithmetic(operation: PreciseOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::PreciseArithmetic { operation }).unwrap(),
+    }
+}
+
+/// Create PreciseD18Arithmetic instruction
+pub fn precise_d18_arithmetic(operation: D18Operation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::PreciseD18Arithmetic { operation }).unwrap(),
+    }
+}
+
+/// Create WideArithmetic instruction
+pub fn wide_arithmetic(operation: WideOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::WideArithmetic { operation }).unwrap(),
+    }
+}
+
+/// Create IntegerSquareRoot instruction
+pub fn integer_sqrt(radicand: UnsignedInteger) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::IntegerSquareRoot { radicand }).unwrap(),
+    }
+}
+
+/// Create FixedPointMath instruction
+pub fn fixed_point_math(number: FixedPointType, operation: FixedPointOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::FixedPointMath { number, operation }).unwrap(),
+    }
+}
+
+/// Create NormalDistribution instruction
+pub fn normal_distribution(operation: NormalOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::NormalDistribution { operation }).unwrap(),
+    }
+}
+
+/// Create BlackScholes instruction
+pub fn black_scholes(option: OptionKind, inputs: BlackScholesInputs) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::BlackScholes { option, inputs }).unwrap(),
+    }
+}
+
+/// Create Curve instruction
+pub fn curve(operation: CurveOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::Curve { operation }).unwrap(),
+    }
+}
+
+/// Create StableSwap instruction
+pub fn stable_swap(operation: StableSwapOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::StableSwap { operation }).unwrap(),
+    }
+}
+
+/// Create WeightedPool instruction
+pub fn weighted_pool(operation: WeightedOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::WeightedPool { operation }).unwrap(),
+    }
+}
+
+/// Create CompoundInterest instruction
+pub fn compound_interest(operation: InterestOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::CompoundInterest { operation }).unwrap(),
+    }
+}
+
+/// Create SignedArithmetic instruction
+pub fn signed_arithmetic(number: FixedPointType, operation: SignedOperation) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: borsh::to_vec(&MathInstruction::SignedArithmetic { number, operation }).unwrap(),
+    }
+}
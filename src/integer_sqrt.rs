@@ -0,0 +1,157 @@
+//! Integer square root of every unsigned width, with `approximations::sqrt`
+//! up to u128 and `integer_sqrt` for U256
+
+use crate::{
+    approximations::sqrt,
+    bench::Bench,
+    error::MathError,
+    instruction::{MathResult, UnsignedInteger},
+    uint::U256,
+};
+
+/// Measure the square root of the radicand, in its own width
+pub(crate) fn process_integer_sqrt(
+    bench: &Bench,
+    radicand: UnsignedInteger,
+) -> Result<MathResult, MathError> {
+    let root = match radicand {
+        UnsignedInteger::U8(radicand) => UnsignedInteger::U8(
+            bench
+                .measure("integer_sqrt_u8", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?,
+        ),
+        UnsignedInteger::U16(radicand) => UnsignedInteger::U16(
+            bench
+                .measure("integer_sqrt_u16", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?,
+        ),
+        UnsignedInteger::U32(radicand) => UnsignedInteger::U32(
+            bench
+                .measure("integer_sqrt_u32", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?,
+        ),
+        UnsignedInteger::U64(radicand) => UnsignedInteger::U64(
+            bench
+                .measure("integer_sqrt_u64", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?,
+        ),
+        UnsignedInteger::U128(radicand) => UnsignedInteger::U128(
+            bench
+                .measure("integer_sqrt_u128", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?,
+        ),
+        UnsignedInteger::U256(radicand) => {
+            let radicand = U256(radicand);
+            UnsignedInteger::U256(
+                bench
+                    .measure("integer_sqrt_u256", || radicand.integer_sqrt())
+                    .0,
+            )
+        }
+    };
+    Ok(MathResult::Unsigned(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, proptest::prelude::*};
+
+    /// Whether `root` is the integer square root of `radicand`, that is
+    /// `root^2 <= radicand < (root + 1)^2`, without overflowing
+    fn is_root(radicand: U256, root: U256) -> bool {
+        let below = root
+            .checked_mul(root)
+            .is_some_and(|square| square <= radicand);
+        let above = root
+            .checked_add(U256::one())
+            .and_then(|next| next.checked_mul(next))
+            .is_none_or(|square| square > radicand);
+        below && above
+    }
+
+    #[test]
+    fn test_exhaustive_u8() {
+        for radicand in u8::MIN..=u8::MAX {
+            let root = sqrt(radicand).unwrap();
+            assert!(
+                is_root(U256::from(radicand), U256::from(root)),
+                "sqrt({}) = {}",
+                radicand,
+                root
+            );
+        }
+    }
+
+    #[test]
+    fn test_exhaustive_u16() {
+        for radicand in u16::MIN..=u16::MAX {
+            let root = sqrt(radicand).unwrap();
+            assert!(
+                is_root(U256::from(radicand), U256::from(root)),
+                "sqrt({}) = {}",
+                radicand,
+                root
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_integer_sqrt() {
+        let run = |radicand| process_integer_sqrt(&Bench::default(), radicand);
+        for (radicand, root) in [
+            (UnsignedInteger::U8(u8::MAX), UnsignedInteger::U8(15)),
+            (UnsignedInteger::U16(u16::MAX), UnsignedInteger::U16(255)),
+            (UnsignedInteger::U32(u32::MAX), UnsignedInteger::U32(65535)),
+            (
+                UnsignedInteger::U64(u64::MAX),
+                UnsignedInteger::U64(u32::MAX as u64),
+            ),
+            (
+                UnsignedInteger::U128(u128::MAX),
+                UnsignedInteger::U128(u64::MAX as u128),
+            ),
+            (
+                UnsignedInteger::U256(U256::MAX.0),
+                UnsignedInteger::U256(U256::from(u128::MAX).0),
+            ),
+            (UnsignedInteger::U256([0; 4]), UnsignedInteger::U256([0; 4])),
+        ] {
+            assert_eq!(run(radicand), Ok(MathResult::Unsigned(root)));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_sqrt_u32(radicand: u32) {
+            let root = sqrt(radicand).unwrap();
+            prop_assert!(is_root(U256::from(radicand), U256::from(root)));
+        }
+
+        #[test]
+        fn test_sqrt_u64(radicand: u64) {
+            let root = sqrt(radicand).unwrap();
+            prop_assert!(is_root(U256::from(radicand), U256::from(root)));
+        }
+
+        #[test]
+        fn test_sqrt_u128(radicand: u128) {
+            let root = sqrt(radicand).unwrap();
+            prop_assert!(is_root(U256::from(radicand), U256::from(root)));
+        }
+
+        #[test]
+        fn test_sqrt_u256(limbs: [u64; 4]) {
+            let radicand = U256(limbs);
+            prop_assert!(is_root(radicand, radicand.integer_sqrt()));
+        }
+
+        #[test]
+        fn test_sqrt_u128_perfect_squares(root: u64) {
+            let radicand = root as u128 * root as u128;
+            prop_assert_eq!(sqrt(radicand), Some(root as u128));
+            if radicand > 0 {
+                prop_assert_eq!(sqrt(radicand - 1), Some(root as u128 - 1));
+            }
+        }
+    }
+}
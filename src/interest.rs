@@ -0,0 +1,523 @@
+//! Compound interest of `PreciseNumber` rates: growth factors compounded per
+//! period or continuously, and conversions between APR and APY
+//!
+//! `compound` is `PreciseNumber::checked_pow` of `1 + rate`, exponentiation
+//! by squaring that rounds every product to the 12 decimals, and a rate per
+//! slot already loses most of its digits to them. `accrue` squares the same
+//! way in the 36-decimal intermediate of `fixed`, normalized to a mantissa in
+//! `[1, 2)` times a power of 2 so growth of any size keeps its precision, and
+//! rounds every product in one direction: the result is a bound on the named
+//! side of the exact value, within a unit in the last place of it for any
+//! practical number of periods. `continuous_accrue` bounds `e^(apr * years)`
+//! the same way, moving the intermediate exponential past its error before
+//! rounding it.
+
+use {
+    crate::{
+        bench::Bench,
+        error::MathError,
+        fixed::{
+            div_nearest, wide_exp, wide_exp_split, wide_ln, wide_one, widening, PRECISE_DECIMALS,
+            WIDE_DECIMALS,
+        },
+        instruction::{Compounding, InterestOperation, MathResult},
+        precise_number::{self, PreciseNumber},
+        uint::U256,
+    },
+    solana_program::clock::{DEFAULT_MS_PER_SLOT, SECONDS_PER_DAY},
+};
+
+/// Slots in a year of 365 days at the target slot time
+pub const SLOTS_PER_YEAR: u64 = SECONDS_PER_DAY * 365 * 1_000 / DEFAULT_MS_PER_SLOT;
+
+/// Largest power of 2 of an intermediate, past any `PreciseNumber`
+const MAX_BINARY_EXPONENT: usize = 256;
+
+/// Decimals of the relative error bound of the intermediate exponential
+const EXP_ERROR_DECIMALS: usize = 30;
+
+/// Number of at least 1, as `mantissa * 2^exponent` with the mantissa in
+/// `[1, 2)` in the intermediate precision
+#[derive(Clone, Copy, Debug)]
+struct Scaled {
+    mantissa: U256,
+    exponent: usize,
+}
+
+impl Scaled {
+    /// `mantissa * 2^exponent` normalized, halving the mantissa rounded down
+    /// or up; `None` past `MAX_BINARY_EXPONENT`
+    fn new(mut mantissa: U256, mut exponent: usize, up: bool) -> Option<Self> {
+        let two = wide_one() << 1;
+        while mantissa >= two {
+            mantissa = if up {
+                (mantissa + U256::one()) >> 1
+            } else {
+                mantissa >> 1
+            };
+            exponent += 1;
+        }
+        (exponent <= MAX_BINARY_EXPONENT).then_some(Self { mantissa, exponent })
+    }
+
+    /// `self * other`, rounded down or up
+    fn mul(self, other: Self, up: bool) -> Option<Self> {
+        // mantissas are below 2^121, so the product fits
+        let product = self.mantissa * other.mantissa;
+        let (mantissa, remainder) = product.div_mod(wide_one());
+        let mantissa = if up && !remainder.is_zero() {
+            mantissa + U256::one()
+        } else {
+            mantissa
+        };
+        Self::new(mantissa, self.exponent + other.exponent, up)
+    }
+
+    /// `self^exponent` by squaring, every product rounded down or up
+    fn pow(self, mut exponent: u64, up: bool) -> Option<Self> {
+        let mut result = Self {
+            mantissa: wide_one(),
+            exponent: 0,
+        };
+        let mut power = self;
+        loop {
+            if exponent & 1 == 1 {
+                result = result.mul(power, up)?;
+            }
+            exponent >>= 1;
+            if exponent == 0 {
+                return Some(result);
+            }
+            power = power.mul(power, up)?;
+        }
+    }
+
+    /// Inner value with `decimals`, rounded down or up; `None` if it does
+    /// not fit
+    fn narrow(self, decimals: usize, up: bool) -> Option<U256> {
+        // shift before narrowing as far as the intermediate allows
+        let early = self.exponent.min(256 - self.mantissa.bits());
+        let (value, remainder) = (self.mantissa << early).div_mod(widening(decimals));
+        let value = if up && !remainder.is_zero() {
+            value + U256::one()
+        } else {
+            value
+        };
+        let late = self.exponent - early;
+        (value.bits() + late <= 256).then(|| value << late)
+    }
+}
+
+/// `1 + apr / periods_per_year` in the intermediate precision, rounded down
+/// or up
+fn periodic_base(apr: &PreciseNumber, periods_per_year: u64, up: bool) -> Option<Scaled> {
+    if periods_per_year == 0 {
+        return None;
+    }
+    let (rate, remainder) = apr
+        .value
+        .checked_mul(widening(PRECISE_DECIMALS))?
+        .div_mod(U256::from(periods_per_year));
+    let rate = if up && !remainder.is_zero() {
+        rate + U256::one()
+    } else {
+        rate
+    };
+    Scaled::new(wide_one().checked_add(rate)?, 0, up)
+}
+
+/// `(1 + rate)^periods` with `PreciseNumber::checked_pow`, every product
+/// rounded to the nearest; `None` if it does not fit
+pub fn compound(rate: &PreciseNumber, periods: u64) -> Option<PreciseNumber> {
+    let base = PreciseNumber::new(1)?.checked_add(rate)?;
+    base.checked_pow(periods as u128)
+}
+
+/// Growth factor `(1 + apr / periods_per_year)^periods` of `periods`
+/// compounding periods, rounded down or up
+///
+/// Returns `None` for no periods per year, or a factor that does not fit.
+pub fn accrue(
+    apr: &PreciseNumber,
+    periods_per_year: u64,
+    periods: u64,
+    round_up: bool,
+) -> Option<PreciseNumber> {
+    let factor = periodic_base(apr, periods_per_year, round_up)?.pow(periods, round_up)?;
+    factor
+        .narrow(PRECISE_DECIMALS, round_up)
+        .map(|value| PreciseNumber { value })
+}
+
+/// Growth factor `e^(apr * years)` of continuous compounding, rounded down
+/// or up; `None` if it does not fit
+pub fn continuous_accrue(
+    apr: &PreciseNumber,
+    years: &PreciseNumber,
+    round_up: bool,
+) -> Option<PreciseNumber> {
+    // the product has 24 decimals, exact in the intermediate
+    let exponent = apr
+        .value
+        .checked_mul(years.value)?
+        .checked_mul(widening(2 * PRECISE_DECIMALS))?;
+    if exponent.is_zero() {
+        return PreciseNumber::new(1);
+    }
+    let (e_r, k) = wide_exp_split(exponent);
+    if k > U256::from(MAX_BINARY_EXPONENT) {
+        return None;
+    }
+    // e^r * 2^k is within a relative 1e-30 of the exact factor
+    let error = e_r / U256::exp10(EXP_ERROR_DECIMALS) + U256::one();
+    let mantissa = if round_up { e_r + error } else { e_r - error };
+    Scaled::new(mantissa, k.as_usize(), round_up)?
+        .narrow(PRECISE_DECIMALS, round_up)
+        .map(|value| PreciseNumber { value })
+}
+
+/// APY of an APR compounded `periods_per_year` times a year,
+/// `(1 + apr / periods_per_year)^periods_per_year - 1`, rounded down or up
+///
+/// Returns `None` for no periods per year, or an APY that does not fit.
+pub fn apr_to_apy(
+    apr: &PreciseNumber,
+    periods_per_year: u64,
+    round_up: bool,
+) -> Option<PreciseNumber> {
+    let factor = accrue(apr, periods_per_year, periods_per_year, round_up)?;
+    factor.checked_sub(&PreciseNumber::new(1)?)
+}
+
+/// APR that compounded `periods_per_year` times a year gives the APY,
+/// `periods_per_year * ((1 + apy)^(1 / periods_per_year) - 1)`, rounded to
+/// the nearest
+///
+/// The root is `e^(ln(1 + apy) / periods_per_year)` in the intermediate
+/// precision, within `1e-30 * periods_per_year` of the exact APR before it
+/// is rounded. Returns `None` for no periods per year.
+pub fn apy_to_apr(apy: &PreciseNumber, periods_per_year: u64) -> Option<PreciseNumber> {
+    if periods_per_year == 0 {
+        return None;
+    }
+    let growth = apy.value.checked_add(U256::from(precise_number::ONE))?;
+    // a growth of at least 1 has a logarithm below zero only by its error
+    let (ln, negative) = wide_ln(growth, PRECISE_DECIMALS)?;
+    let ln = if negative { U256::zero() } else { ln };
+    let periods_per_year = U256::from(periods_per_year);
+    let root = wide_exp(ln / periods_per_year, false, WIDE_DECIMALS)?;
+    let apr = (root - wide_one()).checked_mul(periods_per_year)?;
+    div_nearest(apr, widening(PRECISE_DECIMALS)).map(|value| PreciseNumber { value })
+}
+
+/// Run the operation and return the inner value of its result
+pub(crate) fn process_interest(
+    bench: &Bench,
+    operation: InterestOperation,
+) -> Result<MathResult, MathError> {
+    let precise = |value| PreciseNumber { value: U256(value) };
+    let result = match operation {
+        InterestOperation::Accrue {
+            apr,
+            years,
+            compounding,
+            round_up,
+        } => {
+            let apr = precise(apr);
+            let (periods_per_year, suffix) = match compounding {
+                Compounding::PerSlot => (SLOTS_PER_YEAR, "per_slot"),
+                Compounding::PerYear => (1, "per_year"),
+                Compounding::Continuous => {
+                    let years = PreciseNumber::new(years as u128).ok_or(MathError::Overflow)?;
+                    let result = bench
+                        .measure("interest_accrue_continuous", || {
+                            continuous_accrue(&apr, &years, round_up)
+                        })
+                        .ok_or(MathError::Overflow)?;
+                    return Ok(MathResult::Precise(result.value.0));
+                }
+            };
+            let periods = periods_per_year
+                .checked_mul(years)
+                .ok_or(MathError::Overflow)?;
+            let result = bench.measure(&format!("interest_accrue_{}", suffix), || {
+                accrue(&apr, periods_per_year, periods, round_up)
+            });
+            // the same growth from the rate per period in 12 decimals
+            let rate =
+                PreciseNumber::new(periods_per_year as u128).and_then(|n| apr.checked_div(&n));
+            bench.measure(&format!("precise_compound_{}", suffix), || {
+                rate.as_ref().and_then(|rate| compound(rate, periods))
+            });
+            result.ok_or(MathError::Overflow)?
+        }
+        InterestOperation::AprToApy {
+            apr,
+            periods_per_year,
+            round_up,
+        } => {
+            if periods_per_year == 0 {
+                return Err(MathError::DivisionByZero);
+            }
+            let apr = precise(apr);
+            bench
+                .measure("interest_apr_to_apy", || {
+                    apr_to_apy(&apr, periods_per_year, round_up)
+                })
+                .ok_or(MathError::Overflow)?
+        }
+        InterestOperation::ApyToApr {
+            apy,
+            periods_per_year,
+        } => {
+            if periods_per_year == 0 {
+                return Err(MathError::DivisionByZero);
+            }
+            let apy = precise(apy);
+            bench
+                .measure("interest_apy_to_apr", || apy_to_apr(&apy, periods_per_year))
+                .ok_or(MathError::Overflow)?
+        }
+    };
+    Ok(MathResult::Precise(result.value.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, num_bigint::BigInt, num_rational::BigRational, num_traits::One};
+
+    fn precise(value: &str) -> PreciseNumber {
+        let value = crate::d18::from_decimal_str(value).unwrap().value;
+        PreciseNumber {
+            value: value / U256::exp10(6),
+        }
+    }
+
+    fn rational(value: &PreciseNumber) -> BigRational {
+        BigRational::new(
+            value.value.to_string().parse().unwrap(),
+            BigInt::from(precise_number::ONE),
+        )
+    }
+
+    fn to_f64(value: &PreciseNumber) -> f64 {
+        value.value.as_u128() as f64 / precise_number::ONE as f64
+    }
+
+    /// Exact `(1 + apr / periods_per_year)^periods`
+    fn exact_growth(apr: &BigRational, periods_per_year: u64, periods: u64) -> BigRational {
+        let base = BigRational::one() + apr / BigInt::from(periods_per_year);
+        base.pow(periods as i32)
+    }
+
+    #[test]
+    fn test_compound() {
+        assert_eq!(compound(&precise("0.1"), 2), Some(precise("1.21")));
+        assert_eq!(
+            compound(&precise("0.05"), 10),
+            Some(precise("1.628894626777"))
+        );
+        assert_eq!(compound(&precise("0.05"), 0), Some(precise("1")));
+        assert_eq!(compound(&precise("1"), 300), None);
+    }
+
+    #[test]
+    fn test_accrue() {
+        for (apr, periods_per_year, periods) in [
+            ("0.05", 1, 10),
+            ("0.1", 12, 120),
+            ("0.035", 365, 365),
+            ("0.2", 365, 3650),
+            ("1.5", 4, 40),
+            ("0", 12, 12),
+        ] {
+            let apr = precise(apr);
+            let exact = exact_growth(&rational(&apr), periods_per_year, periods);
+            let down = accrue(&apr, periods_per_year, periods, false).unwrap();
+            let up = accrue(&apr, periods_per_year, periods, true).unwrap();
+            assert!(
+                rational(&down) <= exact && exact <= rational(&up),
+                "{:?} {} {}",
+                apr,
+                periods_per_year,
+                periods
+            );
+            // the floor and the ceiling of the exact value
+            assert!(up.value - down.value <= U256::one());
+        }
+        assert_eq!(accrue(&precise("0.05"), 0, 1, false), None);
+        assert_eq!(accrue(&precise("1"), 1, 256, true), None);
+    }
+
+    #[test]
+    fn test_accrue_per_slot() {
+        for apr in ["0.01", "0.1", "0.5"] {
+            let apr = precise(apr);
+            let float = to_f64(&apr);
+            let n = SLOTS_PER_YEAR as f64;
+            let expected = (n * (float / n).ln_1p()).exp();
+            let down = accrue(&apr, SLOTS_PER_YEAR, SLOTS_PER_YEAR, false).unwrap();
+            let up = accrue(&apr, SLOTS_PER_YEAR, SLOTS_PER_YEAR, true).unwrap();
+            assert!(to_f64(&down) <= expected + 1e-14 && expected - 1e-14 <= to_f64(&up));
+            assert!(up.value - down.value <= U256::one());
+
+            // a rate per slot in 12 decimals is off by up to a relative 4e-4
+            let rate = apr
+                .checked_div(&PreciseNumber::new(SLOTS_PER_YEAR as u128).unwrap())
+                .unwrap();
+            let coarse = compound(&rate, SLOTS_PER_YEAR).unwrap();
+            assert!((to_f64(&coarse) - expected).abs() > 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_continuous_accrue() {
+        // e^0.05 = 1.051271096376024039697, e = 2.718281828459045235360
+        for (apr, years, down, up) in [
+            ("0.05", "1", "1.051271096376", "1.051271096377"),
+            ("0.1", "10", "2.718281828459", "2.718281828460"),
+            ("0", "10", "1", "1"),
+        ] {
+            for (round_up, expected) in [(false, down), (true, up)] {
+                assert_eq!(
+                    continuous_accrue(&precise(apr), &precise(years), round_up),
+                    Some(precise(expected)),
+                    "e^({} * {}) {}",
+                    apr,
+                    years,
+                    round_up
+                );
+            }
+        }
+        // past 30 significant digits, the bounds are a relative 1e-30 apart
+        let exact = precise("26881171418161354484126255515800135873611118.773741922415");
+        let down = continuous_accrue(&precise("1"), &precise("100"), false).unwrap();
+        let up = continuous_accrue(&precise("1"), &precise("100"), true).unwrap();
+        assert!(down.value <= exact.value && exact.value < up.value);
+        assert!(up.value - down.value < exact.value / U256::exp10(29));
+        assert_eq!(
+            continuous_accrue(&precise("10"), &precise("100"), false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_apr_to_apy() {
+        // 1.01^12 = 1.126825030131969720661201
+        assert_eq!(
+            apr_to_apy(&precise("0.12"), 12, false),
+            Some(precise("0.126825030131"))
+        );
+        assert_eq!(
+            apr_to_apy(&precise("0.12"), 12, true),
+            Some(precise("0.126825030132"))
+        );
+        assert_eq!(
+            apr_to_apy(&precise("0.12"), 1, false),
+            Some(precise("0.12"))
+        );
+        assert_eq!(apr_to_apy(&precise("0.12"), 0, false), None);
+    }
+
+    #[test]
+    fn test_apy_to_apr() {
+        let half_unit = BigRational::new(BigInt::one(), BigInt::from(2 * precise_number::ONE));
+        for (apy, periods_per_year) in [
+            ("0.126825030132", 12),
+            ("0.05", 12),
+            ("0.05", 365),
+            ("1", 52),
+            ("0", 12),
+        ] {
+            let apy = precise(apy);
+            let apr = apy_to_apr(&apy, periods_per_year).unwrap();
+            // the exact APR is within half a unit of the result
+            let growth = BigRational::one() + rational(&apy);
+            let n = periods_per_year;
+            let apr = rational(&apr);
+            assert!(
+                exact_growth(&(&apr - &half_unit), n, n) <= growth
+                    && growth <= exact_growth(&(&apr + &half_unit), n, n),
+                "{:?} {}",
+                apy,
+                periods_per_year
+            );
+        }
+        assert_eq!(
+            apy_to_apr(&precise("0.126825030132"), 12),
+            Some(precise("0.12"))
+        );
+        assert_eq!(apy_to_apr(&precise("0.05"), 0), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for apr in ["0.001", "0.05", "0.12", "0.8", "3"] {
+            for periods_per_year in [1, 12, 365, SLOTS_PER_YEAR] {
+                let apr = precise(apr);
+                let apy = apr_to_apy(&apr, periods_per_year, false).unwrap();
+                let again = apy_to_apr(&apy, periods_per_year).unwrap();
+                let difference = apr.value.max(again.value) - apr.value.min(again.value);
+                assert!(difference <= U256::one(), "{:?} {}", apr, periods_per_year);
+            }
+        }
+    }
+
+    #[test]
+    fn test_process_interest() {
+        let run = |operation| process_interest(&Bench::default(), operation);
+        let inner = |value: &str| precise(value).value.0;
+        assert_eq!(
+            run(InterestOperation::Accrue {
+                apr: inner("0.05"),
+                years: 10,
+                compounding: Compounding::PerYear,
+                round_up: false
+            }),
+            // 1.05^10 = 1.62889462677744140625
+            Ok(MathResult::Precise(inner("1.628894626777")))
+        );
+        assert_eq!(
+            run(InterestOperation::Accrue {
+                apr: inner("0.05"),
+                years: 1,
+                compounding: Compounding::Continuous,
+                round_up: false
+            }),
+            Ok(MathResult::Precise(inner("1.051271096376")))
+        );
+        assert_eq!(
+            run(InterestOperation::Accrue {
+                apr: inner("0.05"),
+                years: 1,
+                compounding: Compounding::Continuous,
+                round_up: true
+            }),
+            Ok(MathResult::Precise(inner("1.051271096377")))
+        );
+        assert_eq!(
+            run(InterestOperation::AprToApy {
+                apr: inner("0.12"),
+                periods_per_year: 12,
+                round_up: true
+            }),
+            Ok(MathResult::Precise(inner("0.126825030132")))
+        );
+        assert_eq!(
+            run(InterestOperation::ApyToApr {
+                apy: inner("0.05"),
+                periods_per_year: 0
+            }),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(InterestOperation::Accrue {
+                apr: inner("1"),
+                years: 1_000,
+                compounding: Compounding::PerYear,
+                round_up: true
+            }),
+            Err(MathError::Overflow)
+        );
+    }
+}
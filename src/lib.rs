@@ -1,12 +1,35 @@
 //! Math operations using unsigned integers
 
 #![deny(missing_docs)]
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 
+pub mod batch;
+pub mod bench;
+pub mod black_scholes;
+mod compare;
+pub mod curve;
+pub mod d18;
 mod entrypoint;
 pub mod error;
+pub mod fixed;
+#[cfg(all(
+    feature = "custom-heap",
+    not(feature = "no-entrypoint"),
+    target_os = "solana"
+))]
+pub mod heap;
 pub mod instruction;
+mod integer_sqrt;
+pub mod interest;
+pub mod normal;
+mod precise;
 pub mod processor;
+pub mod rounding;
+pub mod signed;
+pub mod stableswap;
+pub mod sweep;
+pub mod weighted;
+mod wide;
 
 pub use spl_math::{approximations, checked_ceil_div, precise_number, uint};
 
@@ -0,0 +1,376 @@
+//! Standard normal distribution of `PreciseNumber`, with integer arithmetic
+//! only
+//!
+//! * PDF: `e^(-x^2 / 2) / sqrt(2 pi)`
+//! * CDF: `1/2 + pdf(x) * (x + x^3 / 3 + x^5 / (3 * 5) + ...)`, a series of
+//!   positive terms, so nothing cancels. Beyond `|x| = 9` the CDF is 0 or 1
+//!   to within `1.2e-19`.
+//! * Inverse CDF: Newton's method from the estimate of Abramowitz and Stegun
+//!   26.2.23, which is within `4.5e-4`
+//!
+//! Every result is rounded to the nearest unit in the last place, from a
+//! 36-decimal intermediate within `1e-30` for the CDF and PDF and within
+//! `1e-20` for the inverse CDF.
+
+use crate::{
+    fixed::{
+        div_nearest, signed_sub, wide_exp, wide_ln, wide_one, widening, PRECISE_DECIMALS,
+        WIDE_DECIMALS,
+    },
+    precise_number::PreciseNumber,
+    signed::SignedPreciseNumber,
+    uint::U256,
+};
+
+/// `1 / sqrt(2 pi)` with 36 decimals
+const INV_SQRT_2PI: u128 = 398_942_280_401_432_677_939_946_059_934_381_868;
+
+/// Magnitude beyond which the CDF is taken as 0 or 1
+const TAIL_CUTOFF: u64 = 9;
+
+/// Numerator coefficients of Abramowitz and Stegun 26.2.23, with 6 decimals
+const ESTIMATE_NUMERATOR: [u128; 3] = [2_515_517, 802_853, 10_328];
+
+/// Denominator coefficients of Abramowitz and Stegun 26.2.23, with 6
+/// decimals
+const ESTIMATE_DENOMINATOR: [u128; 4] = [1_000_000, 1_432_788, 189_269, 1_308];
+
+/// Newton's method stops once a step is below `10^-NEWTON_TOLERANCE_DECIMALS`
+const NEWTON_TOLERANCE_DECIMALS: usize = 22;
+
+/// Most Newton iterations of the inverse CDF, which usually needs four
+const MAX_NEWTON_ITERATIONS: usize = 16;
+
+/// `lhs * rhs` in the intermediate precision, for an `lhs` too large to
+/// multiply by `rhs` directly
+fn mul_wide(lhs: U256, rhs: U256) -> U256 {
+    let one = wide_one();
+    (lhs / one) * rhs + (lhs % one) * rhs / one
+}
+
+/// Sum of two signed intermediates, as magnitudes and whether they are
+/// negative
+pub(crate) fn signed_add(lhs: (U256, bool), rhs: (U256, bool)) -> (U256, bool) {
+    let (magnitude, negative) = if lhs.1 == rhs.1 {
+        (lhs.0 + rhs.0, lhs.1)
+    } else {
+        let (magnitude, below) = signed_sub(lhs.0, rhs.0);
+        (magnitude, below != lhs.1)
+    };
+    (magnitude, negative && !magnitude.is_zero())
+}
+
+/// PDF of `x` in the intermediate precision
+pub(crate) fn wide_pdf(x: U256) -> U256 {
+    let one = wide_one();
+    x.checked_mul(x)
+        .and_then(|square| wide_exp(square / (one * 2), true, WIDE_DECIMALS))
+        .map_or(U256::zero(), |exp| mul_wide(exp, U256::from(INV_SQRT_2PI)))
+}
+
+/// CDF of `x`, or `-x` if `negative`, in the intermediate precision
+pub(crate) fn wide_cdf(x: U256, negative: bool) -> U256 {
+    let one = wide_one();
+    let half = one / 2;
+    let above_half = if x >= one * U256::from(TAIL_CUTOFF) {
+        half
+    } else {
+        // x + x^3 / 3 + x^5 / (3 * 5) + ..., below 5e53 for x < 9
+        let x_squared = x * x / one;
+        let mut sum = x;
+        let mut term = x;
+        let mut n = 3u64;
+        while !term.is_zero() {
+            term = mul_wide(term, x_squared) / U256::from(n);
+            sum += term;
+            n += 2;
+        }
+        // divide by e^(x^2 / 2) rather than multiply by the PDF, which keeps
+        // only a few digits in the tail; both are cut to 128 bits first
+        let Some(exp) = wide_exp(x_squared / 2, false, WIDE_DECIMALS) else {
+            return if negative { U256::zero() } else { one };
+        };
+        let shift = exp.bits().saturating_sub(128);
+        let ratio = (sum >> shift) * one / (exp >> shift);
+        mul_wide(ratio, U256::from(INV_SQRT_2PI)).min(half)
+    };
+    if negative {
+        half - above_half
+    } else {
+        half + above_half
+    }
+}
+
+/// Inverse CDF of `0 < p < 1` in the intermediate precision, as its
+/// magnitude and whether it is negative
+fn wide_inverse_cdf(p: U256) -> Option<(U256, bool)> {
+    let one = wide_one();
+    let half = one / 2;
+    // solve cdf(y) = 1 - q for the upper half, with q <= 1/2
+    let (q, negative) = if p < half {
+        (p, true)
+    } else {
+        (one - p, false)
+    };
+
+    // t - (c0 + c1 t + c2 t^2) / (1 + d1 t + d2 t^2 + d3 t^3), t = sqrt(-2 ln q)
+    let (ln_q, _) = wide_ln(q, WIDE_DECIMALS)?;
+    let t = (ln_q * U256::from(2) * one).integer_sqrt();
+    let coefficient = U256::exp10(WIDE_DECIMALS - 6);
+    let polynomial = |coefficients: &[u128]| {
+        coefficients.iter().rev().fold(U256::zero(), |sum, c| {
+            sum * t / one + U256::from(*c) * coefficient
+        })
+    };
+    let correction = polynomial(&ESTIMATE_NUMERATOR) * one / polynomial(&ESTIMATE_DENOMINATOR);
+    let mut y = signed_sub(t, correction);
+
+    let target = one - q;
+    let tolerance = U256::exp10(WIDE_DECIMALS - NEWTON_TOLERANCE_DECIMALS);
+    for _ in 0..MAX_NEWTON_ITERATIONS {
+        let pdf = wide_pdf(y.0);
+        if pdf.is_zero() {
+            return None;
+        }
+        let (difference, below) = signed_sub(wide_cdf(y.0, y.1), target);
+        let step = difference * one / pdf;
+        y = signed_add(y, (step, !below));
+        if step < tolerance {
+            return Some((y.0, (y.1 != negative) && !y.0.is_zero()));
+        }
+    }
+    None
+}
+
+/// Intermediate of a `PreciseNumber`, saturated
+fn widen(x: &PreciseNumber) -> U256 {
+    x.value
+        .checked_mul(widening(PRECISE_DECIMALS))
+        .unwrap_or(U256::MAX)
+}
+
+/// `PreciseNumber` of an intermediate, rounded to the nearest
+pub(crate) fn narrow(value: U256) -> Option<PreciseNumber> {
+    div_nearest(value, widening(PRECISE_DECIMALS)).map(|value| PreciseNumber { value })
+}
+
+/// Probability density of the standard normal distribution at `x`; it is
+/// even, so the sign of `x` does not matter
+pub fn normal_pdf(x: &PreciseNumber) -> Option<PreciseNumber> {
+    narrow(wide_pdf(widen(x)))
+}
+
+/// Cumulative distribution of the standard normal distribution at `x`, or
+/// at `-x` if `negative`
+pub fn normal_cdf(x: &PreciseNumber, negative: bool) -> Option<PreciseNumber> {
+    narrow(wide_cdf(widen(x), negative))
+}
+
+/// Quantile of `probability` in the standard normal distribution; `None`
+/// unless `0 < probability < 1`
+pub fn normal_inverse_cdf(probability: &PreciseNumber) -> Option<SignedPreciseNumber> {
+    let p = widen(probability);
+    if p.is_zero() || p >= wide_one() {
+        return None;
+    }
+    let (x, negative) = wide_inverse_cdf(p)?;
+    Some(SignedPreciseNumber::from_magnitude(narrow(x)?, negative))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        num_bigint::BigInt,
+        num_traits::{Signed, Zero},
+    };
+
+    /// Decimals of the reference, far beyond the 36 of the intermediates
+    const REFERENCE_DECIMALS: u32 = 100;
+
+    /// Pi with 110 decimals
+    const PI: &str = "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651";
+
+    fn reference_one() -> BigInt {
+        BigInt::from(10).pow(REFERENCE_DECIMALS)
+    }
+
+    fn reference_sqrt(value: &BigInt) -> BigInt {
+        (value * reference_one()).sqrt()
+    }
+
+    fn reference_pi() -> BigInt {
+        PI.parse::<BigInt>().unwrap() / BigInt::from(10).pow(110 - REFERENCE_DECIMALS)
+    }
+
+    /// `sum (-1)^n z^(2n+1) / (n! (2n+1))`, times `2 / sqrt(pi)`
+    fn reference_erf(z: &BigInt) -> BigInt {
+        let one = reference_one();
+        let z_squared = z * z / &one;
+        let mut term = z.clone();
+        let mut sum = z.clone();
+        let mut n = 1u32;
+        while !term.is_zero() {
+            term = -(term * &z_squared) / (&one * BigInt::from(n));
+            sum += &term / BigInt::from(2 * n + 1);
+            n += 1;
+        }
+        sum * 2 * &one / reference_sqrt(&reference_pi())
+    }
+
+    /// `(1 + erf(x / sqrt 2)) / 2`, with the alternating Maclaurin series of
+    /// erf, unrelated to the series of the implementation
+    fn reference_cdf(x: &BigInt) -> BigInt {
+        let one = reference_one();
+        let z = x * &one / reference_sqrt(&(BigInt::from(2) * &one));
+        (&one + reference_erf(&z)) / 2
+    }
+
+    /// `e^(-x^2 / 2) / sqrt(2 pi)`, with the alternating Taylor series of exp
+    fn reference_pdf(x: &BigInt) -> BigInt {
+        let one = reference_one();
+        let argument = -(x * x) / (&one * 2);
+        let mut term = one.clone();
+        let mut exp = one.clone();
+        let mut n = 1u32;
+        while !term.is_zero() {
+            term = term * &argument / (&one * BigInt::from(n));
+            exp += &term;
+            n += 1;
+        }
+        exp * &one / reference_sqrt(&(BigInt::from(2) * reference_pi()))
+    }
+
+    /// Reference of a signed `PreciseNumber`
+    fn reference(x: &PreciseNumber, negative: bool) -> BigInt {
+        let x = BigInt::from(x.value.as_u128())
+            * BigInt::from(10).pow(REFERENCE_DECIMALS - PRECISE_DECIMALS as u32);
+        if negative {
+            -x
+        } else {
+            x
+        }
+    }
+
+    /// Whether `value` is the reference rounded to the nearest unit in the
+    /// last place, give or take `1e-30`
+    fn is_rounded(value: &PreciseNumber, reference: &BigInt) -> bool {
+        let scale = BigInt::from(10).pow(REFERENCE_DECIMALS - PRECISE_DECIMALS as u32);
+        let value = BigInt::from(value.value.as_u128()) * &scale;
+        let slack = BigInt::from(10).pow(REFERENCE_DECIMALS - 30);
+        (value - reference).abs() <= scale / 2 + slack
+    }
+
+    fn precise(value: i128) -> (PreciseNumber, bool) {
+        let x = PreciseNumber {
+            value: U256::from(value.unsigned_abs()),
+        };
+        (x, value < 0)
+    }
+
+    #[test]
+    fn test_against_reference() {
+        // [-8, 8] by 1/16
+        for sixteenths in -128i128..=128 {
+            let (x, negative) = precise(sixteenths * 62_500_000_000);
+            let reference_x = reference(&x, negative);
+
+            let cdf = normal_cdf(&x, negative).unwrap();
+            assert!(
+                is_rounded(&cdf, &reference_cdf(&reference_x)),
+                "cdf({}/16) = {}",
+                sixteenths,
+                cdf.value
+            );
+            let pdf = normal_pdf(&x).unwrap();
+            assert!(
+                is_rounded(&pdf, &reference_pdf(&reference_x)),
+                "pdf({}/16) = {}",
+                sixteenths,
+                pdf.value
+            );
+        }
+    }
+
+    #[test]
+    fn test_intermediate_precision() {
+        // the intermediates themselves, before rounding
+        let scale = BigInt::from(10).pow(REFERENCE_DECIMALS - WIDE_DECIMALS as u32);
+        let slack = BigInt::from(10).pow(REFERENCE_DECIMALS - 30);
+        for sixteenths in -128i128..=128 {
+            let (x, negative) = precise(sixteenths * 62_500_000_000);
+            let reference_x = reference(&x, negative);
+            let cdf = wide_cdf(widen(&x), negative)
+                .to_string()
+                .parse::<BigInt>()
+                .unwrap()
+                * &scale;
+            assert!(
+                (cdf - reference_cdf(&reference_x)).abs() <= slack,
+                "cdf({}/16)",
+                sixteenths
+            );
+        }
+    }
+
+    #[test]
+    fn test_tails() {
+        let one = PreciseNumber::new(1).unwrap();
+        let zero = PreciseNumber::new(0).unwrap();
+        for x in [9, 10, 1_000_000] {
+            let x = PreciseNumber::new(x).unwrap();
+            assert_eq!(normal_cdf(&x, false).unwrap().value, one.value);
+            assert_eq!(normal_cdf(&x, true).unwrap().value, zero.value);
+            assert_eq!(normal_pdf(&x).unwrap().value, zero.value);
+        }
+        let max = PreciseNumber { value: U256::MAX };
+        assert_eq!(normal_cdf(&max, false).unwrap().value, one.value);
+    }
+
+    #[test]
+    fn test_inverse_cdf() {
+        let half_unit = BigInt::from(10).pow(REFERENCE_DECIMALS - PRECISE_DECIMALS as u32) / 2;
+        let slack = BigInt::from(10).pow(REFERENCE_DECIMALS - 20);
+        for probability in [
+            1,
+            1_000,
+            1_000_000,
+            1_000_000_000,
+            25_000_000_000,
+            100_000_000_000,
+            300_000_000_000,
+            500_000_000_000,
+            700_000_000_000,
+            975_000_000_000,
+            999_999_000_000,
+            999_999_999_999,
+        ] {
+            let (p, _) = precise(probability);
+            let x = normal_inverse_cdf(&p).unwrap();
+            // the exact quantile is within half a unit of x, so the
+            // reference CDF brackets p
+            let x = reference(&x.value, x.negative);
+            let p = reference(&p, false);
+            let below = &x - &half_unit - &slack;
+            let above = &x + &half_unit + &slack;
+            assert!(reference_cdf(&below) <= p, "quantile of {}", probability);
+            assert!(reference_cdf(&above) >= p, "quantile of {}", probability);
+        }
+    }
+
+    #[test]
+    fn test_inverse_cdf_values() {
+        let quantile = |probability| {
+            let (p, _) = precise(probability);
+            normal_inverse_cdf(&p).map(|x| (x.value.value.as_u128(), x.negative))
+        };
+        assert_eq!(quantile(500_000_000_000), Some((0, false)));
+        // 1.959963984540054
+        assert_eq!(quantile(975_000_000_000), Some((1_959_963_984_540, false)));
+        assert_eq!(quantile(25_000_000_000), Some((1_959_963_984_540, true)));
+        // 7.034483825301132
+        assert_eq!(quantile(1), Some((7_034_483_825_301, true)));
+        assert_eq!(quantile(0), None);
+        assert_eq!(quantile(1_000_000_000_000), None);
+    }
+}
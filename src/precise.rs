@@ -0,0 +1,849 @@
+//! Arithmetic of `PreciseNumber` and `PreciseNumber256D18` on raw inner
+//! values
+
+use crate::{
+    approximations::f32_normal_cdf,
+    bench::Bench,
+    d18,
+    error::MathError,
+    fixed::FixedPointMath,
+    instruction::{
+        D18Operation, FixedPointOperation, FixedPointType, MathResult, NormalOperation,
+        PreciseOperation, SignedOperation, SignedValue,
+    },
+    normal::{normal_cdf, normal_inverse_cdf, normal_pdf},
+    precise_number::{self, PreciseNumber, PreciseNumber256D18},
+    signed::{Magnitude, Signed},
+    uint::U256,
+};
+
+/// Precise number with the given inner value
+fn precise(value: [u64; 4]) -> PreciseNumber {
+    PreciseNumber { value: U256(value) }
+}
+
+/// Run the operation and return the inner value of its result
+pub(crate) fn process_precise_arithmetic(
+    bench: &Bench,
+    operation: PreciseOperation,
+) -> Result<MathResult, MathError> {
+    let result = match operation {
+        PreciseOperation::Add { lhs, rhs } => {
+            let (lhs, rhs) = (precise(lhs), precise(rhs));
+            bench
+                .measure("precise_add", || lhs.checked_add(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        PreciseOperation::Sub { lhs, rhs } => {
+            let (lhs, rhs) = (precise(lhs), precise(rhs));
+            bench
+                .measure("precise_sub", || lhs.checked_sub(&rhs))
+                .ok_or(MathError::Underflow)?
+        }
+        PreciseOperation::Mul { lhs, rhs } => {
+            let (lhs, rhs) = (precise(lhs), precise(rhs));
+            bench
+                .measure("precise_mul", || lhs.checked_mul(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        PreciseOperation::Div { lhs, rhs } => {
+            if rhs == [0; 4] {
+                return Err(MathError::DivisionByZero);
+            }
+            let (lhs, rhs) = (precise(lhs), precise(rhs));
+            bench
+                .measure("precise_div", || lhs.checked_div(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        PreciseOperation::Pow { base, exponent } => {
+            let base = precise(base);
+            bench
+                .measure("precise_pow", || base.checked_pow(exponent))
+                .ok_or(MathError::Overflow)?
+        }
+        PreciseOperation::Floor { value } => {
+            let value = precise(value);
+            bench
+                .measure("precise_floor", || value.floor())
+                .ok_or(MathError::Overflow)?
+        }
+        PreciseOperation::Ceiling { value } => {
+            let value = precise(value);
+            bench
+                .measure("precise_ceiling", || value.ceiling())
+                .ok_or(MathError::Overflow)?
+        }
+        PreciseOperation::AlmostEq {
+            lhs,
+            rhs,
+            precision,
+        } => {
+            let (lhs, rhs) = (precise(lhs), precise(rhs));
+            let almost_eq =
+                bench.measure("precise_almost_eq", || lhs.almost_eq(&rhs, U256(precision)));
+            return Ok(MathResult::Bool(almost_eq));
+        }
+    };
+    Ok(MathResult::Precise(result.value.0))
+}
+
+/// Run the operation and return the inner value of its result, or the
+/// integer for `ToU128`
+pub(crate) fn process_precise_d18_arithmetic(
+    bench: &Bench,
+    operation: D18Operation,
+) -> Result<MathResult, MathError> {
+    let result = match operation {
+        D18Operation::FromU128 { value } => bench
+            .measure("d18_local_from_u128", || d18::from_u128(value))
+            .ok_or(MathError::Overflow)?,
+        D18Operation::FromDecimal { value } => bench
+            .measure("d18_local_from_decimal", || d18::from_decimal_str(&value))
+            .ok_or(MathError::ConversionFailed)?,
+        D18Operation::Mul { lhs, rhs } => {
+            let (lhs, rhs) = (d18::from_limbs(lhs), d18::from_limbs(rhs));
+            bench
+                .measure("d18_mul", || lhs.checked_mul(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        D18Operation::Div { lhs, rhs } => {
+            if rhs == [0; 4] {
+                return Err(MathError::DivisionByZero);
+            }
+            let (lhs, rhs) = (d18::from_limbs(lhs), d18::from_limbs(rhs));
+            bench
+                .measure("d18_div", || lhs.checked_div(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        D18Operation::MulFloor { lhs, rhs } => {
+            let (lhs, rhs) = (d18::from_limbs(lhs), d18::from_limbs(rhs));
+            bench
+                .measure("d18_local_mul_floor", || d18::checked_mul_floor(&lhs, &rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        D18Operation::MulCeil { lhs, rhs } => {
+            let (lhs, rhs) = (d18::from_limbs(lhs), d18::from_limbs(rhs));
+            bench
+                .measure("d18_local_mul_ceil", || d18::checked_mul_ceil(&lhs, &rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        D18Operation::DivFloor { lhs, rhs } => {
+            if rhs == [0; 4] {
+                return Err(MathError::DivisionByZero);
+            }
+            let (lhs, rhs) = (d18::from_limbs(lhs), d18::from_limbs(rhs));
+            bench
+                .measure("d18_local_div_floor", || d18::checked_div_floor(&lhs, &rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        D18Operation::DivCeil { lhs, rhs } => {
+            if rhs == [0; 4] {
+                return Err(MathError::DivisionByZero);
+            }
+            let (lhs, rhs) = (d18::from_limbs(lhs), d18::from_limbs(rhs));
+            bench
+                .measure("d18_local_div_ceil", || d18::checked_div_ceil(&lhs, &rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        D18Operation::Pow { base, exponent } => {
+            let base = d18::from_limbs(base);
+            bench
+                .measure("d18_pow", || base.checked_pow(exponent))
+                .ok_or(MathError::Overflow)?
+        }
+        D18Operation::ToU128 { value } => {
+            let value = d18::from_limbs(value);
+            let result = bench
+                .measure("d18_to_u128", || value.to_imprecise())
+                .ok_or(MathError::Overflow)?;
+            return Ok(MathResult::U128(result));
+        }
+    };
+    Ok(MathResult::Precise(result.value.0))
+}
+
+/// Run the operation on the signed counterpart of the fixed-point type and
+/// return the inner value of the magnitude of its result and its sign
+pub(crate) fn process_signed_arithmetic(
+    bench: &Bench,
+    number: FixedPointType,
+    operation: SignedOperation,
+) -> Result<MathResult, MathError> {
+    match number {
+        FixedPointType::PreciseNumber => {
+            process_signed::<PreciseNumber>(bench, "signed", operation)
+        }
+        FixedPointType::PreciseNumber256D18 => {
+            process_signed::<PreciseNumber256D18>(bench, "signed_d18", operation)
+        }
+    }
+}
+
+/// Run the operation on `Signed<T>` and return the inner value of the
+/// magnitude of its result and its sign
+fn process_signed<T: Magnitude>(
+    bench: &Bench,
+    prefix: &str,
+    operation: SignedOperation,
+) -> Result<MathResult, MathError> {
+    let label = |operation: &str| format!("{}_{}", prefix, operation);
+    let signed = |operand: SignedValue| {
+        Signed::from_magnitude(T::from_inner(U256(operand.value)), operand.negative)
+    };
+    let result = match operation {
+        SignedOperation::Add { lhs, rhs } => {
+            let (lhs, rhs) = (signed(lhs), signed(rhs));
+            bench
+                .measure(&label("add"), || lhs.checked_add(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        SignedOperation::Sub { lhs, rhs } => {
+            let (lhs, rhs) = (signed(lhs), signed(rhs));
+            bench
+                .measure(&label("sub"), || lhs.checked_sub(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        SignedOperation::Mul { lhs, rhs } => {
+            let (lhs, rhs) = (signed(lhs), signed(rhs));
+            bench
+                .measure(&label("mul"), || lhs.checked_mul(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        SignedOperation::Div { lhs, rhs } => {
+            if rhs.value == [0; 4] {
+                return Err(MathError::DivisionByZero);
+            }
+            let (lhs, rhs) = (signed(lhs), signed(rhs));
+            bench
+                .measure(&label("div"), || lhs.checked_div(&rhs))
+                .ok_or(MathError::Overflow)?
+        }
+        SignedOperation::Pow { base, exponent } => {
+            let base = signed(base);
+            bench
+                .measure(&label("pow"), || base.checked_pow(exponent))
+                .ok_or(MathError::Overflow)?
+        }
+        SignedOperation::Floor { value } => {
+            let value = signed(value);
+            bench
+                .measure(&label("floor"), || value.floor())
+                .ok_or(MathError::Overflow)?
+        }
+        SignedOperation::Ceiling { value } => {
+            let value = signed(value);
+            bench
+                .measure(&label("ceiling"), || value.ceiling())
+                .ok_or(MathError::Overflow)?
+        }
+        SignedOperation::AlmostEq {
+            lhs,
+            rhs,
+            precision,
+        } => {
+            let (lhs, rhs) = (signed(lhs), signed(rhs));
+            let almost_eq =
+                bench.measure(&label("almost_eq"), || lhs.almost_eq(&rhs, U256(precision)));
+            return Ok(MathResult::Bool(almost_eq));
+        }
+    };
+    Ok(MathResult::SignedPrecise {
+        value: result.value.inner().0,
+        negative: result.negative,
+    })
+}
+
+/// Value of an inner value with `one` as 1, for the f64 comparison
+fn to_f64(value: [u64; 4], one: u128) -> f64 {
+    let value = value
+        .iter()
+        .rev()
+        .fold(0.0, |value, limb| value * 2f64.powi(64) + *limb as f64);
+    value / one as f64
+}
+
+/// Run the operation on `T` and then in f64, and return the inner value of
+/// the fixed-point result
+fn process_fixed_point<T: FixedPointMath>(
+    bench: &Bench,
+    prefix: &str,
+    one: u128,
+    number: impl Fn([u64; 4]) -> T,
+    inner: impl Fn(T) -> [u64; 4],
+    operation: FixedPointOperation,
+) -> Result<MathResult, MathError> {
+    let label = |operation: &str| format!("{}_{}", prefix, operation);
+    match operation {
+        FixedPointOperation::Exp { value, negative } => {
+            let float = to_f64(value, one);
+            let float = if negative { -float } else { float };
+            let value = number(value);
+            let result = if negative {
+                bench.measure(&label("exp"), || value.checked_exp_neg())
+            } else {
+                bench.measure(&label("exp"), || value.checked_exp())
+            };
+            bench.measure("f64_exp", || float.exp());
+            Ok(MathResult::Precise(inner(
+                result.ok_or(MathError::Overflow)?,
+            )))
+        }
+        FixedPointOperation::Ln { value } => {
+            let float = to_f64(value, one);
+            let value = number(value);
+            let result = bench
+                .measure(&label("ln"), || value.checked_ln())
+                .ok_or(MathError::DomainError)?;
+            bench.measure("f64_ln", || float.ln());
+            Ok(MathResult::SignedPrecise {
+                value: inner(result.value),
+                negative: result.negative,
+            })
+        }
+        FixedPointOperation::Log2 { value } => {
+            let float = to_f64(value, one);
+            let value = number(value);
+            let result = bench
+                .measure(&label("log2"), || value.checked_log2())
+                .ok_or(MathError::DomainError)?;
+            bench.measure("f64_log2", || float.log2());
+            Ok(MathResult::SignedPrecise {
+                value: inner(result.value),
+                negative: result.negative,
+            })
+        }
+        FixedPointOperation::Pow { base, exponent } => {
+            let (float_base, float_exponent) = (to_f64(base, one), to_f64(exponent, one));
+            let (base, exponent) = (number(base), number(exponent));
+            let result = bench
+                .measure(&label("pow_fraction"), || {
+                    base.checked_pow_fraction(&exponent)
+                })
+                .ok_or(MathError::Overflow)?;
+            bench.measure("f64_powf", || float_base.powf(float_exponent));
+            Ok(MathResult::Precise(inner(result)))
+        }
+        FixedPointOperation::DirectedPow {
+            base,
+            exponent,
+            round_up,
+        } => {
+            let (float_base, float_exponent) = (to_f64(base, one), to_f64(exponent, one));
+            let (base, exponent) = (number(base), number(exponent));
+            let result = if round_up {
+                bench.measure(&label("pow_up"), || base.checked_pow_up(&exponent))
+            } else {
+                bench.measure(&label("pow_down"), || base.checked_pow_down(&exponent))
+            };
+            bench.measure("f64_powf", || float_base.powf(float_exponent));
+            Ok(MathResult::Precise(inner(
+                result.ok_or(MathError::Overflow)?,
+            )))
+        }
+    }
+}
+
+/// Run the exponential or logarithm on the fixed-point type, and the same
+/// operation in f64 for comparison
+pub(crate) fn process_fixed_point_math(
+    bench: &Bench,
+    number: FixedPointType,
+    operation: FixedPointOperation,
+) -> Result<MathResult, MathError> {
+    match number {
+        FixedPointType::PreciseNumber => process_fixed_point(
+            bench,
+            "precise",
+            precise_number::ONE,
+            precise,
+            |number| number.value.0,
+            operation,
+        ),
+        FixedPointType::PreciseNumber256D18 => process_fixed_point(
+            bench,
+            "d18",
+            d18::ONE,
+            d18::from_limbs,
+            |number| number.value.0,
+            operation,
+        ),
+    }
+}
+
+/// `1 / sqrt(2 pi)` in f32
+const F32_INV_SQRT_2PI: f32 = 0.398_942_3;
+
+/// Run the normal distribution of a `PreciseNumber`, then in f32, and
+/// return the inner value of the fixed-point result
+pub(crate) fn process_normal_distribution(
+    bench: &Bench,
+    operation: NormalOperation,
+) -> Result<MathResult, MathError> {
+    match operation {
+        NormalOperation::Cdf { argument, negative } => {
+            let float = to_f64(argument, precise_number::ONE) as f32;
+            let float = if negative { -float } else { float };
+            let argument = precise(argument);
+            let result = bench
+                .measure("precise_normal_cdf", || normal_cdf(&argument, negative))
+                .ok_or(MathError::Overflow)?;
+            bench.measure("f32_normal_cdf", || f32_normal_cdf(float));
+            Ok(MathResult::Precise(result.value.0))
+        }
+        NormalOperation::Pdf { argument } => {
+            let float = to_f64(argument, precise_number::ONE) as f32;
+            let argument = precise(argument);
+            let result = bench
+                .measure("precise_normal_pdf", || normal_pdf(&argument))
+                .ok_or(MathError::Overflow)?;
+            bench.measure("f32_normal_pdf", || {
+                (-float * float / 2.0).exp() * F32_INV_SQRT_2PI
+            });
+            Ok(MathResult::Precise(result.value.0))
+        }
+        NormalOperation::InverseCdf { probability } => {
+            let probability = precise(probability);
+            let result = bench
+                .measure("precise_normal_inverse_cdf", || {
+                    normal_inverse_cdf(&probability)
+                })
+                .ok_or(MathError::DomainError)?;
+            Ok(MathResult::SignedPrecise {
+                value: result.value.value.0,
+                negative: result.negative,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::precise_number::ONE};
+
+    /// Inner value of `units / 100`
+    fn hundredths(units: u128) -> [u64; 4] {
+        (U256::from(units) * U256::from(ONE) / U256::from(100)).0
+    }
+
+    fn run(operation: PreciseOperation) -> Result<MathResult, MathError> {
+        process_precise_arithmetic(&Bench::default(), operation)
+    }
+
+    #[test]
+    fn test_precise_arithmetic() {
+        let (lhs, rhs) = (hundredths(150), hundredths(225));
+        assert_eq!(
+            run(PreciseOperation::Add { lhs, rhs }),
+            Ok(MathResult::Precise(hundredths(375)))
+        );
+        assert_eq!(
+            run(PreciseOperation::Sub { lhs: rhs, rhs: lhs }),
+            Ok(MathResult::Precise(hundredths(75)))
+        );
+        assert_eq!(
+            run(PreciseOperation::Mul {
+                lhs,
+                rhs: hundredths(200)
+            }),
+            Ok(MathResult::Precise(hundredths(300)))
+        );
+        assert_eq!(
+            run(PreciseOperation::Div {
+                lhs: hundredths(300),
+                rhs: hundredths(200)
+            }),
+            Ok(MathResult::Precise(lhs))
+        );
+        assert_eq!(
+            run(PreciseOperation::Pow {
+                base: hundredths(200),
+                exponent: 10
+            }),
+            Ok(MathResult::Precise(hundredths(102400)))
+        );
+    }
+
+    #[test]
+    fn test_precise_rounding() {
+        assert_eq!(
+            run(PreciseOperation::Floor {
+                value: hundredths(270)
+            }),
+            Ok(MathResult::Precise(hundredths(200)))
+        );
+        assert_eq!(
+            run(PreciseOperation::Ceiling {
+                value: hundredths(230)
+            }),
+            Ok(MathResult::Precise(hundredths(300)))
+        );
+        assert_eq!(
+            run(PreciseOperation::Ceiling {
+                value: hundredths(300)
+            }),
+            Ok(MathResult::Precise(hundredths(300)))
+        );
+    }
+
+    #[test]
+    fn test_precise_almost_eq() {
+        let almost_eq = |lhs, rhs| {
+            run(PreciseOperation::AlmostEq {
+                lhs: hundredths(lhs),
+                rhs: hundredths(rhs),
+                precision: hundredths(1),
+            })
+        };
+        assert_eq!(almost_eq(100, 100), Ok(MathResult::Bool(true)));
+        assert_eq!(almost_eq(101, 100), Ok(MathResult::Bool(false)));
+        assert_eq!(almost_eq(100, 101), Ok(MathResult::Bool(false)));
+    }
+
+    #[test]
+    fn test_precise_d18_arithmetic() {
+        let run = |operation| process_precise_d18_arithmetic(&Bench::default(), operation);
+        let inner = |value: &str| d18::from_decimal_str(value).unwrap().value.0;
+        assert_eq!(
+            run(D18Operation::FromDecimal {
+                value: "1.5".to_string()
+            }),
+            Ok(MathResult::Precise(inner("1.5")))
+        );
+        assert_eq!(
+            run(D18Operation::FromDecimal {
+                value: "1,5".to_string()
+            }),
+            Err(MathError::ConversionFailed)
+        );
+        assert_eq!(
+            run(D18Operation::DivCeil {
+                lhs: inner("2"),
+                rhs: inner("3")
+            }),
+            Ok(MathResult::Precise(inner("0.666666666666666667")))
+        );
+        assert_eq!(
+            run(D18Operation::DivFloor {
+                lhs: inner("2"),
+                rhs: [0; 4]
+            }),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(D18Operation::Div {
+                lhs: inner("2"),
+                rhs: inner("3")
+            }),
+            Ok(MathResult::Precise(inner("0.666666666666666667")))
+        );
+        assert_eq!(
+            run(D18Operation::Div {
+                lhs: inner("2"),
+                rhs: [0; 4]
+            }),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(D18Operation::Mul {
+                lhs: inner("1.5"),
+                rhs: inner("3")
+            }),
+            Ok(MathResult::Precise(inner("4.5")))
+        );
+        assert_eq!(
+            run(D18Operation::Mul {
+                lhs: inner("0.000000000000000001"),
+                rhs: inner("0.5")
+            }),
+            Ok(MathResult::Precise(inner("0.000000000000000001")))
+        );
+        assert_eq!(
+            run(D18Operation::MulFloor {
+                lhs: inner("0.000000000000000001"),
+                rhs: inner("0.5")
+            }),
+            Ok(MathResult::Precise([0; 4]))
+        );
+        assert_eq!(
+            run(D18Operation::Pow {
+                base: inner("1.5"),
+                exponent: 4
+            }),
+            Ok(MathResult::Precise(inner("5.0625")))
+        );
+        assert_eq!(
+            run(D18Operation::ToU128 {
+                value: inner("2.4")
+            }),
+            Ok(MathResult::U128(2))
+        );
+        assert_eq!(
+            run(D18Operation::ToU128 {
+                value: inner("340282366920938463463374607431768211454.5")
+            }),
+            Ok(MathResult::U128(u128::MAX))
+        );
+    }
+
+    #[test]
+    fn test_fixed_point_math() {
+        let run =
+            |number, operation| process_fixed_point_math(&Bench::default(), number, operation);
+        let d18_inner = |value: &str| d18::from_decimal_str(value).unwrap().value.0;
+        assert_eq!(
+            run(
+                FixedPointType::PreciseNumber,
+                FixedPointOperation::Exp {
+                    value: hundredths(100),
+                    negative: false
+                }
+            ),
+            Ok(MathResult::Precise(U256::from(2_718_281_828_459u128).0))
+        );
+        assert_eq!(
+            run(
+                FixedPointType::PreciseNumber256D18,
+                FixedPointOperation::Ln {
+                    value: d18_inner("0.5")
+                }
+            ),
+            Ok(MathResult::SignedPrecise {
+                value: d18_inner("0.693147180559945309"),
+                negative: true
+            })
+        );
+        assert_eq!(
+            run(
+                FixedPointType::PreciseNumber256D18,
+                FixedPointOperation::Log2 { value: [0; 4] }
+            ),
+            Err(MathError::DomainError)
+        );
+        assert_eq!(
+            run(
+                FixedPointType::PreciseNumber256D18,
+                FixedPointOperation::Pow {
+                    base: d18_inner("4"),
+                    exponent: d18_inner("1.5")
+                }
+            ),
+            Ok(MathResult::Precise(d18_inner("8")))
+        );
+        assert_eq!(
+            run(
+                FixedPointType::PreciseNumber,
+                FixedPointOperation::DirectedPow {
+                    base: hundredths(25),
+                    exponent: hundredths(50),
+                    round_up: false
+                }
+            ),
+            Ok(MathResult::Precise(U256::from(499_999_999_999u128).0))
+        );
+        assert_eq!(
+            run(
+                FixedPointType::PreciseNumber,
+                FixedPointOperation::DirectedPow {
+                    base: hundredths(25),
+                    exponent: hundredths(50),
+                    round_up: true
+                }
+            ),
+            Ok(MathResult::Precise(U256::from(500_000_000_001u128).0))
+        );
+        assert_eq!(
+            run(
+                FixedPointType::PreciseNumber,
+                FixedPointOperation::Exp {
+                    value: hundredths(100_000),
+                    negative: false
+                }
+            ),
+            Err(MathError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_normal_distribution() {
+        let run = |operation| process_normal_distribution(&Bench::default(), operation);
+        let inner = |value: u128| U256::from(value).0;
+        assert_eq!(
+            run(NormalOperation::Cdf {
+                argument: hundredths(196),
+                negative: false
+            }),
+            Ok(MathResult::Precise(inner(975_002_104_852)))
+        );
+        assert_eq!(
+            run(NormalOperation::Cdf {
+                argument: hundredths(196),
+                negative: true
+            }),
+            Ok(MathResult::Precise(inner(24_997_895_148)))
+        );
+        assert_eq!(
+            run(NormalOperation::Pdf {
+                argument: hundredths(100)
+            }),
+            Ok(MathResult::Precise(inner(241_970_724_519)))
+        );
+        assert_eq!(
+            run(NormalOperation::InverseCdf {
+                probability: inner(25_000_000_000)
+            }),
+            Ok(MathResult::SignedPrecise {
+                value: inner(1_959_963_984_540),
+                negative: true
+            })
+        );
+        for probability in [0, ONE] {
+            assert_eq!(
+                run(NormalOperation::InverseCdf {
+                    probability: inner(probability)
+                }),
+                Err(MathError::DomainError)
+            );
+        }
+    }
+
+    #[test]
+    fn test_precise_errors() {
+        let max = U256::MAX.0;
+        let one = hundredths(100);
+        assert_eq!(
+            run(PreciseOperation::Add { lhs: max, rhs: one }),
+            Err(MathError::Overflow)
+        );
+        assert_eq!(
+            run(PreciseOperation::Sub {
+                lhs: [0; 4],
+                rhs: one
+            }),
+            Err(MathError::Underflow)
+        );
+        assert_eq!(
+            run(PreciseOperation::Div {
+                lhs: one,
+                rhs: [0; 4]
+            }),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(PreciseOperation::Pow {
+                base: max,
+                exponent: 2
+            }),
+            Err(MathError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_signed_arithmetic() {
+        let run = |operation| {
+            process_signed_arithmetic(&Bench::default(), FixedPointType::PreciseNumber, operation)
+        };
+        let signed = |units: u128, negative| SignedValue {
+            value: hundredths(units),
+            negative,
+        };
+        // where the unsigned `Sub` underflows
+        assert_eq!(
+            run(SignedOperation::Sub {
+                lhs: signed(150, false),
+                rhs: signed(225, false)
+            }),
+            Ok(MathResult::SignedPrecise {
+                value: hundredths(75),
+                negative: true
+            })
+        );
+        assert_eq!(
+            run(SignedOperation::Mul {
+                lhs: signed(150, true),
+                rhs: signed(200, true)
+            }),
+            Ok(MathResult::SignedPrecise {
+                value: hundredths(300),
+                negative: false
+            })
+        );
+        assert_eq!(
+            run(SignedOperation::Floor {
+                value: signed(150, true)
+            }),
+            Ok(MathResult::SignedPrecise {
+                value: hundredths(200),
+                negative: true
+            })
+        );
+        assert_eq!(
+            run(SignedOperation::AlmostEq {
+                lhs: signed(1, true),
+                rhs: signed(1, false),
+                precision: hundredths(3)
+            }),
+            Ok(MathResult::Bool(true))
+        );
+        assert_eq!(
+            run(SignedOperation::Div {
+                lhs: signed(100, true),
+                rhs: signed(0, true)
+            }),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(SignedOperation::Add {
+                lhs: SignedValue {
+                    value: U256::MAX.0,
+                    negative: true
+                },
+                rhs: signed(100, true)
+            }),
+            Err(MathError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_signed_d18_arithmetic() {
+        let run = |operation| {
+            process_signed_arithmetic(
+                &Bench::default(),
+                FixedPointType::PreciseNumber256D18,
+                operation,
+            )
+        };
+        let signed = |value: &str, negative| SignedValue {
+            value: d18::from_decimal_str(value).unwrap().value.0,
+            negative,
+        };
+        assert_eq!(
+            run(SignedOperation::Sub {
+                lhs: signed("1.5", false),
+                rhs: signed("2.25", false)
+            }),
+            Ok(MathResult::SignedPrecise {
+                value: signed("0.75", false).value,
+                negative: true
+            })
+        );
+        assert_eq!(
+            run(SignedOperation::Div {
+                lhs: signed("2", true),
+                rhs: signed("3", false)
+            }),
+            Ok(MathResult::SignedPrecise {
+                value: signed("0.666666666666666667", false).value,
+                negative: true
+            })
+        );
+        assert_eq!(
+            run(SignedOperation::Ceiling {
+                value: signed("0.000000000000000001", true)
+            }),
+            Ok(MathResult::SignedPrecise {
+                value: [0; 4],
+                negative: false
+            })
+        );
+    }
+}
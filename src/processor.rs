@@ -1,321 +1,442 @@
 #![allow(clippy::arithmetic_side_effects)]
 //! Program state processor
 
-use crate::instruction::SqrtAlgorithm;
-use solana_program::compute_units::sol_remaining_compute_units;
-use spl_math::precise_number::PreciseNumber256D18;
+use crate::instruction::{D18Input, SqrtAlgorithm};
 use {
     crate::{
         approximations::{f32_normal_cdf, sqrt},
-        instruction::MathInstruction,
+        batch::process_batch,
+        bench::Bench,
+        black_scholes::process_black_scholes,
+        checked_ceil_div::CheckedCeilDiv,
+        compare::process_compare,
+        curve::process_curve,
+        error::MathError,
+        instruction::{MathInstruction, MathResult, RoundingMode},
+        integer_sqrt::process_integer_sqrt,
+        interest::process_interest,
+        precise::{
+            process_fixed_point_math, process_normal_distribution, process_precise_arithmetic,
+            process_precise_d18_arithmetic, process_signed_arithmetic,
+        },
         precise_number::PreciseNumber,
+        rounding::MulDivRounding,
+        stableswap::process_stable_swap,
+        sweep::process_sweep,
+        weighted::process_weighted_pool,
+        wide::process_wide_arithmetic,
     },
     borsh::BorshDeserialize,
+    num_traits::{Float, PrimInt},
     solana_program::{
-        account_info::AccountInfo, entrypoint::ProgramResult, log::sol_log_compute_units, msg,
+        account_info::AccountInfo, entrypoint::ProgramResult, msg, program::set_return_data,
         pubkey::Pubkey,
     },
 };
 
-/// Compensate for compute units used syscall overhead; checked by Noop instruction
-pub const CU_CORRECTION: u64 = 102;
-
 /// u64_multiply
 #[inline(never)]
-fn u64_multiply(multiplicand: u64, multiplier: u64) -> u64 {
+pub(crate) fn u64_multiply(multiplicand: u64, multiplier: u64) -> u64 {
     multiplicand * multiplier
 }
 
 /// u64_divide
 #[inline(never)]
-fn u64_divide(dividend: u64, divisor: u64) -> u64 {
+pub(crate) fn u64_divide(dividend: u64, divisor: u64) -> u64 {
     dividend / divisor
 }
 
 /// f32_multiply
 #[inline(never)]
-fn f32_multiply(multiplicand: f32, multiplier: f32) -> f32 {
+pub(crate) fn f32_multiply(multiplicand: f32, multiplier: f32) -> f32 {
     multiplicand * multiplier
 }
 
 /// f32_divide
 #[inline(never)]
-fn f32_divide(dividend: f32, divisor: f32) -> f32 {
+pub(crate) fn f32_divide(dividend: f32, divisor: f32) -> f32 {
     dividend / divisor
 }
 
 /// f32_exponentiate
 #[inline(never)]
-fn f32_exponentiate(base: f32, exponent: f32) -> f32 {
+pub(crate) fn f32_exponentiate(base: f32, exponent: f32) -> f32 {
     base.powf(exponent)
 }
 
 /// f32_natural_log
 #[inline(never)]
-fn f32_natural_log(argument: f32) -> f32 {
+pub(crate) fn f32_natural_log(argument: f32) -> f32 {
     argument.ln()
 }
 
 /// u128_multiply
 #[inline(never)]
-fn u128_multiply(multiplicand: u128, multiplier: u128) -> u128 {
+pub(crate) fn u128_multiply(multiplicand: u128, multiplier: u128) -> u128 {
     multiplicand * multiplier
 }
 
 /// u128_divide
 #[inline(never)]
-fn u128_divide(dividend: u128, divisor: u128) -> u128 {
+pub(crate) fn u128_divide(dividend: u128, divisor: u128) -> u128 {
     dividend / divisor
 }
 
 /// f64_multiply
 #[inline(never)]
-fn f64_multiply(multiplicand: f64, multiplier: f64) -> f64 {
+pub(crate) fn f64_multiply(multiplicand: f64, multiplier: f64) -> f64 {
     multiplicand * multiplier
 }
 
 /// f64_divide
 #[inline(never)]
-fn f64_divide(dividend: f64, divisor: f64) -> f64 {
+pub(crate) fn f64_divide(dividend: f64, divisor: f64) -> f64 {
     dividend / divisor
 }
 
+/// Reject floating point inputs and results that are not a number
+pub(crate) fn not_nan<T: Float>(value: T) -> Result<T, MathError> {
+    if value.is_nan() {
+        Err(MathError::NotANumber)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Reject a floating point divisor that is not a number or zero
+pub(crate) fn check_divisor<T: Float>(divisor: T) -> Result<T, MathError> {
+    if not_nan(divisor)?.is_zero() {
+        Err(MathError::DivisionByZero)
+    } else {
+        Ok(divisor)
+    }
+}
+
+/// Reject integer operands whose product does not fit
+pub(crate) fn check_product<T: PrimInt>(
+    multiplicand: T,
+    multiplier: T,
+) -> Result<(T, T), MathError> {
+    if multiplicand.checked_mul(&multiplier).is_none() {
+        Err(MathError::Overflow)
+    } else {
+        Ok((multiplicand, multiplier))
+    }
+}
+
+/// Reject a zero integer divisor
+pub(crate) fn check_integer_divisor<T: PrimInt>(divisor: T) -> Result<T, MathError> {
+    if divisor.is_zero() {
+        Err(MathError::DivisionByZero)
+    } else {
+        Ok(divisor)
+    }
+}
+
+/// Reject a power whose real value is undefined: a negative base raised to a
+/// fractional exponent
+pub(crate) fn check_pow<T: Float>(base: T, exponent: T) -> Result<(T, T), MathError> {
+    let base = not_nan(base)?;
+    let exponent = not_nan(exponent)?;
+    if base < T::zero() && !exponent.fract().is_zero() {
+        Err(MathError::DomainError)
+    } else {
+        Ok((base, exponent))
+    }
+}
+
+/// Reject a logarithm argument that is not positive
+pub(crate) fn check_log_argument<T: Float>(argument: T) -> Result<T, MathError> {
+    if not_nan(argument)? <= T::zero() {
+        Err(MathError::DomainError)
+    } else {
+        Ok(argument)
+    }
+}
+
+/// Convert the inputs of a precise muldiv, rejecting a zero denominator
+pub(crate) fn precise_muldiv_inputs(
+    val: u64,
+    num: u64,
+    denom: u64,
+) -> Result<(PreciseNumber, PreciseNumber, PreciseNumber), MathError> {
+    if denom == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    let val = PreciseNumber::new(val as u128).ok_or(MathError::ConversionFailed)?;
+    let num = PreciseNumber::new(num as u128).ok_or(MathError::ConversionFailed)?;
+    let denom = PreciseNumber::new(denom as u128).ok_or(MathError::ConversionFailed)?;
+    Ok((val, num, denom))
+}
+
+/// Publish the full-precision result of an instruction as return data
+fn return_result(result: &MathResult) -> ProgramResult {
+    set_return_data(&borsh::to_vec(result)?);
+    Ok(())
+}
+
 /// Instruction processor
 pub fn process_instruction(
     _program_id: &Pubkey,
     _accounts: &[AccountInfo],
     input: &[u8],
 ) -> ProgramResult {
-    let instruction = MathInstruction::try_from_slice(input).unwrap();
+    let instruction =
+        MathInstruction::try_from_slice(input).map_err(|_| MathError::InvalidInstruction)?;
+    let bench = Bench::calibrate();
     match instruction {
         MathInstruction::PreciseSquareRoot { radicand: radicands, algorithm } => {
             msg!("Calculating square root using PreciseNumber");
-            let radicands: Vec<PreciseNumber256D18> = radicands.iter().map(|x| PreciseNumber256D18::new_from_f64(*x).unwrap()).collect();
-
-                match algorithm {
-                    SqrtAlgorithm::Newton => {
-                        sol_log_compute_units();
-                        let cu_before = sol_remaining_compute_units();
-                        // collect sum to avoid optimizer removing the loop
-                        let mut sum_result: i128 = 0;
-                        for radicand in radicands {
-                            let result = radicand.sqrt_newton().unwrap();
-                            sum_result += result.value.0[0] as i128;
-                        }
-                        let cu_after = sol_remaining_compute_units();
-                        sol_log_compute_units();
-                        msg!("cu_bench_consumed {}", cu_before - cu_after);
-                        msg!("{}", sum_result);
+            let radicands = radicands
+                .iter()
+                .map(D18Input::to_precise)
+                .collect::<Result<Vec<_>, _>>()?;
+            // the roots are collected in the window, in one allocation, so its
+            // heap usage is that of the `Vec<PreciseNumber256D18>` built here
+            let roots = match algorithm {
+                SqrtAlgorithm::Newton => bench.measure("precise_sqrt_newton", || {
+                    let mut roots = Vec::with_capacity(radicands.len());
+                    for radicand in &radicands {
+                        roots.push(radicand.sqrt_newton()?);
                     }
-                    SqrtAlgorithm::Cordic => {
-                        sol_log_compute_units();
-                        let cu_before = sol_remaining_compute_units();
-                        // collect sum to avoid optimizer removing the loop
-                        let mut sum_result: i128 = 0;
-                        for radicand in radicands {
-                            let result = radicand.sqrt_cordic().unwrap();
-                            sum_result += result.value.0[0] as i128;
-                        }
-                        let cu_after = sol_remaining_compute_units();
-                        sol_log_compute_units();
-                        msg!("cu_bench_consumed {}", cu_before - cu_after);
-                        msg!("{}", sum_result);
+                    Some(roots)
+                }),
+                SqrtAlgorithm::Cordic => bench.measure("precise_sqrt_cordic", || {
+                    let mut roots = Vec::with_capacity(radicands.len());
+                    for radicand in &radicands {
+                        roots.push(radicand.sqrt_cordic()?);
                     }
-                }
-
-            Ok(())
+                    Some(roots)
+                }),
+            }
+            .ok_or(MathError::Overflow)?;
+            let results = roots.iter().map(|root| root.value.0).collect();
+            return_result(&MathResult::PreciseArray(results))
         }
-        MathInstruction::PreciseMulDiv { val, num, denom } => {
+        MathInstruction::PreciseMulDiv {
+            val,
+            num,
+            denom,
+            rounding,
+        } => {
             msg!("Calculating muldiv using PreciseNumber");
-            let val = PreciseNumber::new(val as u128).unwrap();
-            let num = PreciseNumber::new(num as u128).unwrap();
-            let denom = PreciseNumber::new(denom as u128).unwrap();
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = val.mul_div_floor(num, denom).unwrap().to_imprecise().unwrap();
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result);
-            Ok(())
+            let (val, num, denom) = precise_muldiv_inputs(val, num, denom)?;
+            // floor keeps measuring spl-math's own `mul_div_floor`
+            let label = match rounding {
+                RoundingMode::Floor => "precise_muldiv",
+                RoundingMode::Ceil => "precise_muldiv_ceil",
+                RoundingMode::HalfUp => "precise_muldiv_half_up",
+                RoundingMode::HalfEven => "precise_muldiv_half_even",
+                RoundingMode::TowardZero => "precise_muldiv_toward_zero",
+            };
+            let result = bench
+                .measure(label, || match rounding {
+                    RoundingMode::Floor => val.mul_div_floor(num, denom),
+                    rounding => val.checked_mul_div(&num, &denom, rounding),
+                })
+                .ok_or(MathError::Overflow)?;
+            return_result(&MathResult::Precise(result.value.0))
         }
         MathInstruction::SquareRootU64 { radicand } => {
             msg!("Calculating u64 square root");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = sqrt(radicand).unwrap();
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result);
-            Ok(())
+            let result = bench
+                .measure("sqrt_u64", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?;
+            return_result(&MathResult::U64(result))
         }
         MathInstruction::SquareRootU128 { radicand } => {
             msg!("Calculating u128 square root");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = sqrt(radicand).unwrap();
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result);
-            Ok(())
+            let result = bench
+                .measure("sqrt_u128", || sqrt(radicand))
+                .ok_or(MathError::Overflow)?;
+            return_result(&MathResult::U128(result))
         }
         MathInstruction::U64Multiply {
             multiplicand,
             multiplier,
         } => {
             msg!("Calculating U64 Multiply");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = u64_multiply(multiplicand, multiplier);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result);
-            Ok(())
+            let (multiplicand, multiplier) = check_product(multiplicand, multiplier)?;
+            let result = bench.measure("u64_multiply", || u64_multiply(multiplicand, multiplier));
+            return_result(&MathResult::U64(result))
         }
         MathInstruction::U64Divide { dividend, divisor } => {
             msg!("Calculating U64 Divide");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = u64_divide(dividend, divisor);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result);
-            Ok(())
+            let divisor = check_integer_divisor(divisor)?;
+            let result = bench.measure("u64_divide", || u64_divide(dividend, divisor));
+            return_result(&MathResult::U64(result))
         }
         MathInstruction::F32Multiply {
             multiplicand,
             multiplier,
         } => {
             msg!("Calculating f32 Multiply");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = f32_multiply(multiplicand, multiplier);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result as u64);
-            Ok(())
+            let multiplicand = not_nan(multiplicand)?;
+            let multiplier = not_nan(multiplier)?;
+            let result = bench.measure("f32_multiply", || f32_multiply(multiplicand, multiplier));
+            return_result(&MathResult::F32(not_nan(result)?))
         }
         MathInstruction::F32Divide { dividend, divisor } => {
             msg!("Calculating f32 Divide");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = f32_divide(dividend, divisor);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result as u64);
-            Ok(())
+            let dividend = not_nan(dividend)?;
+            let divisor = check_divisor(divisor)?;
+            let result = bench.measure("f32_divide", || f32_divide(dividend, divisor));
+            return_result(&MathResult::F32(not_nan(result)?))
         }
         MathInstruction::F32Exponentiate { base, exponent } => {
             msg!("Calculating f32 Exponent");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = f32_exponentiate(base, exponent);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result as u64);
-            Ok(())
+            let (base, exponent) = check_pow(base, exponent)?;
+            let result = bench.measure("f32_exponentiate", || f32_exponentiate(base, exponent));
+            return_result(&MathResult::F32(not_nan(result)?))
         }
         MathInstruction::F32NaturalLog { argument } => {
             msg!("Calculating f32 Natural Log");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = f32_natural_log(argument);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result as u64);
-            Ok(())
+            let argument = check_log_argument(argument)?;
+            let result = bench.measure("f32_natural_log", || f32_natural_log(argument));
+            return_result(&MathResult::F32(not_nan(result)?))
         }
         MathInstruction::F32NormalCDF { argument } => {
             msg!("Calculating f32 Normal CDF");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = f32_normal_cdf(argument);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result as u64);
-            Ok(())
+            let argument = not_nan(argument)?;
+            let result = bench.measure("f32_normal_cdf", || f32_normal_cdf(argument));
+            return_result(&MathResult::F32(not_nan(result)?))
         }
         MathInstruction::F64Pow { base, exponent } => {
             msg!("Calculating f64 Pow");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result1 = base.powi(exponent as i32);
-            let result2 = base.powf(exponent);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result1 as u64);
-            msg!("{}", result2 as u64);
-            Ok(())
+            let (base, exponent) = check_pow(base, exponent)?;
+            let (powi, powf) = bench.measure("f64_pow", || {
+                (base.powi(exponent as i32), base.powf(exponent))
+            });
+            return_result(&MathResult::F64Pow {
+                powi: not_nan(powi)?,
+                powf: not_nan(powf)?,
+            })
         }
         MathInstruction::U128Multiply {
             multiplicand,
             multiplier,
         } => {
             msg!("Calculating u128 Multiply");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = u128_multiply(multiplicand, multiplier);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result);
-            Ok(())
+            let (multiplicand, multiplier) = check_product(multiplicand, multiplier)?;
+            let result = bench.measure("u128_multiply", || u128_multiply(multiplicand, multiplier));
+            return_result(&MathResult::U128(result))
         }
         MathInstruction::U128Divide { dividend, divisor } => {
             msg!("Calculating u128 Divide");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = u128_divide(dividend, divisor);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result);
-            Ok(())
+            let divisor = check_integer_divisor(divisor)?;
+            let result = bench.measure("u128_divide", || u128_divide(dividend, divisor));
+            return_result(&MathResult::U128(result))
         }
         MathInstruction::F64Multiply {
             multiplicand,
             multiplier,
         } => {
             msg!("Calculating f64 Multiply");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = f64_multiply(multiplicand, multiplier);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result as u64);
-            Ok(())
+            let multiplicand = not_nan(multiplicand)?;
+            let multiplier = not_nan(multiplier)?;
+            let result = bench.measure("f64_multiply", || f64_multiply(multiplicand, multiplier));
+            return_result(&MathResult::F64(not_nan(result)?))
         }
         MathInstruction::F64Divide { dividend, divisor } => {
             msg!("Calculating f64 Divide");
-            sol_log_compute_units();
-            let cu_before = sol_remaining_compute_units();
-            let result = f64_divide(dividend, divisor);
-            let cu_after = sol_remaining_compute_units();
-            sol_log_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", result as u64);
-            Ok(())
+            let dividend = not_nan(dividend)?;
+            let divisor = check_divisor(divisor)?;
+            let result = bench.measure("f64_divide", || f64_divide(dividend, divisor));
+            return_result(&MathResult::F64(not_nan(result)?))
         }
         MathInstruction::Noop => {
             msg!("Perform NOOP");
-            let cu_before = sol_remaining_compute_units();
-            // no-op
-            let cu_after = sol_remaining_compute_units();
-            msg!("cu_bench_consumed {}", cu_before - cu_after);
-            msg!("{}", "noop");
+            bench.measure("noop", || ());
             Ok(())
         }
+        MathInstruction::Compare {
+            implementation,
+            operation,
+        } => {
+            msg!("Comparing {:?} implementation", implementation);
+            let result = process_compare(&bench, implementation, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::Sweep { operation, inputs } => {
+            msg!("Sweeping {:?}", operation);
+            let result = process_sweep(&bench, operation, inputs)?;
+            return_result(&result)
+        }
+        MathInstruction::CheckedCeilDiv { dividend, divisor } => {
+            msg!("Calculating u128 CheckedCeilDiv");
+            if divisor == 0 {
+                return Err(MathError::DivisionByZero.into());
+            }
+            let (quotient, divisor) = bench
+                .measure("checked_ceil_div", || dividend.checked_ceil_div(divisor))
+                .ok_or(MathError::Overflow)?;
+            return_result(&MathResult::CeilDiv { quotient, divisor })
+        }
+        MathInstruction::PreciseArithmetic { operation } => {
+            msg!("Calculating PreciseNumber arithmetic");
+            let result = process_precise_arithmetic(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::PreciseD18Arithmetic { operation } => {
+            msg!("Calculating PreciseNumber256D18 arithmetic");
+            let result = process_precise_d18_arithmetic(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::Batch { operation } => {
+            msg!("Calculating batch of {} inputs", operation.count());
+            let result = process_batch(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::WideArithmetic { operation } => {
+            msg!("Calculating wide integer arithmetic");
+            let result = process_wide_arithmetic(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::IntegerSquareRoot { radicand } => {
+            msg!("Calculating integer square root");
+            let result = process_integer_sqrt(&bench, radicand)?;
+            return_result(&result)
+        }
+        MathInstruction::FixedPointMath { number, operation } => {
+            msg!("Calculating fixed-point exponential or logarithm");
+            let result = process_fixed_point_math(&bench, number, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::NormalDistribution { operation } => {
+            msg!("Calculating fixed-point normal distribution");
+            let result = process_normal_distribution(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::BlackScholes { option, inputs } => {
+            msg!("Calculating Black-Scholes valuation");
+            let result = process_black_scholes(&bench, option, inputs)?;
+            return_result(&result)
+        }
+        MathInstruction::Curve { operation } => {
+            msg!("Calculating constant-product curve");
+            let result = process_curve(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::StableSwap { operation } => {
+            msg!("Calculating StableSwap invariant");
+            let result = process_stable_swap(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::WeightedPool { operation } => {
+            msg!("Calculating weighted pool swap");
+            let result = process_weighted_pool(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::CompoundInterest { operation } => {
+            msg!("Calculating compound interest");
+            let result = process_interest(&bench, operation)?;
+            return_result(&result)
+        }
+        MathInstruction::SignedArithmetic { number, operation } => {
+            msg!("Calculating signed fixed-point arithmetic");
+            let result = process_signed_arithmetic(&bench, number, operation)?;
+            return_result(&result)
+        }
     }
 }
 
@@ -335,6 +456,72 @@ mod tests {
         assert_eq!(2, u64_divide(2, 1));
     }
 
+    #[test]
+    fn test_not_nan() {
+        assert_eq!(Ok(1.5), not_nan(1.5_f32));
+        assert_eq!(Err(MathError::NotANumber), not_nan(f32::NAN));
+        assert_eq!(Err(MathError::NotANumber), not_nan(f64::INFINITY * 0.0));
+    }
+
+    #[test]
+    fn test_checked_ceil_div() {
+        // (dividend, divisor, quotient, adjusted divisor)
+        let cases = [
+            // zero dividend
+            (0, 5, 0, 0),
+            // exact division keeps the divisor
+            (10, 5, 2, 5),
+            (u128::MAX, 1, u128::MAX, 1),
+            // a remainder rounds the quotient up, and the divisor becomes
+            // `ceil(dividend / quotient)`, which may be the divisor itself
+            (10, 3, 4, 3),
+            (11, 4, 3, 4),
+            (100, 7, 15, 7),
+            (u128::MAX, 2, 1 << 127, 2),
+            // or a smaller one
+            (7, 5, 2, 4),
+            (13, 10, 2, 7),
+            // a dividend below the divisor rounds to the nearest of 0 and 1
+            (3, 5, 1, 0),
+            (2, 5, 0, 0),
+        ];
+        for (dividend, divisor, quotient, adjusted_divisor) in cases {
+            assert_eq!(
+                dividend.checked_ceil_div(divisor),
+                Some((quotient, adjusted_divisor)),
+                "{} / {}",
+                dividend,
+                divisor
+            );
+        }
+        // the quotient is 0, so the dividend is doubled to round it
+        assert_eq!((u128::MAX - 1).checked_ceil_div(u128::MAX), None);
+    }
+
+    #[test]
+    fn test_check_integer_operands() {
+        assert_eq!(Ok((u64::MAX, 1)), check_product(u64::MAX, 1));
+        assert_eq!(Err(MathError::Overflow), check_product(u64::MAX, 2));
+        assert_eq!(Err(MathError::Overflow), check_product(u128::MAX, 2));
+        assert_eq!(Ok(1_u128), check_integer_divisor(1_u128));
+        assert_eq!(Err(MathError::DivisionByZero), check_integer_divisor(0_u64));
+    }
+
+    #[test]
+    fn test_input_checks() {
+        assert_eq!(Ok(2.0), check_divisor(2.0_f32));
+        assert_eq!(Err(MathError::DivisionByZero), check_divisor(-0.0_f64));
+        assert_eq!(Err(MathError::NotANumber), check_divisor(f32::NAN));
+        assert_eq!(Ok((-2.0, 3.0)), check_pow(-2.0_f64, 3.0));
+        assert_eq!(Err(MathError::DomainError), check_pow(-2.0_f32, 0.5));
+        assert_eq!(Ok(1.0), check_log_argument(1.0_f32));
+        assert_eq!(Err(MathError::DomainError), check_log_argument(0.0_f64));
+        assert_eq!(
+            Err(MathError::DivisionByZero),
+            precise_muldiv_inputs(1, 1, 0).map(|_| ())
+        );
+    }
+
     #[test]
     fn test_f32_multiply() {
         assert_eq!(2.0 * 2.0, f32_multiply(2.0, 2.0));
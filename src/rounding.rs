@@ -0,0 +1,227 @@
+//! `self * num / denom` with a chosen rounding of the last place
+//!
+//! `PreciseNumber::mul_div_floor` always rounds down, which is right for an
+//! amount paid out but not for an amount owed to a protocol, which should
+//! round up, or for a displayed amount, which should round to the nearest.
+//! `checked_mul_div` takes the rounding as a `RoundingMode`. The product of
+//! the inner values is exact in a U256, so only the quotient rounds.
+//!
+//! Directions are on the number line: `Floor` rounds a negative
+//! `SignedPreciseNumber` away from zero and `TowardZero` does not, while
+//! both round a `PreciseNumber` down. `HalfUp` rounds halves away from zero,
+//! like the rounding of `PreciseNumber` itself.
+
+use {
+    crate::{
+        instruction::RoundingMode, precise_number::PreciseNumber, signed::SignedPreciseNumber,
+        uint::U256,
+    },
+    std::cmp::Ordering,
+};
+
+/// `self * num / denom` with a chosen rounding
+pub trait MulDivRounding: Sized {
+    /// `self * num / denom` rounded by `rounding`; `None` if `denom` is zero
+    /// or the result does not fit
+    fn checked_mul_div(&self, num: &Self, denom: &Self, rounding: RoundingMode) -> Option<Self>;
+}
+
+/// Magnitude of `lhs * rhs / denominator` rounded by `rounding`, for a
+/// result that is negative if `negative`
+fn mul_div(
+    lhs: U256,
+    rhs: U256,
+    denominator: U256,
+    negative: bool,
+    rounding: RoundingMode,
+) -> Option<U256> {
+    let product = lhs.checked_mul(rhs)?;
+    let quotient = product.checked_div(denominator)?;
+    let remainder = product.checked_rem(denominator)?;
+    if remainder.is_zero() {
+        return Some(quotient);
+    }
+    // the remainder against half the denominator, without doubling it
+    let half = remainder.cmp(&(denominator - remainder));
+    let away_from_zero = match rounding {
+        RoundingMode::Floor => negative,
+        RoundingMode::Ceil => !negative,
+        RoundingMode::TowardZero => false,
+        RoundingMode::HalfUp => half != Ordering::Less,
+        RoundingMode::HalfEven => match half {
+            Ordering::Less => false,
+            Ordering::Equal => quotient.bit(0),
+            Ordering::Greater => true,
+        },
+    };
+    if away_from_zero {
+        quotient.checked_add(U256::one())
+    } else {
+        Some(quotient)
+    }
+}
+
+impl MulDivRounding for PreciseNumber {
+    fn checked_mul_div(&self, num: &Self, denom: &Self, rounding: RoundingMode) -> Option<Self> {
+        let value = mul_div(self.value, num.value, denom.value, false, rounding)?;
+        Some(Self { value })
+    }
+}
+
+impl MulDivRounding for SignedPreciseNumber {
+    fn checked_mul_div(&self, num: &Self, denom: &Self, rounding: RoundingMode) -> Option<Self> {
+        let negative = self.negative ^ num.negative ^ denom.negative;
+        let magnitude = mul_div(
+            self.value.value,
+            num.value.value,
+            denom.value.value,
+            negative,
+            rounding,
+        )?;
+        Some(Self::from_magnitude(
+            PreciseNumber { value: magnitude },
+            negative,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, proptest::prelude::*};
+
+    /// Modes in the order of the expected results of `BOUNDARIES`
+    const MODES: [RoundingMode; 5] = [
+        RoundingMode::Floor,
+        RoundingMode::Ceil,
+        RoundingMode::HalfUp,
+        RoundingMode::HalfEven,
+        RoundingMode::TowardZero,
+    ];
+
+    /// `(numerator, denominator)` of a quotient and its expected rounding
+    /// in each of `MODES`
+    const BOUNDARIES: [(i128, i128, [i128; 5]); 16] = [
+        (10, 2, [5, 5, 5, 5, 5]),
+        (0, 7, [0, 0, 0, 0, 0]),
+        (1, 2, [0, 1, 1, 0, 0]),
+        (5, 2, [2, 3, 3, 2, 2]),
+        (7, 2, [3, 4, 4, 4, 3]),
+        (2_499, 1_000, [2, 3, 2, 2, 2]),
+        (2_501, 1_000, [2, 3, 3, 3, 2]),
+        (1, 1_000_000, [0, 1, 0, 0, 0]),
+        (999_999, 1_000_000, [0, 1, 1, 1, 0]),
+        (-10, 2, [-5, -5, -5, -5, -5]),
+        (-1, 2, [-1, 0, -1, 0, 0]),
+        (-5, 2, [-3, -2, -3, -2, -2]),
+        (-7, 2, [-4, -3, -4, -4, -3]),
+        (-2_499, 1_000, [-3, -2, -2, -2, -2]),
+        (-2_501, 1_000, [-3, -2, -3, -3, -2]),
+        (-1, 1_000_000, [-1, 0, 0, 0, 0]),
+    ];
+
+    /// Number with the given signed inner value
+    fn signed(value: i128) -> SignedPreciseNumber {
+        let magnitude = PreciseNumber {
+            value: U256::from(value.unsigned_abs()),
+        };
+        SignedPreciseNumber::from_magnitude(magnitude, value < 0)
+    }
+
+    fn unsigned(value: u128) -> PreciseNumber {
+        PreciseNumber {
+            value: U256::from(value),
+        }
+    }
+
+    #[test]
+    fn test_boundaries() {
+        for (numerator, denominator, expected) in BOUNDARIES {
+            for (rounding, expected) in MODES.into_iter().zip(expected) {
+                let case = (numerator, denominator, rounding);
+                // the sign on any one of the three operands
+                for (val, num, denom) in [
+                    (numerator, 1, denominator),
+                    (numerator.abs(), numerator.signum(), denominator),
+                    (-numerator, 1, -denominator),
+                ] {
+                    let result = signed(val)
+                        .checked_mul_div(&signed(num), &signed(denom), rounding)
+                        .unwrap();
+                    assert_eq!(result, signed(expected), "{:?}", case);
+                }
+                if numerator >= 0 {
+                    let result = unsigned(numerator as u128)
+                        .checked_mul_div(&unsigned(1), &unsigned(denominator as u128), rounding)
+                        .unwrap();
+                    assert_eq!(result, unsigned(expected as u128), "{:?}", case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_floor_matches_mul_div_floor() {
+        for (val, num, denom) in [(42, 84, 7), (5, 1, 2), (1, 1, 3), (u128::MAX, 3, 7)] {
+            let (val, num, denom) = (unsigned(val), unsigned(num), unsigned(denom));
+            assert_eq!(
+                val.checked_mul_div(&num, &denom, RoundingMode::Floor),
+                val.clone().mul_div_floor(num, denom)
+            );
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        for rounding in MODES {
+            assert_eq!(
+                unsigned(1).checked_mul_div(&unsigned(1), &unsigned(0), rounding),
+                None
+            );
+            let max = PreciseNumber { value: U256::MAX };
+            assert_eq!(
+                max.checked_mul_div(&unsigned(2), &unsigned(3), rounding),
+                None
+            );
+            // the product of the inner values is exact, even beyond u128
+            assert_eq!(
+                unsigned(u128::MAX).checked_mul_div(&unsigned(4), &unsigned(2), rounding),
+                Some(PreciseNumber {
+                    value: U256::from(u128::MAX) * 2
+                })
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_i128(
+            val in -(1i128 << 60)..1i128 << 60,
+            num in -(1i128 << 60)..1i128 << 60,
+            denom in (1i128..1i128 << 60).prop_union(-(1i128 << 60)..0),
+        ) {
+            let product = val * num;
+            // truncated quotient and remainder, and the direction away from zero
+            let (quotient, remainder) = (product / denom, product % denom);
+            let away = if (product < 0) != (denom < 0) { -1 } else { 1 };
+            let inexact = if remainder == 0 { 0 } else { away };
+            let twice = 2 * remainder.unsigned_abs();
+            let half = twice.cmp(&denom.unsigned_abs());
+            let expected = [
+                product.div_euclid(denom) - i128::from(denom < 0 && remainder != 0),
+                quotient + if away > 0 { inexact } else { 0 },
+                quotient + if half.is_ge() { inexact } else { 0 },
+                quotient
+                    + if half.is_gt() || half.is_eq() && quotient % 2 != 0 {
+                        inexact
+                    } else {
+                        0
+                    },
+                quotient,
+            ];
+            for (rounding, expected) in MODES.into_iter().zip(expected) {
+                let result = signed(val).checked_mul_div(&signed(num), &signed(denom), rounding);
+                prop_assert_eq!(result, Some(signed(expected)), "{:?}", rounding);
+            }
+        }
+    }
+}
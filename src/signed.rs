@@ -0,0 +1,455 @@
+//! Signed fixed-point numbers, as a magnitude and a sign
+//!
+//! `Signed` gives a sign to `PreciseNumber` or `PreciseNumber256D18`. The
+//! products, quotients and powers are those of the magnitude, with the same
+//! rounding, so they round half away from zero; sums, differences and the
+//! integer roundings work on the inner values. A difference below zero is a
+//! negative number rather than `None`. Zero is never negative, so equal
+//! numbers have equal representations.
+
+use {
+    crate::{
+        d18,
+        precise_number::{self, PreciseNumber, PreciseNumber256D18},
+        uint::U256,
+    },
+    std::{cmp::Ordering, ops::Neg},
+};
+
+/// Unsigned fixed-point number that `Signed` gives a sign to
+pub trait Magnitude: Clone + Sized {
+    /// Inner value of 1
+    const ONE: u128;
+
+    /// Inner value, the number times `ONE`
+    fn inner(&self) -> U256;
+    /// Number of the inner value
+    fn from_inner(value: U256) -> Self;
+    /// `self * rhs`, rounded like the type; `None` if it does not fit
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+    /// `self / rhs`, rounded like the type; `None` if `rhs` is zero or the
+    /// quotient does not fit
+    fn checked_div(&self, rhs: &Self) -> Option<Self>;
+    /// `self ^ exponent`, rounded like the type; `None` if it does not fit
+    fn checked_pow(&self, exponent: u128) -> Option<Self>;
+    /// Integer nearest to the number; `None` if it does not fit
+    fn to_imprecise(&self) -> Option<u128>;
+    /// Square root
+    fn sqrt(&self) -> Option<Self>;
+}
+
+impl Magnitude for PreciseNumber {
+    const ONE: u128 = precise_number::ONE;
+
+    fn inner(&self) -> U256 {
+        self.value
+    }
+
+    fn from_inner(value: U256) -> Self {
+        Self { value }
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        PreciseNumber::checked_mul(self, rhs)
+    }
+
+    fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        PreciseNumber::checked_div(self, rhs)
+    }
+
+    fn checked_pow(&self, exponent: u128) -> Option<Self> {
+        PreciseNumber::checked_pow(self, exponent)
+    }
+
+    fn to_imprecise(&self) -> Option<u128> {
+        PreciseNumber::to_imprecise(self)
+    }
+
+    fn sqrt(&self) -> Option<Self> {
+        PreciseNumber::sqrt(self)
+    }
+}
+
+impl Magnitude for PreciseNumber256D18 {
+    const ONE: u128 = d18::ONE;
+
+    fn inner(&self) -> U256 {
+        self.value
+    }
+
+    fn from_inner(value: U256) -> Self {
+        d18::from_inner(value)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        PreciseNumber256D18::checked_mul(self, rhs)
+    }
+
+    fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        PreciseNumber256D18::checked_div(self, rhs)
+    }
+
+    fn checked_pow(&self, exponent: u128) -> Option<Self> {
+        PreciseNumber256D18::checked_pow(self, exponent)
+    }
+
+    fn to_imprecise(&self) -> Option<u128> {
+        PreciseNumber256D18::to_imprecise(self)
+    }
+
+    fn sqrt(&self) -> Option<Self> {
+        self.sqrt_newton()
+    }
+}
+
+/// Fixed-point number with a sign
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signed<T> {
+    /// Magnitude
+    pub value: T,
+    /// Whether the number is negative, never for zero
+    pub negative: bool,
+}
+
+/// `PreciseNumber` with a sign
+pub type SignedPreciseNumber = Signed<PreciseNumber>;
+
+/// `PreciseNumber256D18` with a sign
+pub type SignedPreciseNumber256D18 = Signed<PreciseNumber256D18>;
+
+impl<T: Magnitude> From<T> for Signed<T> {
+    fn from(value: T) -> Self {
+        Self {
+            value,
+            negative: false,
+        }
+    }
+}
+
+impl<T: Magnitude> Neg for Signed<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::from_magnitude(self.value, !self.negative)
+    }
+}
+
+impl<T: Magnitude> Signed<T> {
+    /// Number of the magnitude and sign; a negative zero is made positive
+    pub fn from_magnitude(value: T, negative: bool) -> Self {
+        let negative = negative && !value.inner().is_zero();
+        Self { value, negative }
+    }
+
+    /// Number of the signed inner value of a magnitude type
+    fn from_inner(value: U256, negative: bool) -> Self {
+        Self::from_magnitude(T::from_inner(value), negative)
+    }
+
+    /// Exact number of an integer
+    pub fn new(value: i128) -> Option<Self> {
+        let magnitude = U256::from(value.unsigned_abs()).checked_mul(U256::from(T::ONE))?;
+        Some(Self::from_inner(magnitude, value < 0))
+    }
+
+    /// Integer nearest to the number, halves away from zero; `None` if it
+    /// does not fit
+    pub fn to_imprecise(&self) -> Option<i128> {
+        let magnitude = self.value.to_imprecise()?;
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    /// Absolute value
+    pub fn abs(&self) -> T {
+        self.value.clone()
+    }
+
+    /// Whether `self` and `rhs` differ by less than `precision`
+    pub fn almost_eq(&self, rhs: &Self, precision: U256) -> bool {
+        self.checked_sub(rhs)
+            .is_some_and(|difference| difference.value.inner() < precision)
+    }
+
+    /// Order of `self` and `rhs`
+    fn compare(&self, rhs: &Self) -> Ordering {
+        match (self.negative, rhs.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.value.inner().cmp(&rhs.value.inner()),
+            (true, true) => rhs.value.inner().cmp(&self.value.inner()),
+        }
+    }
+
+    /// Whether `self < rhs`
+    pub fn less_than(&self, rhs: &Self) -> bool {
+        self.compare(rhs) == Ordering::Less
+    }
+
+    /// Whether `self > rhs`
+    pub fn greater_than(&self, rhs: &Self) -> bool {
+        self.compare(rhs) == Ordering::Greater
+    }
+
+    /// Whether `self <= rhs`
+    pub fn less_than_or_equal(&self, rhs: &Self) -> bool {
+        self.compare(rhs) != Ordering::Greater
+    }
+
+    /// Whether `self >= rhs`
+    pub fn greater_than_or_equal(&self, rhs: &Self) -> bool {
+        self.compare(rhs) != Ordering::Less
+    }
+
+    /// Largest integer not above the number
+    pub fn floor(&self) -> Option<Self> {
+        let one = U256::from(T::ONE);
+        let magnitude = self.value.inner();
+        let truncated = magnitude / one * one;
+        let magnitude = if self.negative && truncated != magnitude {
+            truncated.checked_add(one)?
+        } else {
+            truncated
+        };
+        Some(Self::from_inner(magnitude, self.negative))
+    }
+
+    /// Smallest integer not below the number
+    pub fn ceiling(&self) -> Option<Self> {
+        (-self.clone()).floor().map(Neg::neg)
+    }
+
+    /// `self + rhs`; `None` if the magnitude does not fit
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        let (magnitude, rhs_magnitude) = (self.value.inner(), rhs.value.inner());
+        if self.negative == rhs.negative {
+            let sum = magnitude.checked_add(rhs_magnitude)?;
+            return Some(Self::from_inner(sum, self.negative));
+        }
+        // opposite signs: the larger magnitude keeps its sign
+        if magnitude >= rhs_magnitude {
+            Some(Self::from_inner(magnitude - rhs_magnitude, self.negative))
+        } else {
+            Some(Self::from_inner(rhs_magnitude - magnitude, rhs.negative))
+        }
+    }
+
+    /// `self - rhs`, negative rather than `None` below zero; `None` if the
+    /// magnitude does not fit
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        self.checked_add(&-rhs.clone())
+    }
+
+    /// `self * rhs`; `None` if the magnitude does not fit
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        let magnitude = self.value.checked_mul(&rhs.value)?;
+        Some(Self::from_magnitude(
+            magnitude,
+            self.negative != rhs.negative,
+        ))
+    }
+
+    /// `self / rhs`; `None` if `rhs` is zero or the magnitude does not fit
+    pub fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        let magnitude = self.value.checked_div(&rhs.value)?;
+        Some(Self::from_magnitude(
+            magnitude,
+            self.negative != rhs.negative,
+        ))
+    }
+
+    /// `self ^ exponent` for an integer exponent, negative for a negative
+    /// base and an odd exponent; `None` if the magnitude does not fit
+    pub fn checked_pow(&self, exponent: u128) -> Option<Self> {
+        let magnitude = self.value.checked_pow(exponent)?;
+        Some(Self::from_magnitude(
+            magnitude,
+            self.negative && exponent % 2 == 1,
+        ))
+    }
+
+    /// Square root; `None` for a negative number
+    pub fn sqrt(&self) -> Option<Self> {
+        if self.negative {
+            return None;
+        }
+        self.value.sqrt().map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::precise_number::ONE, proptest::prelude::*};
+
+    /// Number with the given signed inner value
+    fn inner(value: i128) -> SignedPreciseNumber {
+        let magnitude = PreciseNumber {
+            value: U256::from(value.unsigned_abs()),
+        };
+        SignedPreciseNumber::from_magnitude(magnitude, value < 0)
+    }
+
+    /// Signed inner value of the number
+    fn to_inner(number: &SignedPreciseNumber) -> i128 {
+        let magnitude = number.value.value.as_u128() as i128;
+        if number.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    fn number(value: &str) -> SignedPreciseNumber {
+        let (negative, magnitude) = match value.strip_prefix('-') {
+            Some(magnitude) => (true, magnitude),
+            None => (false, value),
+        };
+        let magnitude = crate::d18::from_decimal_str(magnitude).unwrap().value;
+        SignedPreciseNumber::from_magnitude(
+            PreciseNumber {
+                value: magnitude / U256::exp10(6),
+            },
+            negative,
+        )
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let one = |lhs, rhs| (number(lhs), number(rhs));
+        let (lhs, rhs) = one("1.5", "2.25");
+        assert_eq!(lhs.checked_sub(&rhs), Some(number("-0.75")));
+        assert_eq!(lhs.checked_add(&-rhs.clone()), Some(number("-0.75")));
+        assert_eq!(rhs.checked_sub(&lhs), Some(number("0.75")));
+        let (lhs, rhs) = one("-1.5", "2");
+        assert_eq!(lhs.checked_mul(&rhs), Some(number("-3")));
+        assert_eq!(lhs.checked_add(&rhs), Some(number("0.5")));
+        let (lhs, rhs) = one("-3", "-2");
+        assert_eq!(lhs.checked_div(&rhs), Some(number("1.5")));
+        assert_eq!(lhs.checked_div(&number("0")), None);
+        assert_eq!(number("-2").checked_pow(3), Some(number("-8")));
+        assert_eq!(number("-2").checked_pow(2), Some(number("4")));
+        assert_eq!(number("-2").checked_pow(0), Some(number("1")));
+        // a product rounds half away from zero
+        assert_eq!(inner(-5).checked_mul(&number("0.1")), Some(inner(-1)));
+    }
+
+    #[test]
+    fn test_zero_is_positive() {
+        let zero = number("1").checked_sub(&number("1")).unwrap();
+        assert!(!zero.negative);
+        assert_eq!(zero, SignedPreciseNumber::new(0).unwrap());
+        assert_eq!(-zero.clone(), zero);
+        assert_eq!(number("-0"), zero);
+        assert_eq!(
+            number("-0.4").checked_mul(&number("0.000000000001")),
+            Some(zero)
+        );
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(number("-1.5").floor(), Some(number("-2")));
+        assert_eq!(number("-1.5").ceiling(), Some(number("-1")));
+        assert_eq!(number("1.5").floor(), Some(number("1")));
+        assert_eq!(number("1.5").ceiling(), Some(number("2")));
+        assert_eq!(number("-2").floor(), Some(number("-2")));
+        assert_eq!(number("-0.5").ceiling(), Some(number("0")));
+        assert_eq!(number("-2.5").to_imprecise(), Some(-3));
+        assert_eq!(number("-2.4").to_imprecise(), Some(-2));
+        assert_eq!(
+            SignedPreciseNumber::new(-5).unwrap().to_imprecise(),
+            Some(-5)
+        );
+        assert_eq!(
+            SignedPreciseNumber::new(i128::MIN).unwrap().to_imprecise(),
+            Some(i128::MIN)
+        );
+        let beyond = SignedPreciseNumber::from(PreciseNumber::new(i128::MAX as u128 + 1).unwrap());
+        assert_eq!(beyond.to_imprecise(), None);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let ordered = ["-3", "-1.5", "-0.000000000001", "0", "0.5", "2"].map(number);
+        for (i, lhs) in ordered.iter().enumerate() {
+            for (j, rhs) in ordered.iter().enumerate() {
+                assert_eq!(lhs.less_than(rhs), i < j);
+                assert_eq!(lhs.greater_than(rhs), i > j);
+                assert_eq!(lhs.less_than_or_equal(rhs), i <= j);
+                assert_eq!(lhs.greater_than_or_equal(rhs), i >= j);
+            }
+        }
+        assert!(inner(-1).almost_eq(&inner(1), U256::from(3)));
+        assert!(!inner(-1).almost_eq(&inner(1), U256::from(2)));
+        assert_eq!(number("-4").sqrt(), None);
+        assert_eq!(number("-4").abs(), number("4").value);
+    }
+
+    #[test]
+    fn test_d18() {
+        let number = |value: &str| match value.strip_prefix('-') {
+            Some(magnitude) => -Signed::from(d18::from_decimal_str(magnitude).unwrap()),
+            None => Signed::from(d18::from_decimal_str(value).unwrap()),
+        };
+        let tiny = number("0.000000000000000001");
+        assert_eq!(
+            number("1.5").checked_sub(&number("2.25")),
+            Some(number("-0.75"))
+        );
+        assert_eq!(
+            tiny.checked_sub(&tiny.checked_add(&tiny).unwrap()),
+            Some(-tiny.clone())
+        );
+        // 18 decimals, rounded half away from zero
+        assert_eq!(
+            (-tiny.clone()).checked_mul(&number("0.5")),
+            Some(-tiny.clone())
+        );
+        assert_eq!(
+            number("-2").checked_div(&number("3")),
+            Some(number("-0.666666666666666667"))
+        );
+        assert_eq!(number("-1.5").checked_pow(3), Some(number("-3.375")));
+        assert_eq!((-tiny.clone()).floor(), Some(number("-1")));
+        assert_eq!((-tiny.clone()).ceiling(), Some(number("0")));
+        assert_eq!(SignedPreciseNumber256D18::new(-7), Some(number("-7")));
+        assert_eq!(number("-2.5").to_imprecise(), Some(-3));
+        assert!(number("-0.5").less_than(&tiny));
+        assert_eq!(number("-4").sqrt(), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_i128(
+            lhs in -(1i128 << 80)..1i128 << 80,
+            rhs in -(1i128 << 80)..1i128 << 80,
+        ) {
+            let (a, b) = (inner(lhs), inner(rhs));
+            prop_assert_eq!(to_inner(&a.checked_add(&b).unwrap()), lhs + rhs);
+            prop_assert_eq!(to_inner(&a.checked_sub(&b).unwrap()), lhs - rhs);
+            prop_assert_eq!(a.less_than(&b), lhs < rhs);
+            prop_assert_eq!(a.greater_than_or_equal(&b), lhs >= rhs);
+
+            // magnitudes rounded half up, then signed
+            let sign = if (lhs < 0) != (rhs < 0) { -1 } else { 1 };
+            let (l, r) = (lhs.unsigned_abs(), rhs.unsigned_abs());
+            let product = U256::from(l) * U256::from(r) + U256::from(ONE / 2);
+            let product = (product / U256::from(ONE)).as_u128() as i128;
+            prop_assert_eq!(to_inner(&a.checked_mul(&b).unwrap()), sign * product);
+            if rhs != 0 {
+                let quotient = (U256::from(l) * U256::from(ONE) + U256::from(r / 2)) / U256::from(r);
+                prop_assert_eq!(
+                    to_inner(&a.checked_div(&b).unwrap()),
+                    sign * quotient.as_u128() as i128
+                );
+            }
+
+            let one = ONE as i128;
+            prop_assert_eq!(to_inner(&a.floor().unwrap()), lhs.div_euclid(one) * one);
+            prop_assert_eq!(to_inner(&a.ceiling().unwrap()), -(-lhs).div_euclid(one) * one);
+        }
+    }
+}
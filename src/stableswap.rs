@@ -0,0 +1,370 @@
+//! StableSwap invariant of Curve, solved with Newton's method
+//!
+//! For `n` coins with balances `x_i`, amplification `A` and `Ann = A * n^n`,
+//! the invariant `D` satisfies
+//!
+//! `Ann * sum(x_i) + D = Ann * D + D^(n+1) / (n^n * prod(x_i))`
+//!
+//! `compute_d` solves it for `D` from `sum(x_i)`, which bounds `D` from above,
+//! and `get_y` for the balance of one coin from `D`. Both iterate like Curve's
+//! contracts, stop once an iteration moves the solution by at most 1 and
+//! report how many iterations that took, so the cap can be tuned against the
+//! compute units. Intermediates are U256, so large balances with a large
+//! amplification overflow, from about `2^100` with `A = 10^6`.
+
+use crate::{
+    bench::Bench,
+    error::MathError,
+    instruction::{MathResult, StableSwapOperation},
+    uint::U256,
+};
+
+/// Most coins of a pool
+pub const MAX_COINS: usize = 8;
+
+/// Iteration cap of Curve's contracts
+pub const MAX_ITERATIONS: u32 = 255;
+
+/// Solution of a Newton iteration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Solution {
+    /// The solution
+    pub value: u128,
+    /// Iterations taken to converge
+    pub iterations: u32,
+}
+
+/// `Ann = amp * n^n`, after checking the pool
+fn amplification(amp: u64, balances: &[u128]) -> Result<U256, MathError> {
+    let n = balances.len();
+    if !(2..=MAX_COINS).contains(&n) || amp == 0 {
+        return Err(MathError::InvalidArgument);
+    }
+    Ok(U256::from(amp) * U256::from(n).pow(U256::from(n)))
+}
+
+/// Iterate `step` from `start` until it moves by at most 1
+fn newton(
+    start: U256,
+    max_iterations: u32,
+    step: impl Fn(U256) -> Option<U256>,
+) -> Result<Solution, MathError> {
+    let mut value = start;
+    for iteration in 1..=max_iterations {
+        let previous = value;
+        value = step(previous).ok_or(MathError::Overflow)?;
+        let moved = if value > previous {
+            value - previous
+        } else {
+            previous - value
+        };
+        if moved <= U256::one() {
+            return Ok(Solution {
+                value: u128::try_from(value).map_err(|_| MathError::Overflow)?,
+                iterations: iteration,
+            });
+        }
+    }
+    Err(MathError::NotConverged)
+}
+
+/// Invariant `D` of a pool of 2 to `MAX_COINS` coins
+///
+/// An empty pool has `D = 0` after no iteration; any other balance must be
+/// positive.
+pub fn compute_d(amp: u64, balances: &[u128], max_iterations: u32) -> Result<Solution, MathError> {
+    let ann = amplification(amp, balances)?;
+    let sum = balances
+        .iter()
+        .fold(U256::zero(), |sum, balance| sum + U256::from(*balance));
+    if sum.is_zero() {
+        return Ok(Solution {
+            value: 0,
+            iterations: 0,
+        });
+    }
+    if balances.contains(&0) {
+        return Err(MathError::InvalidArgument);
+    }
+    let n = U256::from(balances.len());
+    newton(sum, max_iterations, |d| {
+        // D^(n+1) / (n^n * prod(x_i)), one coin at a time
+        let mut d_p = d;
+        for balance in balances {
+            d_p = d_p.checked_mul(d)? / (U256::from(*balance) * n);
+        }
+        // D = (Ann * S + n * D_P) * D / ((Ann - 1) * D + (n + 1) * D_P)
+        let numerator = ann
+            .checked_mul(sum)?
+            .checked_add(d_p.checked_mul(n)?)?
+            .checked_mul(d)?;
+        let denominator = (ann - 1)
+            .checked_mul(d)?
+            .checked_add(d_p.checked_mul(n + 1)?)?;
+        numerator.checked_div(denominator)
+    })
+}
+
+/// Balance of coin `j` that keeps the invariant `d` once coin `i` is set to
+/// `x`
+///
+/// `i` and `j` must be distinct coins of the pool, and `x` and the balances
+/// of the coins other than `j` positive.
+pub fn get_y(
+    amp: u64,
+    balances: &[u128],
+    i: usize,
+    j: usize,
+    x: u128,
+    d: u128,
+    max_iterations: u32,
+) -> Result<Solution, MathError> {
+    let ann = amplification(amp, balances)?;
+    let n = balances.len();
+    if i == j || i >= n || j >= n {
+        return Err(MathError::InvalidArgument);
+    }
+    let (n, d) = (U256::from(n), U256::from(d));
+
+    // c = D^(n+1) / (n^n * prod(x_k) * Ann), b = sum(x_k) + D / Ann, k != j
+    let mut c = d;
+    let mut sum = U256::zero();
+    for (k, balance) in balances.iter().enumerate() {
+        if k == j {
+            continue;
+        }
+        let balance = U256::from(if k == i { x } else { *balance });
+        if balance.is_zero() {
+            return Err(MathError::InvalidArgument);
+        }
+        sum += balance;
+        c = c.checked_mul(d).ok_or(MathError::Overflow)? / (balance * n);
+    }
+    c = c.checked_mul(d).ok_or(MathError::Overflow)? / (ann * n);
+    let b = sum + d / ann;
+
+    // y = (y^2 + c) / (2 y + b - D)
+    newton(d, max_iterations, |y| {
+        let numerator = y.checked_mul(y)?.checked_add(c)?;
+        let denominator = (y << 1).checked_add(b)?.checked_sub(d)?;
+        numerator.checked_div(denominator)
+    })
+}
+
+/// Run the solve and return its solution and iterations
+pub(crate) fn process_stable_swap(
+    bench: &Bench,
+    operation: StableSwapOperation,
+) -> Result<MathResult, MathError> {
+    let solution = match operation {
+        StableSwapOperation::ComputeD {
+            amp,
+            balances,
+            max_iterations,
+        } => {
+            let label = format!("stableswap_d_{}", balances.len());
+            bench.measure(&label, || compute_d(amp, &balances, max_iterations))?
+        }
+        StableSwapOperation::GetY {
+            amp,
+            balances,
+            i,
+            j,
+            x,
+            max_iterations,
+        } => {
+            let d = bench.measure(&format!("stableswap_d_{}", balances.len()), || {
+                compute_d(amp, &balances, max_iterations)
+            })?;
+            bench.measure(&format!("stableswap_y_{}", balances.len()), || {
+                get_y(
+                    amp,
+                    &balances,
+                    i as usize,
+                    j as usize,
+                    x,
+                    d.value,
+                    max_iterations,
+                )
+            })?
+        }
+    };
+    Ok(MathResult::Iterated {
+        value: solution.value,
+        iterations: solution.iterations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        num_bigint::BigInt,
+        proptest::{collection::vec, prelude::*},
+    };
+
+    /// `n^n * prod(x_i) * (Ann * (sum(x_i) - D) + D) - D^(n+1)`, the
+    /// invariant times its positive denominator, which decreases with `D`
+    fn residual(amp: u64, balances: &[u128], d: &BigInt) -> BigInt {
+        let n = balances.len() as u32;
+        let n_n = BigInt::from(n).pow(n);
+        let ann = BigInt::from(amp) * &n_n;
+        let sum: BigInt = balances.iter().map(|x| BigInt::from(*x)).sum();
+        let product: BigInt = balances.iter().map(|x| BigInt::from(*x)).product();
+        n_n * product * (ann * (sum - d) + d) - d.pow(n + 1)
+    }
+
+    /// Whether the exact root of the residual is within `tolerance` of `d`
+    fn is_near_d(amp: u64, balances: &[u128], d: u128, tolerance: u128) -> bool {
+        let below = BigInt::from(d) - BigInt::from(tolerance);
+        let above = BigInt::from(d) + BigInt::from(tolerance);
+        residual(amp, balances, &below) >= BigInt::from(0)
+            && residual(amp, balances, &above) <= BigInt::from(0)
+    }
+
+    #[test]
+    fn test_compute_d() {
+        // a balanced pool is its own invariant, after the step that confirms
+        // it
+        assert_eq!(
+            compute_d(100, &[1_000_000; 3], MAX_ITERATIONS),
+            Ok(Solution {
+                value: 3_000_000,
+                iterations: 1
+            })
+        );
+        let balances = [1_000_000_000_000_000_000_000, 10_000_000_000_000_000_000];
+        let d = compute_d(100, &balances, MAX_ITERATIONS).unwrap();
+        assert!(is_near_d(100, &balances, d.value, 2), "{:?}", d);
+        assert!(d.iterations > 1 && d.iterations < 32, "{:?}", d);
+        // the sum bounds the invariant from above
+        assert!(d.value < balances.iter().sum::<u128>());
+
+        assert_eq!(
+            compute_d(100, &[0, 0], MAX_ITERATIONS),
+            Ok(Solution {
+                value: 0,
+                iterations: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_iteration_cap() {
+        let balances = [1_000_000_000_000_000_000_000, 10_000_000_000_000_000_000];
+        let d = compute_d(100, &balances, MAX_ITERATIONS).unwrap();
+        assert_eq!(compute_d(100, &balances, d.iterations), Ok(d));
+        assert_eq!(
+            compute_d(100, &balances, d.iterations - 1),
+            Err(MathError::NotConverged)
+        );
+        assert_eq!(compute_d(100, &balances, 0), Err(MathError::NotConverged));
+    }
+
+    #[test]
+    fn test_get_y() {
+        let balances = [1_000_000_000_000, 2_000_000_000_000, 3_000_000_000_000];
+        let d = compute_d(200, &balances, MAX_ITERATIONS).unwrap().value;
+        // setting a coin to its own balance keeps the others
+        let y = get_y(200, &balances, 0, 2, balances[0], d, MAX_ITERATIONS).unwrap();
+        assert!(y.value.abs_diff(balances[2]) <= 2, "{:?}", y);
+        // adding to coin 0, the scarcer, takes a little more of coin 1
+        let y = get_y(
+            200,
+            &balances,
+            0,
+            1,
+            balances[0] + 1_000_000,
+            d,
+            MAX_ITERATIONS,
+        )
+        .unwrap();
+        let taken = balances[1] - y.value;
+        assert!(taken > 1_000_000 && taken < 1_010_000, "{}", taken);
+    }
+
+    #[test]
+    fn test_invalid_pools() {
+        let invalid = Err(MathError::InvalidArgument);
+        assert_eq!(compute_d(100, &[1_000], MAX_ITERATIONS), invalid);
+        assert_eq!(
+            compute_d(100, &[1_000; MAX_COINS + 1], MAX_ITERATIONS),
+            invalid
+        );
+        assert_eq!(compute_d(0, &[1_000; 2], MAX_ITERATIONS), invalid);
+        assert_eq!(compute_d(100, &[1_000, 0], MAX_ITERATIONS), invalid);
+        assert_eq!(
+            get_y(100, &[1_000; 2], 1, 1, 1_000, 2_000, MAX_ITERATIONS),
+            invalid
+        );
+        assert_eq!(
+            get_y(100, &[1_000; 2], 0, 2, 1_000, 2_000, MAX_ITERATIONS),
+            invalid
+        );
+        assert_eq!(
+            get_y(100, &[1_000; 2], 0, 1, 0, 2_000, MAX_ITERATIONS),
+            invalid
+        );
+        assert_eq!(
+            compute_d(u64::MAX, &[u128::MAX; MAX_COINS], MAX_ITERATIONS),
+            Err(MathError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_process_stable_swap() {
+        let run = |operation| process_stable_swap(&Bench::default(), operation);
+        assert_eq!(
+            run(StableSwapOperation::ComputeD {
+                amp: 100,
+                balances: vec![1_000_000; 3],
+                max_iterations: MAX_ITERATIONS
+            }),
+            Ok(MathResult::Iterated {
+                value: 3_000_000,
+                iterations: 1
+            })
+        );
+        assert_eq!(
+            run(StableSwapOperation::GetY {
+                amp: 100,
+                balances: vec![1_000_000; 2],
+                i: 0,
+                j: 0,
+                x: 1_000,
+                max_iterations: MAX_ITERATIONS
+            }),
+            Err(MathError::InvalidArgument)
+        );
+    }
+
+    fn pool() -> impl Strategy<Value = (u64, Vec<u128>)> {
+        (
+            1u64..=10_000,
+            vec(1u128..=1_000_000_000_000_000_000_000_000, 2..=5),
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn test_d_is_root((amp, balances) in pool()) {
+            let d = compute_d(amp, &balances, MAX_ITERATIONS).unwrap();
+            prop_assert!(is_near_d(amp, &balances, d.value, 2), "{:?}", d);
+            prop_assert!(d.value <= balances.iter().sum::<u128>());
+        }
+
+        #[test]
+        fn test_y_keeps_d((amp, balances) in pool(), factor in 1u128..=100) {
+            let d = compute_d(amp, &balances, MAX_ITERATIONS).unwrap().value;
+            // add up to as much again to coin 0, and solve for the last coin
+            let j = balances.len() - 1;
+            let x = balances[0] + balances[0] * factor / 100;
+            let y = get_y(amp, &balances, 0, j, x, d, MAX_ITERATIONS).unwrap();
+            let mut swapped = balances.clone();
+            swapped[0] = x;
+            swapped[j] = y.value;
+            let d_swapped = compute_d(amp, &swapped, MAX_ITERATIONS).unwrap().value;
+            prop_assert!(d_swapped.abs_diff(d) <= d / 1_000_000_000 + 4, "{} {}", d, d_swapped);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Sweeps of an operation over a distribution of inputs, to chart compute
+//! units against input magnitude
+
+use {
+    crate::{
+        approximations::sqrt,
+        bench::Bench,
+        d18,
+        error::MathError,
+        instruction::{MathResult, SqrtAlgorithm, SweepInputs, SweepOperation, SweepPoint},
+    },
+    std::iter::successors,
+};
+
+/// Most inputs in one sweep, so the results fit in the return data
+pub const MAX_SWEEP_INPUTS: usize = 32;
+
+impl SweepOperation {
+    /// Largest input accepted by the operation
+    fn max_input(&self) -> u128 {
+        match self {
+            Self::SquareRootU64 => u64::MAX as u128,
+            Self::SquareRootU128 => u128::MAX,
+            Self::PreciseSquareRoot(_) => u64::MAX as u128,
+        }
+    }
+}
+
+/// splitmix64, small and good enough to spread benchmark inputs
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Expand a distribution into the inputs of a sweep of an operation
+/// accepting inputs up to `max_input`
+fn generate_inputs(inputs: SweepInputs, max_input: u128) -> Result<Vec<u128>, MathError> {
+    let inputs: Vec<u128> = match inputs {
+        SweepInputs::Explicit(inputs) => inputs,
+        SweepInputs::Linear { start, step, count } => (0..count as u128)
+            .map(|i| {
+                step.checked_mul(i)
+                    .and_then(|offset| start.checked_add(offset))
+                    .ok_or(MathError::Overflow)
+            })
+            .collect::<Result<_, _>>()?,
+        SweepInputs::Geometric {
+            start,
+            ratio,
+            count,
+        } => {
+            let inputs: Vec<u128> = successors(Some(start), |input| input.checked_mul(ratio))
+                .take(count as usize)
+                .collect();
+            if inputs.len() < count as usize {
+                return Err(MathError::Overflow);
+            }
+            inputs
+        }
+        SweepInputs::Random { seed, max, count } => {
+            let mut state = seed;
+            (0..count)
+                .map(|_| {
+                    let random =
+                        ((next_random(&mut state) as u128) << 64) | next_random(&mut state) as u128;
+                    match max.checked_add(1) {
+                        Some(modulus) => random % modulus,
+                        None => random,
+                    }
+                })
+                .collect()
+        }
+        SweepInputs::EdgeCases => vec![0, 1, 2, max_input / 2, max_input - 1, max_input],
+    };
+    if inputs.len() > MAX_SWEEP_INPUTS {
+        return Err(MathError::InvalidArgument);
+    }
+    if inputs.iter().any(|input| *input > max_input) {
+        return Err(MathError::ConversionFailed);
+    }
+    Ok(inputs)
+}
+
+/// Run `operation` on every input of the distribution, measuring each input
+/// in its own window
+pub(crate) fn process_sweep(
+    bench: &Bench,
+    operation: SweepOperation,
+    inputs: SweepInputs,
+) -> Result<MathResult, MathError> {
+    let inputs = generate_inputs(inputs, operation.max_input())?;
+    let mut points = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let compute_units = match &operation {
+            SweepOperation::SquareRootU64 => {
+                let (result, compute_units) =
+                    bench.measure_units("sweep_sqrt_u64", || sqrt(input as u64));
+                result.ok_or(MathError::Overflow)?;
+                compute_units
+            }
+            SweepOperation::SquareRootU128 => {
+                let (result, compute_units) =
+                    bench.measure_units("sweep_sqrt_u128", || sqrt(input));
+                result.ok_or(MathError::Overflow)?;
+                compute_units
+            }
+            SweepOperation::PreciseSquareRoot(algorithm) => {
+                let radicand = d18::from_u128(input).ok_or(MathError::ConversionFailed)?;
+                let (result, compute_units) =
+                    match algorithm {
+                        SqrtAlgorithm::Newton => bench
+                            .measure_units("sweep_precise_sqrt_newton", || radicand.sqrt_newton()),
+                        SqrtAlgorithm::Cordic => bench
+                            .measure_units("sweep_precise_sqrt_cordic", || radicand.sqrt_cordic()),
+                    };
+                result.ok_or(MathError::Overflow)?;
+                compute_units
+            }
+        };
+        points.push(SweepPoint {
+            input,
+            compute_units,
+        });
+    }
+    Ok(MathResult::Sweep(points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_inputs() {
+        let inputs = SweepInputs::Linear {
+            start: 1000,
+            step: 100,
+            count: 4,
+        };
+        assert_eq!(
+            generate_inputs(inputs, u128::MAX),
+            Ok(vec![1000, 1100, 1200, 1300])
+        );
+
+        let inputs = SweepInputs::Linear {
+            start: u128::MAX,
+            step: 1,
+            count: 2,
+        };
+        assert_eq!(generate_inputs(inputs, u128::MAX), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn test_geometric_inputs() {
+        let inputs = SweepInputs::Geometric {
+            start: 1,
+            ratio: 2,
+            count: 5,
+        };
+        assert_eq!(generate_inputs(inputs, u128::MAX), Ok(vec![1, 2, 4, 8, 16]));
+
+        let inputs = SweepInputs::Geometric {
+            start: 1,
+            ratio: 1 << 64,
+            count: 3,
+        };
+        assert_eq!(generate_inputs(inputs, u128::MAX), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn test_random_inputs() {
+        let inputs = SweepInputs::Random {
+            seed: 42,
+            max: 1000,
+            count: 20,
+        };
+        let first = generate_inputs(inputs.clone(), u128::MAX).unwrap();
+        assert_eq!(first.len(), 20);
+        assert!(first.iter().all(|input| *input <= 1000));
+        // reproducible from the seed
+        assert_eq!(generate_inputs(inputs, u128::MAX), Ok(first));
+
+        let inputs = SweepInputs::Random {
+            seed: 0,
+            max: u128::MAX,
+            count: 2,
+        };
+        let inputs = generate_inputs(inputs, u128::MAX).unwrap();
+        assert_ne!(inputs[0], inputs[1]);
+    }
+
+    #[test]
+    fn test_edge_case_inputs() {
+        assert_eq!(
+            generate_inputs(SweepInputs::EdgeCases, u64::MAX as u128),
+            Ok(vec![
+                0,
+                1,
+                2,
+                u64::MAX as u128 / 2,
+                u64::MAX as u128 - 1,
+                u64::MAX as u128
+            ])
+        );
+    }
+
+    #[test]
+    fn test_input_limits() {
+        let inputs = SweepInputs::Explicit(vec![0; MAX_SWEEP_INPUTS + 1]);
+        assert_eq!(
+            generate_inputs(inputs, u128::MAX),
+            Err(MathError::InvalidArgument)
+        );
+
+        let inputs = SweepInputs::Explicit(vec![u64::MAX as u128 + 1]);
+        assert_eq!(
+            generate_inputs(inputs, u64::MAX as u128),
+            Err(MathError::ConversionFailed)
+        );
+    }
+
+    #[test]
+    fn test_sweep_sqrt_u64() {
+        let result = process_sweep(
+            &Bench::default(),
+            SweepOperation::SquareRootU64,
+            SweepInputs::EdgeCases,
+        )
+        .unwrap();
+        let MathResult::Sweep(points) = result else {
+            panic!("expected sweep points");
+        };
+        let inputs: Vec<u128> = points.iter().map(|point| point.input).collect();
+        assert_eq!(
+            inputs,
+            generate_inputs(SweepInputs::EdgeCases, u64::MAX as u128).unwrap()
+        );
+    }
+}
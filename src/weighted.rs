@@ -0,0 +1,333 @@
+//! Weighted-pool math, as in Balancer: swaps between two tokens of a pool
+//! that keeps `balance_in^weight_in * balance_out^weight_out` constant
+//!
+//! * out given in: `balance_out * (1 - (balance_in / (balance_in + in))^(weight_in / weight_out))`
+//! * in given out: `balance_in * ((balance_out / (balance_out - out))^(weight_out / weight_in) - 1)`
+//!
+//! Amounts are u64 token amounts, and the ratios and the power are
+//! `PreciseNumber`s. Every rounding favors the pool: the ratio of balances
+//! and the ratio of weights are rounded in the direction that raises the
+//! power, the power is taken with `checked_pow_up`, the amount out is
+//! rounded down and the amount in up.
+
+use crate::{
+    bench::Bench,
+    error::MathError,
+    fixed::FixedPointMath,
+    instruction::{MathResult, WeightedOperation},
+    precise_number::{self, PreciseNumber},
+    uint::U256,
+};
+
+/// `numerator / denominator` as a `PreciseNumber`, rounded up if `ceil`
+fn ratio(numerator: u64, denominator: u128, ceil: bool) -> PreciseNumber {
+    let numerator = numerator as u128 * precise_number::ONE;
+    let value = if ceil {
+        numerator.div_ceil(denominator)
+    } else {
+        numerator / denominator
+    };
+    PreciseNumber {
+        value: U256::from(value),
+    }
+}
+
+/// Token amount `balance * factor`, rounded up if `ceil`; `None` beyond u64
+fn scale(balance: u64, factor: U256, ceil: bool) -> Option<u64> {
+    let one = U256::from(precise_number::ONE);
+    let product = U256::from(balance).checked_mul(factor)?;
+    let amount = if ceil {
+        (product + one - U256::one()) / one
+    } else {
+        product / one
+    };
+    (amount <= U256::from(u64::MAX)).then(|| amount.as_u64())
+}
+
+/// Amount out for `amount_in`, rounded down
+///
+/// Returns `None` for an empty pool or a zero weight.
+pub fn out_given_in(
+    balance_in: u64,
+    weight_in: u64,
+    balance_out: u64,
+    weight_out: u64,
+    amount_in: u64,
+) -> Option<u64> {
+    if balance_in == 0 || balance_out == 0 || weight_in == 0 || weight_out == 0 {
+        return None;
+    }
+    // the base is below 1, so a larger base or a smaller exponent raises
+    // the power and lowers the amount out
+    let base = ratio(balance_in, balance_in as u128 + amount_in as u128, true);
+    let exponent = ratio(weight_in, weight_out as u128, false);
+    let power = base.checked_pow_up(&exponent)?;
+    let complement = U256::from(precise_number::ONE).saturating_sub(power.value);
+    scale(balance_out, complement, false)
+}
+
+/// Amount in for `amount_out`, rounded up
+///
+/// Returns `None` for a zero balance in or weight, an amount out that would
+/// empty the pool, or an amount in beyond u64.
+pub fn in_given_out(
+    balance_in: u64,
+    weight_in: u64,
+    balance_out: u64,
+    weight_out: u64,
+    amount_out: u64,
+) -> Option<u64> {
+    if balance_in == 0 || weight_in == 0 || weight_out == 0 || amount_out >= balance_out {
+        return None;
+    }
+    // the base is 1 or more, so a larger base or exponent raises the power
+    // and the amount in
+    let base = ratio(balance_out, (balance_out - amount_out) as u128, true);
+    let exponent = ratio(weight_out, weight_in as u128, true);
+    let power = base.checked_pow_up(&exponent)?;
+    let excess = power.value.saturating_sub(U256::from(precise_number::ONE));
+    scale(balance_in, excess, true)
+}
+
+/// Run the swap, after rejecting the inputs it has no answer for
+pub(crate) fn process_weighted_pool(
+    bench: &Bench,
+    operation: WeightedOperation,
+) -> Result<MathResult, MathError> {
+    match operation {
+        WeightedOperation::OutGivenIn {
+            balance_in,
+            weight_in,
+            balance_out,
+            weight_out,
+            amount_in,
+        } => {
+            if balance_in == 0 || balance_out == 0 || weight_in == 0 || weight_out == 0 {
+                return Err(MathError::InvalidArgument);
+            }
+            bench
+                .measure("weighted_out_given_in", || {
+                    out_given_in(balance_in, weight_in, balance_out, weight_out, amount_in)
+                })
+                .map(MathResult::U64)
+                .ok_or(MathError::Overflow)
+        }
+        WeightedOperation::InGivenOut {
+            balance_in,
+            weight_in,
+            balance_out,
+            weight_out,
+            amount_out,
+        } => {
+            if balance_in == 0 || weight_in == 0 || weight_out == 0 || amount_out >= balance_out {
+                return Err(MathError::InvalidArgument);
+            }
+            bench
+                .measure("weighted_in_given_out", || {
+                    in_given_out(balance_in, weight_in, balance_out, weight_out, amount_out)
+                })
+                .map(MathResult::U64)
+                .ok_or(MathError::Overflow)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{curve, instruction::TradeFee},
+        num_bigint::BigInt,
+        num_rational::BigRational,
+        num_traits::ToPrimitive,
+        proptest::prelude::*,
+    };
+
+    /// Weights of the tests, in and out
+    const WEIGHTS: [(u64, u64); 6] = [(50, 50), (80, 20), (20, 80), (1, 3), (3, 1), (60, 40)];
+
+    fn rational(numerator: u64, denominator: u64) -> BigRational {
+        BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+    }
+
+    /// Numerator and denominator of `numerator / denominator` reduced
+    fn reduced(numerator: u64, denominator: u64) -> (i32, i32) {
+        let exponent = rational(numerator, denominator);
+        (
+            exponent.numer().to_i32().unwrap(),
+            exponent.denom().to_i32().unwrap(),
+        )
+    }
+
+    /// Whether `amount_out` is at most the exact amount out, raising both
+    /// sides to the denominator of the exponent to stay in rationals
+    fn at_most_exact_out(
+        amount_out: u64,
+        (balance_in, weight_in, balance_out, weight_out, amount_in): (u64, u64, u64, u64, u64),
+    ) -> bool {
+        if amount_out >= balance_out {
+            return amount_out == 0;
+        }
+        let (p, q) = reduced(weight_in, weight_out);
+        let remaining = rational(balance_out - amount_out, balance_out).pow(q);
+        let base = BigRational::new(
+            BigInt::from(balance_in),
+            BigInt::from(balance_in) + BigInt::from(amount_in),
+        );
+        remaining >= base.pow(p)
+    }
+
+    /// Whether `amount_in` is at least the exact amount in
+    fn at_least_exact_in(
+        amount_in: u64,
+        (balance_in, weight_in, balance_out, weight_out, amount_out): (u64, u64, u64, u64, u64),
+    ) -> bool {
+        let (p, q) = reduced(weight_out, weight_in);
+        let grown = BigRational::new(
+            BigInt::from(balance_in) + BigInt::from(amount_in),
+            BigInt::from(balance_in),
+        );
+        grown.pow(q) >= rational(balance_out, balance_out - amount_out).pow(p)
+    }
+
+    #[test]
+    fn test_out_given_in() {
+        for (weight_in, weight_out) in WEIGHTS {
+            for (balance_in, balance_out, amount_in) in [
+                (1_000_000, 1_000_000, 1_000),
+                (1_000_000, 1_000_000, 900_000),
+                (5_000_000_000, 7_000_000_000, 123_456_789),
+                (10_000_000_000_000, 2_000_000_000, 1),
+                (1_000, 1_000_000_000_000, 3_000),
+            ] {
+                let inputs = (balance_in, weight_in, balance_out, weight_out, amount_in);
+                let amount_out =
+                    out_given_in(balance_in, weight_in, balance_out, weight_out, amount_in)
+                        .unwrap();
+                assert!(at_most_exact_out(amount_out, inputs), "{:?}", inputs);
+                // less than a unit and a relative 1e-10 below the exact amount
+                let slack = 1 + balance_out / 10_000_000_000;
+                assert!(
+                    !at_most_exact_out(amount_out + slack, inputs),
+                    "{:?} gives {}",
+                    inputs,
+                    amount_out
+                );
+            }
+        }
+        assert_eq!(out_given_in(1_000, 1, 1_000, 1, 0), Some(0));
+        assert_eq!(out_given_in(0, 1, 1_000, 1, 10), None);
+        assert_eq!(out_given_in(1_000, 1, 1_000, 0, 10), None);
+    }
+
+    #[test]
+    fn test_in_given_out() {
+        for (weight_in, weight_out) in WEIGHTS {
+            for (balance_in, balance_out, amount_out) in [
+                (1_000_000, 1_000_000, 1_000),
+                (1_000_000, 1_000_000, 900_000),
+                (5_000_000_000, 7_000_000_000, 123_456_789),
+                (2_000_000_000, 10_000_000_000_000, 1),
+            ] {
+                let inputs = (balance_in, weight_in, balance_out, weight_out, amount_out);
+                let amount_in =
+                    in_given_out(balance_in, weight_in, balance_out, weight_out, amount_out)
+                        .unwrap();
+                assert!(at_least_exact_in(amount_in, inputs), "{:?}", inputs);
+                // less than two units and a relative 1e-10 above the exact amount
+                let slack = 2 + amount_in / 10_000_000_000;
+                assert!(
+                    amount_in < slack || !at_least_exact_in(amount_in - slack, inputs),
+                    "{:?} gives {}",
+                    inputs,
+                    amount_in
+                );
+            }
+        }
+        assert_eq!(in_given_out(1_000, 1, 1_000, 1, 0), Some(0));
+        assert_eq!(in_given_out(1_000, 1, 1_000, 1, 1_000), None);
+        // a thousand times the balance to the fourth power
+        assert_eq!(in_given_out(u64::MAX / 2, 1, 1_000, 4, 999), None);
+    }
+
+    #[test]
+    fn test_equal_weights_match_constant_product() {
+        let no_fee = TradeFee {
+            numerator: 0,
+            denominator: 0,
+        };
+        for (amount_in, reserve_in, reserve_out) in [
+            (1_000, 1_000_000, 1_000_000),
+            (12_345, 67_890, 1_000_000),
+            (1, 3, 1_000_000_000),
+        ] {
+            let constant_product =
+                curve::swap_exact_in(amount_in, reserve_in, reserve_out, &no_fee)
+                    .unwrap()
+                    .amount_out;
+            let weighted = out_given_in(reserve_in, 1, reserve_out, 1, amount_in).unwrap();
+            assert!(weighted <= constant_product && weighted + 1 >= constant_product);
+        }
+    }
+
+    #[test]
+    fn test_process_weighted_pool() {
+        let run = |operation| process_weighted_pool(&Bench::default(), operation);
+        // 1_000_000 * (1 - (1_000_000 / 1_001_000)^4) = 3_990.02...
+        assert_eq!(
+            run(WeightedOperation::OutGivenIn {
+                balance_in: 1_000_000,
+                weight_in: 80,
+                balance_out: 1_000_000,
+                weight_out: 20,
+                amount_in: 1_000
+            }),
+            Ok(MathResult::U64(3_990))
+        );
+        assert_eq!(
+            run(WeightedOperation::InGivenOut {
+                balance_in: 1_000_000,
+                weight_in: 80,
+                balance_out: 1_000_000,
+                weight_out: 20,
+                amount_out: 1_000_000
+            }),
+            Err(MathError::InvalidArgument)
+        );
+        assert_eq!(
+            run(WeightedOperation::InGivenOut {
+                balance_in: u64::MAX / 2,
+                weight_in: 1,
+                balance_out: 1_000,
+                weight_out: 4,
+                amount_out: 999
+            }),
+            Err(MathError::Overflow)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip_favors_pool(
+            balance_in in 1_000..u64::MAX >> 20,
+            balance_out in 1_000..u64::MAX >> 20,
+            weight_in in 1..100u64,
+            weight_out in 1..100u64,
+            amount_in in 0..u64::MAX >> 20,
+        ) {
+            let amount_out =
+                out_given_in(balance_in, weight_in, balance_out, weight_out, amount_in).unwrap();
+            prop_assume!(amount_out < balance_out);
+            // selling back what was bought never returns more than was paid
+            let back = out_given_in(
+                balance_out - amount_out,
+                weight_out,
+                balance_in + amount_in,
+                weight_in,
+                amount_out,
+            )
+            .unwrap();
+            prop_assert!(back <= amount_in);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Arithmetic of the wide integers of `uint`, to compare the cost of widening
+//! to U256 or U192 against the u128 operations
+
+use crate::{
+    bench::Bench,
+    error::MathError,
+    instruction::{MathResult, WideOperation},
+    uint::{U192, U256},
+};
+
+/// Largest shift accepted by the U256 shifts, exclusive
+const U256_BITS: u32 = 256;
+
+/// Run the operation and return its result as little-endian u64 limbs
+pub(crate) fn process_wide_arithmetic(
+    bench: &Bench,
+    operation: WideOperation,
+) -> Result<MathResult, MathError> {
+    let result = match operation {
+        WideOperation::U256Multiply {
+            multiplicand,
+            multiplier,
+        } => {
+            let (multiplicand, multiplier) = (U256(multiplicand), U256(multiplier));
+            bench
+                .measure("u256_multiply", || multiplicand.checked_mul(multiplier))
+                .ok_or(MathError::Overflow)?
+        }
+        WideOperation::U256Divide { dividend, divisor } => {
+            let (dividend, divisor) = (U256(dividend), U256(divisor));
+            bench
+                .measure("u256_divide", || dividend.checked_div(divisor))
+                .ok_or(MathError::DivisionByZero)?
+        }
+        WideOperation::U256MulDiv {
+            value,
+            numerator,
+            denominator,
+        } => {
+            if denominator == [0; 4] {
+                return Err(MathError::DivisionByZero);
+            }
+            let (value, numerator, denominator) = (U256(value), U256(numerator), U256(denominator));
+            bench
+                .measure("u256_mul_div", || {
+                    value
+                        .checked_mul(numerator)
+                        .and_then(|product| product.checked_div(denominator))
+                })
+                .ok_or(MathError::Overflow)?
+        }
+        WideOperation::U256Sqrt { radicand } => {
+            let radicand = U256(radicand);
+            bench.measure("u256_sqrt", || radicand.integer_sqrt())
+        }
+        WideOperation::U256ShiftLeft { value, shift } => {
+            if shift >= U256_BITS {
+                return Err(MathError::InvalidArgument);
+            }
+            let value = U256(value);
+            bench.measure("u256_shift_left", || value << shift)
+        }
+        WideOperation::U256ShiftRight { value, shift } => {
+            if shift >= U256_BITS {
+                return Err(MathError::InvalidArgument);
+            }
+            let value = U256(value);
+            bench.measure("u256_shift_right", || value >> shift)
+        }
+        WideOperation::U256OverflowingAdd { lhs, rhs } => {
+            let (lhs, rhs) = (U256(lhs), U256(rhs));
+            let result = bench.measure("u256_overflowing_add", || lhs.overflowing_add(rhs));
+            return Ok(overflowing(result));
+        }
+        WideOperation::U256OverflowingSub { lhs, rhs } => {
+            let (lhs, rhs) = (U256(lhs), U256(rhs));
+            let result = bench.measure("u256_overflowing_sub", || lhs.overflowing_sub(rhs));
+            return Ok(overflowing(result));
+        }
+        WideOperation::U256OverflowingMul { lhs, rhs } => {
+            let (lhs, rhs) = (U256(lhs), U256(rhs));
+            let result = bench.measure("u256_overflowing_mul", || lhs.overflowing_mul(rhs));
+            return Ok(overflowing(result));
+        }
+        WideOperation::U192Multiply {
+            multiplicand,
+            multiplier,
+        } => {
+            let (multiplicand, multiplier) = (U192(multiplicand), U192(multiplier));
+            let result = bench
+                .measure("u192_multiply", || multiplicand.checked_mul(multiplier))
+                .ok_or(MathError::Overflow)?;
+            return Ok(MathResult::U192(result.0));
+        }
+        WideOperation::U192Divide { dividend, divisor } => {
+            let (dividend, divisor) = (U192(dividend), U192(divisor));
+            let result = bench
+                .measure("u192_divide", || dividend.checked_div(divisor))
+                .ok_or(MathError::DivisionByZero)?;
+            return Ok(MathResult::U192(result.0));
+        }
+    };
+    Ok(MathResult::U256(result.0))
+}
+
+/// Result of a wrapping U256 operation
+fn overflowing((value, overflowed): (U256, bool)) -> MathResult {
+    MathResult::U256Overflowing {
+        value: value.0,
+        overflowed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(operation: WideOperation) -> Result<MathResult, MathError> {
+        process_wide_arithmetic(&Bench::default(), operation)
+    }
+
+    fn limbs(value: u128) -> [u64; 4] {
+        U256::from(value).0
+    }
+
+    #[test]
+    fn test_u256_arithmetic() {
+        // u128::MAX^2 only fits after widening
+        let max = limbs(u128::MAX);
+        let square = U256::from(u128::MAX) * U256::from(u128::MAX);
+        assert_eq!(
+            run(WideOperation::U256Multiply {
+                multiplicand: max,
+                multiplier: max
+            }),
+            Ok(MathResult::U256(square.0))
+        );
+        assert_eq!(
+            run(WideOperation::U256Divide {
+                dividend: square.0,
+                divisor: max
+            }),
+            Ok(MathResult::U256(max))
+        );
+        assert_eq!(
+            run(WideOperation::U256MulDiv {
+                value: max,
+                numerator: limbs(3),
+                denominator: limbs(6)
+            }),
+            Ok(MathResult::U256(limbs(u128::MAX / 2)))
+        );
+        assert_eq!(
+            run(WideOperation::U256Sqrt { radicand: square.0 }),
+            Ok(MathResult::U256(max))
+        );
+        assert_eq!(
+            run(WideOperation::U256Sqrt {
+                radicand: (square - U256::one()).0
+            }),
+            Ok(MathResult::U256(limbs(u128::MAX - 1)))
+        );
+    }
+
+    #[test]
+    fn test_u256_shifts() {
+        assert_eq!(
+            run(WideOperation::U256ShiftLeft {
+                value: limbs(3),
+                shift: 127
+            }),
+            Ok(MathResult::U256((U256::from(3) << 127).0))
+        );
+        assert_eq!(
+            run(WideOperation::U256ShiftLeft {
+                value: limbs(3),
+                shift: 255
+            }),
+            Ok(MathResult::U256([0, 0, 0, 1 << 63]))
+        );
+        assert_eq!(
+            run(WideOperation::U256ShiftRight {
+                value: [0, 0, 0, 1 << 63],
+                shift: 255
+            }),
+            Ok(MathResult::U256(limbs(1)))
+        );
+        assert_eq!(
+            run(WideOperation::U256ShiftRight {
+                value: limbs(1),
+                shift: 256
+            }),
+            Err(MathError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_u256_overflowing() {
+        let max = U256::MAX.0;
+        assert_eq!(
+            run(WideOperation::U256OverflowingAdd {
+                lhs: max,
+                rhs: limbs(2)
+            }),
+            Ok(MathResult::U256Overflowing {
+                value: limbs(1),
+                overflowed: true
+            })
+        );
+        assert_eq!(
+            run(WideOperation::U256OverflowingSub {
+                lhs: limbs(0),
+                rhs: limbs(1)
+            }),
+            Ok(MathResult::U256Overflowing {
+                value: max,
+                overflowed: true
+            })
+        );
+        assert_eq!(
+            run(WideOperation::U256OverflowingMul {
+                lhs: limbs(6),
+                rhs: limbs(7)
+            }),
+            Ok(MathResult::U256Overflowing {
+                value: limbs(42),
+                overflowed: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_u192_arithmetic() {
+        let max = U192::from(u128::MAX);
+        assert_eq!(
+            run(WideOperation::U192Multiply {
+                multiplicand: max.0,
+                multiplier: [0, 0, 1]
+            }),
+            Err(MathError::Overflow)
+        );
+        assert_eq!(
+            run(WideOperation::U192Multiply {
+                multiplicand: max.0,
+                multiplier: U192::from(u64::MAX).0
+            }),
+            Ok(MathResult::U192((max * U192::from(u64::MAX)).0))
+        );
+        assert_eq!(
+            run(WideOperation::U192Divide {
+                dividend: max.0,
+                divisor: [0; 3]
+            }),
+            Err(MathError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_u256_errors() {
+        let max = U256::MAX.0;
+        assert_eq!(
+            run(WideOperation::U256Multiply {
+                multiplicand: max,
+                multiplier: limbs(2)
+            }),
+            Err(MathError::Overflow)
+        );
+        assert_eq!(
+            run(WideOperation::U256Divide {
+                dividend: max,
+                divisor: [0; 4]
+            }),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(
+            run(WideOperation::U256MulDiv {
+                value: max,
+                numerator: limbs(2),
+                denominator: limbs(2)
+            }),
+            Err(MathError::Overflow)
+        );
+        assert_eq!(
+            run(WideOperation::U256MulDiv {
+                value: max,
+                numerator: limbs(1),
+                denominator: [0; 4]
+            }),
+            Err(MathError::DivisionByZero)
+        );
+    }
+}
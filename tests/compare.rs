@@ -0,0 +1,104 @@
+// Mark this test as BPF-only due to current `ProgramTest` limitations when
+// CPIing into the system program
+#![cfg(all(feature = "test-sbf", feature = "compare-upstream"))]
+
+use {
+    solana_program_test::*,
+    solana_sdk::{signature::Signer, transaction::Transaction},
+    spl_math_example::{
+        bench::BenchRecord,
+        id,
+        instruction::{self, ComparedOperation, Implementation, MathResult},
+        processor::process_instruction,
+        uint::U256,
+    },
+};
+
+async fn run(
+    banks_client: &mut BanksClient,
+    payer: &solana_sdk::signature::Keypair,
+    implementation: Implementation,
+    operation: ComparedOperation,
+) -> (u64, MathResult) {
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::compare(implementation, operation)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    let metadata = result.metadata.as_ref().unwrap();
+    let record = metadata
+        .log_messages
+        .iter()
+        .find_map(|log| BenchRecord::parse(log))
+        .unwrap();
+    let return_data = metadata.return_data.as_ref().unwrap();
+    let value = MathResult::from_return_data(&return_data.program_id, &return_data.data).unwrap();
+    (record.consumed, value)
+}
+
+fn precise_to_f64(result: &MathResult, decimals: i32) -> f64 {
+    let MathResult::Precise(value) = result else {
+        panic!("expected a precise number, got {:?}", result);
+    };
+    U256(*value).as_u128() as f64 / 10_f64.powi(decimals)
+}
+
+#[tokio::test]
+async fn test_compare_implementations() {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(1_000_000);
+
+    let (mut banks_client, payer, _) = pc.start().await;
+
+    let operations = [
+        ("sqrt_u64", ComparedOperation::SquareRootU64 { radicand: u64::MAX }),
+        ("sqrt_u128", ComparedOperation::SquareRootU128 { radicand: u128::MAX }),
+        (
+            "mul_div_floor",
+            ComparedOperation::MulDivFloor {
+                val: 42,
+                num: 84,
+                denom: 7,
+            },
+        ),
+        ("sqrt_newton", ComparedOperation::SqrtNewton { radicand: u32::MAX as u64 }),
+        ("f32_normal_cdf", ComparedOperation::NormalCdf { argument: 0.5 }),
+    ];
+
+    println!("{:<16} {:>10} {:>10}", "operation", "upstream", "evolved");
+    for (name, operation) in operations {
+        let (upstream_cu, upstream) =
+            run(&mut banks_client, &payer, Implementation::Upstream, operation.clone()).await;
+        let (evolved_cu, evolved) =
+            run(&mut banks_client, &payer, Implementation::Evolved, operation).await;
+        let note = if name == "sqrt_newton" {
+            "  (not like-for-like: 12 decimals upstream, 18 evolved)"
+        } else {
+            ""
+        };
+        println!("{:<16} {:>10} {:>10}{}", name, upstream_cu, evolved_cu, note);
+
+        match name {
+            // the evolved square root carries 18 decimals, the original 12
+            "sqrt_newton" => {
+                let upstream = precise_to_f64(&upstream, 12);
+                let evolved = precise_to_f64(&evolved, 18);
+                assert!((upstream - evolved).abs() <= 1e-6, "{} != {}", upstream, evolved);
+            }
+            "f32_normal_cdf" => {
+                let (MathResult::F32(upstream), MathResult::F32(evolved)) = (&upstream, &evolved)
+                else {
+                    panic!("expected f32 results, got {:?} and {:?}", upstream, evolved);
+                };
+                assert!((upstream - evolved).abs() <= 1e-6, "{} != {}", upstream, evolved);
+            }
+            _ => assert_eq!(upstream, evolved),
+        }
+    }
+}
@@ -0,0 +1,677 @@
+// Mark this test as BPF-only due to current `ProgramTest` limitations when
+// CPIing into the system program
+#![cfg(feature = "test-sbf")]
+
+//! Compares the compute units of every benchmark against the checked-in
+//! baseline in `tests/cu_baseline.toml`.
+//!
+//! Every record a benchmark logs is compared under its label. A regression,
+//! or a record missing from either side, fails the test.
+//!
+//! * `CU_BASELINE_TOLERANCE` allows regressions up to the given percentage
+//! * `UPDATE_CU_BASELINE=1` rewrites the baseline with the measured values
+
+use {
+    solana_program::instruction::Instruction,
+    solana_program_test::*,
+    solana_sdk::{signature::Signer, transaction::Transaction},
+    spl_math_example::{
+        bench::BenchRecord,
+        d18, id,
+        instruction::{
+            self, BatchOperation, BlackScholesInputs, Compounding, CurveOperation, D18Operation,
+            FixedPointOperation, FixedPointType, InterestOperation, NormalOperation, OptionKind,
+            PreciseOperation, RoundingMode, SignedOperation, SignedValue, SqrtAlgorithm,
+            StableSwapOperation, TradeFee, UnsignedInteger, WeightedOperation, WideOperation,
+        },
+        precise_number::PreciseNumber,
+        processor::process_instruction,
+        uint::U256,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        env, fs,
+    },
+};
+
+const BASELINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cu_baseline.toml");
+
+const BASELINE_HEADER: &str = "\
+# Compute units consumed per benchmark and record label, as reported by
+# `cu_bench_consumed`, net of the window overhead the program calibrates at
+# runtime.
+# Regenerate with `UPDATE_CU_BASELINE=1 cargo test-sbf --test cu_baseline`.
+";
+
+fn cases() -> Vec<(&'static str, Instruction)> {
+    let precise = |value: u128| PreciseNumber::new(value).unwrap().value.0;
+    let precise_d18 = |value: u128| d18::from_u128(value).unwrap().value.0;
+    let wide = |value: u128| U256::from(value).0;
+    vec![
+        ("noop", instruction::noop()),
+        (
+            "newton_sqrt_u64_max",
+            instruction::precise_sqrt(u64::MAX, SqrtAlgorithm::Newton),
+        ),
+        (
+            "cordic_sqrt_u32_max",
+            instruction::precise_sqrt(u32::MAX as u64, SqrtAlgorithm::Cordic),
+        ),
+        ("sqrt_u64_max", instruction::sqrt_u64(u64::MAX)),
+        (
+            "newton_sqrt_array",
+            instruction::precise_sqrt_array(1000, 100, SqrtAlgorithm::Newton),
+        ),
+        (
+            "cordic_sqrt_array",
+            instruction::precise_sqrt_array(1000, 100, SqrtAlgorithm::Cordic),
+        ),
+        ("sqrt_u128", instruction::sqrt_u128(u64::MAX as u128)),
+        ("sqrt_u128_max", instruction::sqrt_u128(u128::MAX)),
+        ("muldiv_u64", instruction::precise_muldiv(42, 84, 7)),
+        ("u64_multiply", instruction::u64_multiply(42, 84)),
+        ("u64_divide", instruction::u64_divide(3, 1)),
+        ("f32_multiply", instruction::f32_multiply(1.5_f32, 2.0_f32)),
+        ("f32_divide", instruction::f32_divide(3_f32, 1.5_f32)),
+        (
+            "f32_exponentiate",
+            instruction::f32_exponentiate(4_f32, 2_f32),
+        ),
+        ("f32_natural_log", instruction::f32_natural_log(1_f32.exp())),
+        ("f32_normal_cdf", instruction::f32_normal_cdf(0_f32)),
+        ("f64_pow", instruction::f64_pow(50_f64, 10.5_f64)),
+        (
+            "u128_multiply",
+            instruction::u128_multiply(u64::MAX.into(), u64::MAX.into()),
+        ),
+        (
+            "u128_divide",
+            instruction::u128_divide(u128::MAX, u128::MAX / 69),
+        ),
+        (
+            "f64_multiply",
+            instruction::f64_multiply(f64::powf(2., 42.), 1e-4),
+        ),
+        (
+            "f64_divide",
+            instruction::f64_divide(f64::powf(2., 42.), 420420.6969),
+        ),
+        ("checked_ceil_div", instruction::checked_ceil_div(100, 7)),
+        (
+            "precise_add",
+            instruction::precise_arithmetic(PreciseOperation::Add {
+                lhs: precise(42),
+                rhs: precise(84),
+            }),
+        ),
+        (
+            "precise_mul",
+            instruction::precise_arithmetic(PreciseOperation::Mul {
+                lhs: precise(42),
+                rhs: precise(84),
+            }),
+        ),
+        (
+            "precise_div",
+            instruction::precise_arithmetic(PreciseOperation::Div {
+                lhs: precise(42),
+                rhs: precise(84),
+            }),
+        ),
+        (
+            "precise_pow",
+            instruction::precise_arithmetic(PreciseOperation::Pow {
+                base: precise(2),
+                exponent: 64,
+            }),
+        ),
+        (
+            "d18_local_from_decimal",
+            instruction::precise_d18_arithmetic(D18Operation::FromDecimal {
+                value: "1234567890.123456789".to_string(),
+            }),
+        ),
+        (
+            "d18_mul",
+            instruction::precise_d18_arithmetic(D18Operation::Mul {
+                lhs: precise_d18(42),
+                rhs: precise_d18(84),
+            }),
+        ),
+        (
+            "d18_div",
+            instruction::precise_d18_arithmetic(D18Operation::Div {
+                lhs: precise_d18(42),
+                rhs: precise_d18(84),
+            }),
+        ),
+        (
+            "d18_local_mul_floor",
+            instruction::precise_d18_arithmetic(D18Operation::MulFloor {
+                lhs: precise_d18(42),
+                rhs: precise_d18(84),
+            }),
+        ),
+        (
+            "d18_local_div_ceil",
+            instruction::precise_d18_arithmetic(D18Operation::DivCeil {
+                lhs: precise_d18(42),
+                rhs: precise_d18(84),
+            }),
+        ),
+        (
+            "d18_pow",
+            instruction::precise_d18_arithmetic(D18Operation::Pow {
+                base: precise_d18(2),
+                exponent: 64,
+            }),
+        ),
+        (
+            "d18_exp",
+            instruction::fixed_point_math(
+                FixedPointType::PreciseNumber256D18,
+                FixedPointOperation::Exp {
+                    value: precise_d18(10),
+                    negative: false,
+                },
+            ),
+        ),
+        (
+            "d18_ln",
+            instruction::fixed_point_math(
+                FixedPointType::PreciseNumber256D18,
+                FixedPointOperation::Ln {
+                    value: precise_d18(42),
+                },
+            ),
+        ),
+        (
+            "precise_pow_fraction",
+            instruction::fixed_point_math(
+                FixedPointType::PreciseNumber,
+                FixedPointOperation::Pow {
+                    base: precise(2),
+                    exponent: (U256(precise(1)) / 2).0,
+                },
+            ),
+        ),
+        (
+            "precise_normal_cdf",
+            instruction::normal_distribution(NormalOperation::Cdf {
+                argument: precise(2),
+                negative: true,
+            }),
+        ),
+        (
+            "precise_normal_inverse_cdf",
+            instruction::normal_distribution(NormalOperation::InverseCdf {
+                probability: (U256(precise(1)) / 40).0,
+            }),
+        ),
+        (
+            "f64_black_scholes_call",
+            instruction::black_scholes(
+                OptionKind::Call,
+                BlackScholesInputs::F64 {
+                    spot: 100.,
+                    strike: 100.,
+                    rate: 0.05,
+                    volatility: 0.2,
+                    time: 1.,
+                },
+            ),
+        ),
+        (
+            "precise_black_scholes_call",
+            instruction::black_scholes(
+                OptionKind::Call,
+                BlackScholesInputs::Precise {
+                    spot: precise(100),
+                    strike: precise(100),
+                    rate: (U256(precise(1)) / 20).0,
+                    volatility: (U256(precise(1)) / 5).0,
+                    time: precise(1),
+                },
+            ),
+        ),
+        (
+            "curve_swap_exact_in",
+            instruction::curve(CurveOperation::SwapExactIn {
+                amount_in: 1_000_000,
+                reserve_in: u64::MAX / 2,
+                reserve_out: u64::MAX / 3,
+                fee: TradeFee {
+                    numerator: 25,
+                    denominator: 10_000,
+                },
+            }),
+        ),
+        (
+            "curve_swap_exact_out",
+            instruction::curve(CurveOperation::SwapExactOut {
+                amount_out: 1_000_000,
+                reserve_in: u64::MAX / 2,
+                reserve_out: u64::MAX / 3,
+                fee: TradeFee {
+                    numerator: 25,
+                    denominator: 10_000,
+                },
+            }),
+        ),
+        (
+            "stableswap_d_2",
+            instruction::stable_swap(StableSwapOperation::ComputeD {
+                amp: 100,
+                balances: vec![1_000_000_000_000_000_000_000, 10_000_000_000_000_000_000],
+                max_iterations: 255,
+            }),
+        ),
+        (
+            "stableswap_d_8",
+            instruction::stable_swap(StableSwapOperation::ComputeD {
+                amp: 100,
+                balances: (1..=8).map(|coin| coin * 1_000_000_000_000).collect(),
+                max_iterations: 255,
+            }),
+        ),
+        (
+            "weighted_out_given_in",
+            instruction::weighted_pool(WeightedOperation::OutGivenIn {
+                balance_in: 1_000_000_000_000,
+                weight_in: 80,
+                balance_out: 3_000_000_000_000,
+                weight_out: 20,
+                amount_in: 1_000_000_000,
+            }),
+        ),
+        (
+            "weighted_in_given_out",
+            instruction::weighted_pool(WeightedOperation::InGivenOut {
+                balance_in: 1_000_000_000_000,
+                weight_in: 80,
+                balance_out: 3_000_000_000_000,
+                weight_out: 20,
+                amount_out: 1_000_000_000,
+            }),
+        ),
+        (
+            "interest_accrue_per_slot",
+            instruction::compound_interest(InterestOperation::Accrue {
+                apr: (U256(precise(1)) / 10).0,
+                years: 1,
+                compounding: Compounding::PerSlot,
+                round_up: false,
+            }),
+        ),
+        (
+            "interest_accrue_per_year",
+            instruction::compound_interest(InterestOperation::Accrue {
+                apr: (U256(precise(1)) / 10).0,
+                years: 1,
+                compounding: Compounding::PerYear,
+                round_up: false,
+            }),
+        ),
+        (
+            "interest_accrue_continuous",
+            instruction::compound_interest(InterestOperation::Accrue {
+                apr: (U256(precise(1)) / 10).0,
+                years: 1,
+                compounding: Compounding::Continuous,
+                round_up: false,
+            }),
+        ),
+        (
+            "signed_sub",
+            instruction::signed_arithmetic(
+                FixedPointType::PreciseNumber,
+                SignedOperation::Sub {
+                    lhs: SignedValue {
+                        value: precise(42),
+                        negative: false,
+                    },
+                    rhs: SignedValue {
+                        value: precise(84),
+                        negative: false,
+                    },
+                },
+            ),
+        ),
+        (
+            "signed_mul",
+            instruction::signed_arithmetic(
+                FixedPointType::PreciseNumber,
+                SignedOperation::Mul {
+                    lhs: SignedValue {
+                        value: precise(42),
+                        negative: true,
+                    },
+                    rhs: SignedValue {
+                        value: precise(84),
+                        negative: false,
+                    },
+                },
+            ),
+        ),
+        (
+            "signed_d18_mul",
+            instruction::signed_arithmetic(
+                FixedPointType::PreciseNumber256D18,
+                SignedOperation::Mul {
+                    lhs: SignedValue {
+                        value: precise_d18(42),
+                        negative: true,
+                    },
+                    rhs: SignedValue {
+                        value: precise_d18(84),
+                        negative: false,
+                    },
+                },
+            ),
+        ),
+        (
+            "muldiv_u64_ceil",
+            instruction::precise_muldiv_rounded(43, 85, 7, RoundingMode::Ceil),
+        ),
+        (
+            "muldiv_u64_half_even",
+            instruction::precise_muldiv_rounded(43, 85, 7, RoundingMode::HalfEven),
+        ),
+        (
+            "integer_sqrt_u32_max",
+            instruction::integer_sqrt(UnsignedInteger::U32(u32::MAX)),
+        ),
+        (
+            "integer_sqrt_u256_max",
+            instruction::integer_sqrt(UnsignedInteger::U256(U256::MAX.0)),
+        ),
+        (
+            "u256_multiply",
+            instruction::wide_arithmetic(WideOperation::U256Multiply {
+                multiplicand: wide(u64::MAX.into()),
+                multiplier: wide(u64::MAX.into()),
+            }),
+        ),
+        (
+            "u256_divide",
+            instruction::wide_arithmetic(WideOperation::U256Divide {
+                dividend: wide(u128::MAX),
+                divisor: wide(u128::MAX / 69),
+            }),
+        ),
+        (
+            "u256_mul_div",
+            instruction::wide_arithmetic(WideOperation::U256MulDiv {
+                value: wide(u128::MAX),
+                numerator: wide(u128::MAX),
+                denominator: wide(u128::MAX / 69),
+            }),
+        ),
+        (
+            "u256_sqrt_u128_max",
+            instruction::wide_arithmetic(WideOperation::U256Sqrt {
+                radicand: wide(u128::MAX),
+            }),
+        ),
+        (
+            "u192_multiply",
+            instruction::wide_arithmetic(WideOperation::U192Multiply {
+                multiplicand: [u64::MAX, 0, 0],
+                multiplier: [u64::MAX, 0, 0],
+            }),
+        ),
+        (
+            "batch_u64_multiply",
+            instruction::batch(BatchOperation::U64Multiply(vec![(42, 84); 16])),
+        ),
+        (
+            "batch_f64_divide",
+            instruction::batch(BatchOperation::F64Divide(vec![
+                (f64::powf(2., 42.), 420420.6969);
+                16
+            ])),
+        ),
+    ]
+}
+
+/// Compute units per record label, per benchmark
+type Measurements = BTreeMap<String, BTreeMap<String, u64>>;
+
+#[derive(Debug, PartialEq)]
+enum Status {
+    Unchanged,
+    Improved,
+    Regressed,
+    WithinTolerance,
+    New,
+    Removed,
+}
+
+impl Status {
+    /// Whether the record fails the comparison, unless the baseline is being
+    /// rewritten
+    fn fails(&self) -> bool {
+        matches!(self, Self::Regressed | Self::New | Self::Removed)
+    }
+}
+
+fn compare(baseline: Option<u64>, measured: Option<u64>, tolerance_percent: f64) -> Status {
+    let (baseline, measured) = match (baseline, measured) {
+        (Some(baseline), Some(measured)) => (baseline, measured),
+        (Some(_), None) => return Status::Removed,
+        (None, _) => return Status::New,
+    };
+    if measured == baseline {
+        Status::Unchanged
+    } else if measured < baseline {
+        Status::Improved
+    } else if (measured - baseline) as f64 <= baseline as f64 * tolerance_percent / 100.0 {
+        Status::WithinTolerance
+    } else {
+        Status::Regressed
+    }
+}
+
+/// Record of the report, for every record label of every benchmark in
+/// either the baseline or the measurements
+struct Row<'a> {
+    benchmark: &'a str,
+    label: &'a str,
+    baseline: Option<u64>,
+    measured: Option<u64>,
+    status: Status,
+}
+
+fn rows<'a>(
+    baseline: &'a Measurements,
+    measured: &'a Measurements,
+    tolerance_percent: f64,
+) -> Vec<Row<'a>> {
+    let units = |measurements: &'a Measurements, benchmark: &str, label: &str| {
+        measurements.get(benchmark)?.get(label).copied()
+    };
+    let keys: BTreeSet<(&str, &str)> = baseline
+        .iter()
+        .chain(measured)
+        .flat_map(|(benchmark, records)| {
+            records
+                .keys()
+                .map(move |label| (benchmark.as_str(), label.as_str()))
+        })
+        .collect();
+    keys.into_iter()
+        .map(|(benchmark, label)| {
+            let (expected, consumed) = (
+                units(baseline, benchmark, label),
+                units(measured, benchmark, label),
+            );
+            Row {
+                benchmark,
+                label,
+                baseline: expected,
+                measured: consumed,
+                status: compare(expected, consumed, tolerance_percent),
+            }
+        })
+        .collect()
+}
+
+fn load_baseline() -> Measurements {
+    match fs::read_to_string(BASELINE_PATH) {
+        Ok(contents) => toml::from_str(&contents).expect("invalid CU baseline"),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn write_baseline(measured: &Measurements) {
+    let mut contents = BASELINE_HEADER.to_string();
+    for (benchmark, records) in measured {
+        contents.push_str(&format!("\n[{}]\n", benchmark));
+        for (label, consumed) in records {
+            contents.push_str(&format!("{} = {}\n", label, consumed));
+        }
+    }
+    fs::write(BASELINE_PATH, contents).unwrap();
+}
+
+fn print_report(rows: &[Row]) {
+    println!(
+        "{:<24} {:<28} {:>10} {:>10} {:>10}  status",
+        "benchmark", "label", "baseline", "measured", "delta"
+    );
+    let units = |units: Option<u64>| units.map(|units| units.to_string()).unwrap_or_default();
+    for row in rows {
+        let delta = row
+            .baseline
+            .zip(row.measured)
+            .map(|(expected, consumed)| format!("{:+}", consumed as i64 - expected as i64))
+            .unwrap_or_default();
+        println!(
+            "{:<24} {:<28} {:>10} {:>10} {:>10}  {:?}",
+            row.benchmark,
+            row.label,
+            units(row.baseline),
+            units(row.measured),
+            delta,
+            row.status,
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_cu_baseline() {
+    let tolerance_percent = env::var("CU_BASELINE_TOLERANCE")
+        .map(|tolerance| {
+            tolerance
+                .parse::<f64>()
+                .expect("invalid CU_BASELINE_TOLERANCE")
+        })
+        .unwrap_or(0.0);
+    let update = env::var("UPDATE_CU_BASELINE").is_ok_and(|update| update == "1");
+
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(5_000_000);
+
+    let (banks_client, payer, recent_blockhash) = pc.start().await;
+
+    let mut measured = Measurements::new();
+    for (name, instruction) in cases() {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        let logs = &result.metadata.as_ref().unwrap().log_messages;
+        let mut records = BTreeMap::new();
+        for record in logs.iter().filter_map(|log| BenchRecord::parse(log)) {
+            let label = record.label.clone();
+            assert!(
+                records.insert(record.label, record.consumed).is_none(),
+                "{} logged {} twice",
+                name,
+                label
+            );
+        }
+        assert!(!records.is_empty(), "no measurement logged for {}", name);
+        measured.insert(name.to_string(), records);
+    }
+
+    let baseline = load_baseline();
+    let rows = rows(&baseline, &measured, tolerance_percent);
+    print_report(&rows);
+
+    if update {
+        write_baseline(&measured);
+        return;
+    }
+
+    let failures: Vec<String> = rows
+        .iter()
+        .filter(|row| row.status.fails())
+        .map(|row| format!("{}.{} {:?}", row.benchmark, row.label, row.status))
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "compute units differ from the baseline in {:?}, rerun with UPDATE_CU_BASELINE=1 if \
+         intentional",
+        failures
+    );
+}
+
+#[test]
+fn test_compare() {
+    assert_eq!(compare(None, Some(10), 0.0), Status::New);
+    assert_eq!(compare(Some(10), None, 0.0), Status::Removed);
+    assert_eq!(compare(Some(10), Some(10), 0.0), Status::Unchanged);
+    assert_eq!(compare(Some(10), Some(9), 0.0), Status::Improved);
+    assert_eq!(compare(Some(100), Some(105), 5.0), Status::WithinTolerance);
+    assert_eq!(compare(Some(100), Some(106), 5.0), Status::Regressed);
+    assert_eq!(compare(Some(0), Some(1), 50.0), Status::Regressed);
+}
+
+#[test]
+fn test_rows() {
+    let measurements = |records: &[(&str, &str, u64)]| {
+        let mut measurements = Measurements::new();
+        for (benchmark, label, consumed) in records {
+            measurements
+                .entry(benchmark.to_string())
+                .or_default()
+                .insert(label.to_string(), *consumed);
+        }
+        measurements
+    };
+    let baseline = measurements(&[
+        ("exp", "d18_exp", 100),
+        ("exp", "f64_exp", 50),
+        ("old", "old", 1),
+    ]);
+    let measured = measurements(&[
+        ("exp", "d18_exp", 100),
+        ("exp", "f64_exp", 60),
+        ("new", "new", 1),
+    ]);
+    let statuses: Vec<(&str, &str, Status)> = rows(&baseline, &measured, 0.0)
+        .into_iter()
+        .map(|row| (row.benchmark, row.label, row.status))
+        .collect();
+    // every record of a benchmark is compared, not only the first
+    assert_eq!(
+        statuses,
+        [
+            ("exp", "d18_exp", Status::Unchanged),
+            ("exp", "f64_exp", Status::Regressed),
+            ("new", "new", Status::New),
+            ("old", "old", Status::Removed),
+        ]
+    );
+    assert_eq!(
+        statuses
+            .iter()
+            .filter(|(_, _, status)| status.fails())
+            .count(),
+        3
+    );
+}
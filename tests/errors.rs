@@ -0,0 +1,477 @@
+// Mark this test as BPF-only due to current `ProgramTest` limitations when
+// CPIing into the system program
+#![cfg(feature = "test-sbf")]
+
+use {
+    solana_program::instruction::{Instruction, InstructionError},
+    solana_program_test::*,
+    solana_sdk::{
+        signature::Signer,
+        transaction::{Transaction, TransactionError},
+    },
+    spl_math_example::{
+        batch::MAX_BATCH_INPUTS,
+        error::MathError,
+        id,
+        instruction::{
+            self, BatchOperation, BlackScholesInputs, ComparedOperation, CurveOperation,
+            D18Input, D18Operation, FixedPointOperation, FixedPointType, Implementation,
+            InterestOperation, NormalOperation, OptionKind, PreciseOperation, SignedOperation,
+            SignedValue, SqrtAlgorithm, StableSwapOperation, SweepInputs, SweepOperation,
+            TradeFee, WeightedOperation, WideOperation,
+        },
+        precise_number::PreciseNumber,
+        processor::process_instruction,
+        sweep::MAX_SWEEP_INPUTS,
+    },
+};
+
+async fn assert_math_error(instruction: Instruction, expected: MathError) {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(1_000_000);
+
+    let (banks_client, payer, recent_blockhash) = pc.start().await;
+
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected as u32))
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_instruction() {
+    let instruction = Instruction {
+        program_id: id(),
+        accounts: vec![],
+        data: vec![255],
+    };
+    assert_math_error(instruction, MathError::InvalidInstruction).await;
+}
+
+#[tokio::test]
+async fn test_u64_multiply_overflow() {
+    assert_math_error(instruction::u64_multiply(u64::MAX, 2), MathError::Overflow).await;
+}
+
+#[tokio::test]
+async fn test_u128_multiply_overflow() {
+    assert_math_error(instruction::u128_multiply(u128::MAX, 2), MathError::Overflow).await;
+}
+
+#[tokio::test]
+async fn test_u64_divide_by_zero() {
+    assert_math_error(instruction::u64_divide(1, 0), MathError::DivisionByZero).await;
+}
+
+#[tokio::test]
+async fn test_u128_divide_by_zero() {
+    assert_math_error(instruction::u128_divide(1, 0), MathError::DivisionByZero).await;
+}
+
+#[tokio::test]
+async fn test_f32_divide_by_zero() {
+    assert_math_error(instruction::f32_divide(1.0, 0.0), MathError::DivisionByZero).await;
+}
+
+#[tokio::test]
+async fn test_f64_divide_by_zero() {
+    assert_math_error(instruction::f64_divide(1.0, 0.0), MathError::DivisionByZero).await;
+}
+
+#[tokio::test]
+async fn test_muldiv_by_zero() {
+    assert_math_error(instruction::precise_muldiv(42, 84, 0), MathError::DivisionByZero).await;
+}
+
+#[tokio::test]
+async fn test_precise_sqrt_conversion_failed() {
+    assert_math_error(
+        instruction::precise_sqrt_inputs(
+            vec![D18Input::Decimal("1.0000000000000000001".to_string())],
+            SqrtAlgorithm::Newton,
+        ),
+        MathError::ConversionFailed,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_precise_sqrt_negative() {
+    assert_math_error(
+        instruction::precise_sqrt_inputs(
+            vec![D18Input::Decimal("-1".to_string())],
+            SqrtAlgorithm::Cordic,
+        ),
+        MathError::DomainError,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_f32_nan_input() {
+    assert_math_error(instruction::f32_multiply(f32::NAN, 1.0), MathError::NotANumber).await;
+}
+
+#[tokio::test]
+async fn test_f64_nan_result() {
+    assert_math_error(
+        instruction::f64_multiply(f64::INFINITY, 0.0),
+        MathError::NotANumber,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_f32_natural_log_negative() {
+    assert_math_error(instruction::f32_natural_log(-1.0), MathError::DomainError).await;
+}
+
+#[tokio::test]
+async fn test_f64_pow_negative_base_fractional_exponent() {
+    assert_math_error(instruction::f64_pow(-2.0, 0.5), MathError::DomainError).await;
+}
+
+#[cfg(not(feature = "compare-upstream"))]
+#[tokio::test]
+async fn test_upstream_unavailable() {
+    assert_math_error(
+        instruction::compare(
+            Implementation::Upstream,
+            ComparedOperation::SquareRootU64 { radicand: 4 },
+        ),
+        MathError::ImplementationUnavailable,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_compare_muldiv_by_zero() {
+    assert_math_error(
+        instruction::compare(
+            Implementation::Evolved,
+            ComparedOperation::MulDivFloor {
+                val: 1,
+                num: 1,
+                denom: 0,
+            },
+        ),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_sweep_too_many_inputs() {
+    assert_math_error(
+        instruction::sweep(
+            SweepOperation::SquareRootU64,
+            SweepInputs::Explicit(vec![4; MAX_SWEEP_INPUTS + 1]),
+        ),
+        MathError::InvalidArgument,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_sweep_input_out_of_range() {
+    assert_math_error(
+        instruction::sweep(
+            SweepOperation::PreciseSquareRoot(SqrtAlgorithm::Newton),
+            SweepInputs::Explicit(vec![u64::MAX as u128 + 1]),
+        ),
+        MathError::ConversionFailed,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_batch_too_many_inputs() {
+    assert_math_error(
+        instruction::batch(BatchOperation::SquareRootU64(vec![4; MAX_BATCH_INPUTS + 1])),
+        MathError::InvalidArgument,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_batch_divide_by_zero() {
+    assert_math_error(
+        instruction::batch(BatchOperation::F64Divide(vec![(1.0, 2.0), (1.0, 0.0)])),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_checked_ceil_div_by_zero() {
+    assert_math_error(instruction::checked_ceil_div(1, 0), MathError::DivisionByZero).await;
+}
+
+#[tokio::test]
+async fn test_checked_ceil_div_overflow() {
+    // the quotient is 0, so the dividend is doubled to round it, overflowing
+    assert_math_error(
+        instruction::checked_ceil_div(u128::MAX - 1, u128::MAX),
+        MathError::Overflow,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_precise_sub_underflow() {
+    assert_math_error(
+        instruction::precise_arithmetic(PreciseOperation::Sub {
+            lhs: PreciseNumber::new(1).unwrap().value.0,
+            rhs: PreciseNumber::new(2).unwrap().value.0,
+        }),
+        MathError::Underflow,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_precise_div_by_zero() {
+    assert_math_error(
+        instruction::precise_arithmetic(PreciseOperation::Div {
+            lhs: PreciseNumber::new(1).unwrap().value.0,
+            rhs: [0; 4],
+        }),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_precise_mul_overflow() {
+    assert_math_error(
+        instruction::precise_arithmetic(PreciseOperation::Mul {
+            lhs: [u64::MAX; 4],
+            rhs: PreciseNumber::new(2).unwrap().value.0,
+        }),
+        MathError::Overflow,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_d18_from_invalid_decimal() {
+    assert_math_error(
+        instruction::precise_d18_arithmetic(D18Operation::FromDecimal {
+            value: "0.0000000000000000001".to_string(),
+        }),
+        MathError::ConversionFailed,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_d18_div_by_zero() {
+    assert_math_error(
+        instruction::precise_d18_arithmetic(D18Operation::DivCeil {
+            lhs: [1, 0, 0, 0],
+            rhs: [0; 4],
+        }),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_d18_to_u128_overflow() {
+    assert_math_error(
+        instruction::precise_d18_arithmetic(D18Operation::ToU128 {
+            value: [u64::MAX; 4],
+        }),
+        MathError::Overflow,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_u256_multiply_overflow() {
+    assert_math_error(
+        instruction::wide_arithmetic(WideOperation::U256Multiply {
+            multiplicand: [u64::MAX; 4],
+            multiplier: [2, 0, 0, 0],
+        }),
+        MathError::Overflow,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_u256_mul_div_by_zero() {
+    assert_math_error(
+        instruction::wide_arithmetic(WideOperation::U256MulDiv {
+            value: [1, 0, 0, 0],
+            numerator: [1, 0, 0, 0],
+            denominator: [0; 4],
+        }),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_u256_shift_too_wide() {
+    assert_math_error(
+        instruction::wide_arithmetic(WideOperation::U256ShiftLeft {
+            value: [1, 0, 0, 0],
+            shift: 256,
+        }),
+        MathError::InvalidArgument,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_u192_divide_by_zero() {
+    assert_math_error(
+        instruction::wide_arithmetic(WideOperation::U192Divide {
+            dividend: [1, 0, 0],
+            divisor: [0; 3],
+        }),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_ln_of_zero() {
+    assert_math_error(
+        instruction::fixed_point_math(
+            FixedPointType::PreciseNumber256D18,
+            FixedPointOperation::Ln { value: [0; 4] },
+        ),
+        MathError::DomainError,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_exp_overflow() {
+    assert_math_error(
+        instruction::fixed_point_math(
+            FixedPointType::PreciseNumber,
+            FixedPointOperation::Exp {
+                value: PreciseNumber::new(1000).unwrap().value.0,
+                negative: false,
+            },
+        ),
+        MathError::Overflow,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_normal_inverse_cdf_of_zero() {
+    assert_math_error(
+        instruction::normal_distribution(NormalOperation::InverseCdf {
+            probability: [0; 4],
+        }),
+        MathError::DomainError,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_black_scholes_at_expiry() {
+    assert_math_error(
+        instruction::black_scholes(
+            OptionKind::Call,
+            BlackScholesInputs::F64 {
+                spot: 100.,
+                strike: 100.,
+                rate: 0.05,
+                volatility: 0.2,
+                time: 0.,
+            },
+        ),
+        MathError::DomainError,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_curve_swap_out_whole_reserve() {
+    assert_math_error(
+        instruction::curve(CurveOperation::SwapExactOut {
+            amount_out: 1_000,
+            reserve_in: 1_000,
+            reserve_out: 1_000,
+            fee: TradeFee {
+                numerator: 3,
+                denominator: 1000,
+            },
+        }),
+        MathError::InvalidArgument,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_stable_swap_not_converged() {
+    assert_math_error(
+        instruction::stable_swap(StableSwapOperation::ComputeD {
+            amp: 100,
+            balances: vec![1_000_000_000_000_000_000_000, 10_000_000_000_000_000_000],
+            max_iterations: 1,
+        }),
+        MathError::NotConverged,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_weighted_pool_zero_weight() {
+    assert_math_error(
+        instruction::weighted_pool(WeightedOperation::OutGivenIn {
+            balance_in: 1_000_000,
+            weight_in: 0,
+            balance_out: 1_000_000,
+            weight_out: 50,
+            amount_in: 1_000,
+        }),
+        MathError::InvalidArgument,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_apy_to_apr_no_periods() {
+    assert_math_error(
+        instruction::compound_interest(InterestOperation::ApyToApr {
+            apy: PreciseNumber::new(1).unwrap().value.0,
+            periods_per_year: 0,
+        }),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_signed_div_by_zero() {
+    let value = |value: u128| SignedValue {
+        value: PreciseNumber::new(value).unwrap().value.0,
+        negative: true,
+    };
+    assert_math_error(
+        instruction::signed_arithmetic(
+            FixedPointType::PreciseNumber,
+            SignedOperation::Div {
+                lhs: value(1),
+                rhs: value(0),
+            },
+        ),
+        MathError::DivisionByZero,
+    )
+    .await;
+}
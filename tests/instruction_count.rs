@@ -3,12 +3,26 @@
 #![cfg(feature = "test-sbf")]
 
 use {
+    solana_program::instruction::Instruction,
     solana_program_test::*,
     solana_sdk::{signature::Signer, transaction::Transaction},
-    spl_math_example::{id, instruction, processor::process_instruction},
+    spl_math_example::{
+        bench::BenchRecord,
+        d18,
+        id,
+        instruction::{self, MathResult},
+        precise_number::PreciseNumber,
+        processor::process_instruction,
+        uint::U256,
+    },
+};
+use spl_math_example::instruction::{
+    BatchOperation, BlackScholesInputs, Compounding, CurveOperation, D18Input, D18Operation,
+    FixedPointOperation, FixedPointType, InterestOperation, NormalOperation, OptionKind,
+    PreciseOperation, RoundingMode, SignedOperation, SignedValue, SqrtAlgorithm,
+    StableSwapOperation, SweepInputs, SweepOperation, TradeFee, UnsignedInteger,
+    WeightedOperation, WideOperation,
 };
-use spl_math_example::instruction::SqrtAlgorithm;
-use spl_math_example::processor::{CU_CORRECTION};
 
 
 #[tokio::test]
@@ -27,9 +41,14 @@ async fn test_noop() {
 
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
 
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
+    assert_eq!(parse_result_from_return_data(&result), None);
 
-    assert_eq!(consumed_compute_units, 0);
+    // the calibration measures the same empty window, so nothing is left
+    let logs = &result.metadata.as_ref().unwrap().log_messages;
+    let record = logs.iter().find_map(|log| BenchRecord::parse(log)).unwrap();
+    assert!(record.overhead > 0);
+    assert_eq!(record.raw, record.overhead);
+    assert_eq!(record.consumed, 0);
 }
 
 #[tokio::test]
@@ -48,10 +67,7 @@ async fn test_newton_sqrt_u64_max() {
 
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
 
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    // assert_eq!(consumed_compute_units, 363278);
-    // assert_eq!(consumed_compute_units, 149571);// before improvement 2026-01-04
-    assert_eq!(consumed_compute_units, 138626);
+    assert_precise_d18_results(&result, &[(u64::MAX as f64).sqrt()]);
 }
 
 #[tokio::test]
@@ -68,10 +84,7 @@ async fn test_cordic_sqrt_u32_max() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    // assert_eq!(consumed_compute_units, 184943);
-    // assert_eq!(consumed_compute_units, 64791); // before improvement 2026-01-04
-    assert_eq!(consumed_compute_units, 79100);
+    assert_precise_d18_results(&result, &[(u32::MAX as f64).sqrt()]);
 }
 
 #[tokio::test]
@@ -86,9 +99,35 @@ async fn test_cordic_sqrt_u64() {
         Transaction::new_with_payer(&[instruction::sqrt_u64(u64::MAX)], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    // assert_eq!(consumed_compute_units, 816); // before improvement 2026-01-04
-    assert_eq!(consumed_compute_units, 560);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::U64(u32::MAX as u64)));
+}
+
+#[tokio::test]
+async fn test_newton_sqrt_exact_inputs() {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(5_000_000);
+
+    let (banks_client, payer, recent_blockhash) = pc.start().await;
+
+    // neither radicand survives a round trip through f64
+    let radicands = vec![
+        D18Input::U128((1 << 100) + 1),
+        D18Input::Decimal("9007199254740993.000000000000000001".to_string()),
+    ];
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::precise_sqrt_inputs(
+            radicands,
+            SqrtAlgorithm::Newton,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert_precise_d18_results(&result, &[2f64.powi(50), 9_007_199_254_740_993f64.sqrt()]);
 }
 
 
@@ -102,13 +141,13 @@ async fn test_newton_sqrt_array() {
     let (banks_client, payer, recent_blockhash) = pc.start().await;
 
     let mut transaction = Transaction::new_with_payer(
-        &[instruction::precise_sqrt_array(1000.0, 100.0, SqrtAlgorithm::Newton)],
+        &[instruction::precise_sqrt_array(1000, 100, SqrtAlgorithm::Newton)],
         Some(&payer.pubkey()),
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 288153);
+    let expected: Vec<f64> = (0..8).map(|i| (1000.0 + 100.0 * i as f64).sqrt()).collect();
+    assert_precise_d18_results(&result, &expected);
 }
 
 #[tokio::test]
@@ -120,13 +159,13 @@ async fn test_cordic_sqrt_array() {
     let (banks_client, payer, recent_blockhash) = pc.start().await;
 
     let mut transaction = Transaction::new_with_payer(
-        &[instruction::precise_sqrt_array(1000.0, 100.0, SqrtAlgorithm::Cordic)],
+        &[instruction::precise_sqrt_array(1000, 100, SqrtAlgorithm::Cordic)],
         Some(&payer.pubkey()),
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 533995);
+    let expected: Vec<f64> = (0..8).map(|i| (1000.0 + 100.0 * i as f64).sqrt()).collect();
+    assert_precise_d18_results(&result, &expected);
 }
 
 #[tokio::test]
@@ -143,9 +182,7 @@ async fn test_sqrt_u128() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    // assert_eq!(consumed_compute_units, 2905); // before improvement 2026-01-04
-    assert_eq!(consumed_compute_units, 2009);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::U128(u32::MAX as u128)));
 }
 
 #[tokio::test]
@@ -160,9 +197,7 @@ async fn test_sqrt_u128_max() {
         Transaction::new_with_payer(&[instruction::sqrt_u128(u128::MAX)], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    // assert_eq!(consumed_compute_units, 5678); // before improvement 2026-01-04
-    assert_eq!(consumed_compute_units, 3885);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::U128(u64::MAX as u128)));
 }
 
 #[tokio::test]
@@ -177,8 +212,27 @@ async fn test_muldiv_u64() {
         Transaction::new_with_payer(&[instruction::precise_muldiv(42, 84, 7)], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 3477);
+    assert_eq!(
+        parse_result_from_return_data(&result),
+        Some(MathResult::Precise(PreciseNumber::new(504).unwrap().value.0))
+    );
+}
+
+#[tokio::test]
+async fn test_muldiv_rounding() {
+    // the edge cases are covered by the unit tests of the rounding module
+    // 3 * 10^12 / 2^13 ends in a half at the 12 decimals of PreciseNumber
+    run_cases(&[
+        (
+            instruction::precise_muldiv_rounded(3, 1, 8_192, RoundingMode::Ceil),
+            MathResult::Precise(U256::from(366_210_938_u64).0),
+        ),
+        (
+            instruction::precise_muldiv_rounded(3, 1, 8_192, RoundingMode::TowardZero),
+            MathResult::Precise(U256::from(366_210_937_u64).0),
+        ),
+    ])
+    .await;
 }
 
 #[tokio::test]
@@ -193,8 +247,7 @@ async fn test_u64_multiply() {
         Transaction::new_with_payer(&[instruction::u64_multiply(42, 84)], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 7);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::U64(42 * 84)));
 }
 
 #[tokio::test]
@@ -209,8 +262,7 @@ async fn test_u64_divide() {
         Transaction::new_with_payer(&[instruction::u64_divide(3, 1)], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 8);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::U64(3)));
 }
 
 #[tokio::test]
@@ -227,8 +279,7 @@ async fn test_f32_multiply() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 73);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::F32(3.0)));
 }
 
 #[tokio::test]
@@ -245,8 +296,7 @@ async fn test_f32_divide() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 125);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::F32(2.0)));
 }
 
 #[tokio::test]
@@ -263,8 +313,10 @@ async fn test_f32_exponentiate() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 111);
+    match parse_result_from_return_data(&result) {
+        Some(MathResult::F32(value)) => assert!((value - 16.0).abs() <= 16.0 * f32::EPSILON),
+        other => panic!("unexpected result {:?}", other),
+    }
 }
 
 #[tokio::test]
@@ -281,8 +333,10 @@ async fn test_f32_natural_log() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 1958);
+    match parse_result_from_return_data(&result) {
+        Some(MathResult::F32(value)) => assert!((value - 1.0).abs() <= f32::EPSILON),
+        other => panic!("unexpected result {:?}", other),
+    }
 }
 
 #[tokio::test]
@@ -297,8 +351,10 @@ async fn test_f32_normal_cdf() {
         Transaction::new_with_payer(&[instruction::f32_normal_cdf(0_f32)], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 1471);
+    match parse_result_from_return_data(&result) {
+        Some(MathResult::F32(value)) => assert!((value - 0.5).abs() <= 1e-6),
+        other => panic!("unexpected result {:?}", other),
+    }
 }
 
 #[tokio::test]
@@ -315,9 +371,13 @@ async fn test_f64_pow() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    // not sure why this is 0
-    assert_eq!(consumed_compute_units, 0);
+    match parse_result_from_return_data(&result) {
+        Some(MathResult::F64Pow { powi, powf }) => {
+            assert_eq!(powi, 50_f64.powi(10));
+            assert!((powf / 50_f64.powf(10.5) - 1.0).abs() <= 1e-12);
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
 }
 
 #[tokio::test]
@@ -333,8 +393,11 @@ async fn test_u128_multiply() {
         Some(&payer.pubkey()),
     );
     transaction.sign(&[&payer], recent_blockhash);
-    banks_client.process_transaction(transaction).await.unwrap();
-    // TODO
+    let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+    assert_eq!(
+        parse_result_from_return_data(&result),
+        Some(MathResult::U128(u64::MAX as u128 * u64::MAX as u128))
+    );
 }
 
 #[tokio::test]
@@ -351,8 +414,7 @@ async fn test_u128_divide() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 349);
+    assert_eq!(parse_result_from_return_data(&result), Some(MathResult::U128(69)));
 }
 
 #[tokio::test]
@@ -369,8 +431,10 @@ async fn test_f64_multiply() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 125);
+    assert_eq!(
+        parse_result_from_return_data(&result),
+        Some(MathResult::F64(f64::powf(2., 42.) * 1e-4))
+    );
 }
 
 #[tokio::test]
@@ -387,31 +451,537 @@ async fn test_f64_divide() {
     );
     transaction.sign(&[&payer], recent_blockhash);
     let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
-    let consumed_compute_units = parse_compute_units_from_logs(&result).unwrap();
-    assert_eq!(consumed_compute_units, 201);
+    assert_eq!(
+        parse_result_from_return_data(&result),
+        Some(MathResult::F64(f64::powf(2., 42.) / 420420.6969))
+    );
 }
 
+#[tokio::test]
+async fn test_checked_ceil_div() {
+    // the edge cases are covered by the unit tests of the processor
+    run_cases(&[
+        (
+            instruction::checked_ceil_div(10, 3),
+            MathResult::CeilDiv {
+                quotient: 4,
+                divisor: 3,
+            },
+        ),
+        (
+            instruction::checked_ceil_div(7, 5),
+            MathResult::CeilDiv {
+                quotient: 2,
+                divisor: 4,
+            },
+        ),
+    ])
+    .await;
+}
 
-// e.g. Program log: cu_bench_consumed 149570
-fn parse_compute_units_from_logs(result: &BanksTransactionResultWithMetadata) -> Option<u64> {
+#[tokio::test]
+async fn test_precise_arithmetic() {
+    let precise = |value: u128| PreciseNumber::new(value).unwrap().value.0;
+    // 1.5 and 2.25 at the 12 decimals of PreciseNumber
+    let (lhs, rhs) = (
+        U256::from(1_500_000_000_000_u128).0,
+        U256::from(2_250_000_000_000_u128).0,
+    );
+    run_cases(&[
+        (
+            instruction::precise_arithmetic(PreciseOperation::Add { lhs, rhs }),
+            MathResult::Precise(U256::from(3_750_000_000_000_u128).0),
+        ),
+        (
+            instruction::precise_arithmetic(PreciseOperation::AlmostEq {
+                lhs,
+                rhs,
+                precision: precise(1),
+            }),
+            MathResult::Bool(true),
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_precise_d18_arithmetic() {
+    // the edge cases are covered by the unit tests of the processor
+    let inner = |value: &str| d18::from_decimal_str(value).unwrap().value.0;
+    run_cases(&[
+        (
+            instruction::precise_d18_arithmetic(D18Operation::FromDecimal {
+                value: "18446744073709551615.000000000000000001".to_string(),
+            }),
+            MathResult::Precise((U256::from(u64::MAX) * U256::from(d18::ONE) + U256::one()).0),
+        ),
+        (
+            instruction::precise_d18_arithmetic(D18Operation::DivCeil {
+                lhs: inner("2"),
+                rhs: inner("3"),
+            }),
+            MathResult::Precise(inner("0.666666666666666667")),
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_wide_arithmetic() {
+    // the edge cases are covered by the unit tests of the processor
+    let max = U256::from(u128::MAX);
+    run_cases(&[
+        (
+            instruction::wide_arithmetic(WideOperation::U256Multiply {
+                multiplicand: max.0,
+                multiplier: max.0,
+            }),
+            MathResult::U256((max * max).0),
+        ),
+        (
+            instruction::wide_arithmetic(WideOperation::U192Multiply {
+                multiplicand: [u64::MAX, 0, 0],
+                multiplier: [0, u64::MAX, 0],
+            }),
+            MathResult::U192([0, 1, u64::MAX - 1]),
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_integer_sqrt() {
+    // the edge cases are covered by the unit tests of the processor
+    run_cases(&[
+        (
+            instruction::integer_sqrt(UnsignedInteger::U64(u64::MAX)),
+            MathResult::Unsigned(UnsignedInteger::U64(u32::MAX as u64)),
+        ),
+        (
+            instruction::integer_sqrt(UnsignedInteger::U256(U256::MAX.0)),
+            MathResult::Unsigned(UnsignedInteger::U256(U256::from(u128::MAX).0)),
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_fixed_point_math() {
+    // the edge cases are covered by the unit tests of the processor
+    let inner = |value: &str| d18::from_decimal_str(value).unwrap().value.0;
+    run_cases(&[
+        (
+            instruction::fixed_point_math(
+                FixedPointType::PreciseNumber256D18,
+                FixedPointOperation::Exp {
+                    value: inner("1"),
+                    negative: false,
+                },
+            ),
+            MathResult::Precise(inner("2.718281828459045235")),
+        ),
+        (
+            instruction::fixed_point_math(
+                FixedPointType::PreciseNumber,
+                FixedPointOperation::Ln {
+                    value: PreciseNumber::new(10).unwrap().value.0,
+                },
+            ),
+            MathResult::SignedPrecise {
+                value: U256::from(2_302_585_092_994u128).0,
+                negative: false,
+            },
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_normal_distribution() {
+    // the edge cases are covered by the unit tests of the processor
+    let inner = |value: u128| U256::from(value).0;
+    run_cases(&[
+        (
+            instruction::normal_distribution(NormalOperation::Cdf {
+                argument: inner(1_960_000_000_000),
+                negative: false,
+            }),
+            MathResult::Precise(inner(975_002_104_852)),
+        ),
+        (
+            instruction::normal_distribution(NormalOperation::InverseCdf {
+                probability: inner(975_000_000_000),
+            }),
+            MathResult::SignedPrecise {
+                value: inner(1_959_963_984_540),
+                negative: false,
+            },
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_black_scholes() {
+    // the f64 valuation and the edge cases are covered by the unit tests of
+    // the processor
+    // spot 100, strike 100, 5%, 20%, one year
+    let inner = |value: u128| U256::from(value).0;
+    let inputs = BlackScholesInputs::Precise {
+        spot: inner(100_000_000_000_000),
+        strike: inner(100_000_000_000_000),
+        rate: inner(50_000_000_000),
+        volatility: inner(200_000_000_000),
+        time: inner(1_000_000_000_000),
+    };
+    run_cases(&[
+        (
+            instruction::black_scholes(OptionKind::Call, inputs.clone()),
+            MathResult::BlackScholesPrecise {
+                price: inner(10_450_583_572_186),
+                delta: inner(636_830_651_176),
+                vega: inner(37_524_034_691_694),
+            },
+        ),
+        (
+            instruction::black_scholes(OptionKind::Put, inputs),
+            MathResult::BlackScholesPrecise {
+                price: inner(5_573_526_022_257),
+                delta: inner(363_169_348_824),
+                vega: inner(37_524_034_691_694),
+            },
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_curve() {
+    // the edge cases are covered by the unit tests of the processor
+    run_cases(&[
+        (
+            instruction::curve(CurveOperation::SwapExactIn {
+                amount_in: 1_000,
+                reserve_in: 1_000_000,
+                reserve_out: 1_000_000,
+                fee: TradeFee {
+                    numerator: 3,
+                    denominator: 1000,
+                },
+            }),
+            MathResult::Swap {
+                amount_in: 1_000,
+                amount_out: 996,
+                fee: 3,
+            },
+        ),
+        (
+            instruction::curve(CurveOperation::Deposit {
+                pool_tokens: 10,
+                pool_supply: 100,
+                reserve_a: 1_001,
+                reserve_b: 333,
+            }),
+            MathResult::TokenPair {
+                token_a: 101,
+                token_b: 34,
+            },
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_stable_swap() {
+    // the edge cases are covered by the unit tests of the processor
+    run_cases(&[
+        (
+            instruction::stable_swap(StableSwapOperation::ComputeD {
+                amp: 100,
+                balances: vec![1_000_000_000_000_000_000_000, 10_000_000_000_000_000_000],
+                max_iterations: 255,
+            }),
+            MathResult::Iterated {
+                value: 957_524_479_083_543_226_063,
+                iterations: 5,
+            },
+        ),
+        (
+            instruction::stable_swap(StableSwapOperation::GetY {
+                amp: 200,
+                balances: vec![1_000_000_000_000, 2_000_000_000_000, 3_000_000_000_000],
+                i: 0,
+                j: 1,
+                x: 1_000_001_000_000,
+                max_iterations: 255,
+            }),
+            MathResult::Iterated {
+                value: 1_999_998_999_260,
+                iterations: 8,
+            },
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_weighted_pool() {
+    // the edge cases are covered by the unit tests of the processor
+    run_cases(&[
+        (
+            instruction::weighted_pool(WeightedOperation::OutGivenIn {
+                balance_in: 1_000_000,
+                weight_in: 80,
+                balance_out: 1_000_000,
+                weight_out: 20,
+                amount_in: 1_000,
+            }),
+            MathResult::U64(3_990),
+        ),
+        (
+            instruction::weighted_pool(WeightedOperation::InGivenOut {
+                balance_in: 1_000_000,
+                weight_in: 20,
+                balance_out: 1_000_000,
+                weight_out: 80,
+                amount_out: 900_000,
+            }),
+            MathResult::U64(9_999_000_001),
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_compound_interest() {
+    // the edge cases are covered by the unit tests of the processor
+    let inner = |value: u128| U256::from(value).0;
+    run_cases(&[
+        // (1 + 0.1 / 78_840_000)^78_840_000 = 1.10517091800555814503...
+        (
+            instruction::compound_interest(InterestOperation::Accrue {
+                apr: inner(100_000_000_000),
+                years: 1,
+                compounding: Compounding::PerSlot,
+                round_up: false,
+            }),
+            MathResult::Precise(inner(1_105_170_918_005)),
+        ),
+        // e^0.05 = 1.051271096376024039697...
+        (
+            instruction::compound_interest(InterestOperation::Accrue {
+                apr: inner(50_000_000_000),
+                years: 1,
+                compounding: Compounding::Continuous,
+                round_up: true,
+            }),
+            MathResult::Precise(inner(1_051_271_096_377)),
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_signed_arithmetic() {
+    // the edge cases are covered by the unit tests of the processor
+    // 1.5 and -2.25, at 12 decimals and at 18
+    let precise = |value: u128, negative| SignedValue {
+        value: U256::from(value).0,
+        negative,
+    };
+    let decimal = |value: &str, negative| SignedValue {
+        value: d18::from_decimal_str(value).unwrap().value.0,
+        negative,
+    };
+    run_cases(&[
+        (
+            instruction::signed_arithmetic(
+                FixedPointType::PreciseNumber,
+                SignedOperation::Add {
+                    lhs: precise(1_500_000_000_000, false),
+                    rhs: precise(2_250_000_000_000, true),
+                },
+            ),
+            MathResult::SignedPrecise {
+                value: U256::from(750_000_000_000_u128).0,
+                negative: true,
+            },
+        ),
+        (
+            instruction::signed_arithmetic(
+                FixedPointType::PreciseNumber256D18,
+                SignedOperation::Mul {
+                    lhs: decimal("1.5", false),
+                    rhs: decimal("2.25", true),
+                },
+            ),
+            MathResult::SignedPrecise {
+                value: decimal("3.375", false).value,
+                negative: true,
+            },
+        ),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn test_sweep_sqrt_u64() {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(1_000_000);
+
+    let (banks_client, payer, recent_blockhash) = pc.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::sweep(
+            SweepOperation::SquareRootU64,
+            SweepInputs::Geometric {
+                start: 1,
+                ratio: 2,
+                count: 32,
+            },
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+
+    let Some(MathResult::Sweep(points)) = parse_result_from_return_data(&result) else {
+        panic!("expected sweep points");
+    };
+    let inputs: Vec<u128> = points.iter().map(|point| point.input).collect();
+    assert_eq!(inputs, (0..32).map(|i| 1 << i).collect::<Vec<u128>>());
+
+    // one measurement logged per input, matching the returned points
     let logs = &result.metadata.as_ref().unwrap().log_messages;
+    let consumed: Vec<u64> = logs
+        .iter()
+        .filter_map(|log| BenchRecord::parse(log))
+        .map(|record| record.consumed)
+        .collect();
+    assert_eq!(
+        consumed,
+        points.iter().map(|point| point.compute_units).collect::<Vec<u64>>()
+    );
+}
 
-    for log in logs {
-        // only one
-        if log.starts_with("Program log: cu_bench_consumed ") {
-            let parts: Vec<&str> = log.split("cu_bench_consumed").collect();
-            if let Some(units_str) = parts.get(1) {
-                if let Ok(units) = units_str.trim().parse::<u64>() {
-                    match units.checked_sub(CU_CORRECTION) {
-                        Some(corrected_units) => return Some(corrected_units),
-                        None => {
-                            println!("Compute units underflow after correction");
-                            return Some(0)
-                        },
-                    }
-                }
-            }
-        }
+#[tokio::test]
+async fn test_batch_u64_multiply() {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(1_000_000);
+
+    let (banks_client, payer, recent_blockhash) = pc.start().await;
+
+    let inputs: Vec<(u64, u64)> = (1..=16).map(|i| (i, 42)).collect();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::batch(BatchOperation::U64Multiply(inputs))],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+
+    assert_eq!(
+        parse_result_from_return_data(&result),
+        Some(MathResult::U64Array((1..=16).map(|i| i * 42).collect()))
+    );
+    let logs = &result.metadata.as_ref().unwrap().log_messages;
+    let record = logs.iter().find_map(|log| BenchRecord::parse(log)).unwrap();
+    assert_eq!(record.label, "batch_u64_multiply");
+    assert_eq!(record.count, 16);
+    assert_eq!(record.per_op(), record.consumed / 16);
+}
+
+#[tokio::test]
+async fn test_batch_f64_pow() {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(1_000_000);
+
+    let (banks_client, payer, recent_blockhash) = pc.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::batch(BatchOperation::F64Pow(vec![(50.0, 10.5), (2.0, 3.0)]))],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+
+    assert_eq!(
+        parse_result_from_return_data(&result),
+        Some(MathResult::F64PowArray(vec![
+            (50_f64.powi(10), 50_f64.powf(10.5)),
+            (8.0, 8.0)
+        ]))
+    );
+}
+
+#[cfg(feature = "custom-heap")]
+#[tokio::test]
+async fn test_heap_usage_reported() {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(5_000_000);
+
+    let (banks_client, payer, recent_blockhash) = pc.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::precise_sqrt_array(1000, 100, SqrtAlgorithm::Newton)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+    let logs = &result.metadata.as_ref().unwrap().log_messages;
+    let record = logs.iter().find_map(|log| BenchRecord::parse(log)).unwrap();
+    let heap = record.heap.unwrap();
+    // the eight roots of the array, as `Vec<PreciseNumber256D18>`
+    let root_size = size_of::<spl_math_example::precise_number::PreciseNumber256D18>() as u64;
+    assert!(heap.allocations >= 1);
+    assert!(heap.allocated >= 8 * root_size);
+    assert!(heap.peak >= heap.allocated);
+}
+
+/// Process every instruction in its own transaction and check the result it
+/// returns
+async fn run_cases(cases: &[(Instruction, MathResult)]) {
+    let mut pc = ProgramTest::new("spl_math_example", id(), processor!(process_instruction));
+
+    pc.set_compute_max_units(1_000_000);
+
+    let (mut banks_client, payer, _) = pc.start().await;
+
+    for (instruction, expected) in cases {
+        let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+        let mut transaction =
+            Transaction::new_with_payer(&[instruction.clone()], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            parse_result_from_return_data(&result).as_ref(),
+            Some(expected),
+            "{:?}",
+            instruction.data
+        );
+    }
+}
+
+fn parse_result_from_return_data(result: &BanksTransactionResultWithMetadata) -> Option<MathResult> {
+    let return_data = result.metadata.as_ref()?.return_data.as_ref()?;
+    MathResult::from_return_data(&return_data.program_id, &return_data.data)
+}
+
+// PreciseNumber256D18 carries 18 decimals; square roots are compared with a relative tolerance
+fn assert_precise_d18_results(result: &BanksTransactionResultWithMetadata, expected: &[f64]) {
+    let Some(MathResult::PreciseArray(values)) = parse_result_from_return_data(result) else {
+        panic!("expected an array of precise numbers");
+    };
+    assert_eq!(values.len(), expected.len());
+    for (value, expected) in values.iter().zip(expected) {
+        let value = U256(*value).as_u128() as f64 / 1e18;
+        assert!((value / expected - 1.0).abs() <= 1e-9, "{} != {}", value, expected);
     }
-    None
 }